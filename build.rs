@@ -0,0 +1,142 @@
+use std::{collections::HashMap, env, fmt::Write, fs, path::Path};
+
+/*
+    Check the layout of src/packet_h_bind.rs against bind_src/packets.h at build time.
+
+    The packed sizes and member offsets of the structs in the header are computed from the member types,
+    and written as const assertions to $OUT_DIR/packets_h_layout.rs, which packet_h_bind includes.
+    So the build fails when the header and the hand written structs drift apart.
+
+    The rust names follow bindgen: a nested struct is named Outer_Inner, the base struct is the member _base,
+    the bitfields are grouped into _bitfield_1, _bitfield_2..., and type is type_.
+    Good enough for the simple members in the header, not a c++ parser.
+*/
+const HEADER: &str = "bind_src/packets.h";
+
+struct Struct {
+    name: String,
+    bytes: usize,
+    bits: usize,
+    bitfields: usize, // the bitfield groups so far
+}
+
+fn flush_bits(current: &mut Struct) {
+    current.bytes += current.bits.div_ceil(8);
+    current.bits = 0;
+}
+
+fn type_size(sizes: &HashMap<String, usize>, outer: Option<&str>, name: &str) -> usize {
+    match name {
+        "bool" | "uint8_t" | "int8_t" | "Type" | "Resolution" | "WIFI_Rate" => 1,
+        "uint16_t" | "int16_t" => 2,
+        "uint32_t" | "int32_t" => 4,
+        _ => *outer
+            .and_then(|x| sizes.get(&format!("{}_{}", x, name)))
+            .or_else(|| sizes.get(name))
+            .unwrap_or_else(|| panic!("{}: unknown member type:{}", HEADER, name)),
+    }
+}
+
+fn rust_member(name: &str) -> &str {
+    match name {
+        "type" => "type_",
+        _ => name,
+    }
+}
+
+/*
+    return the assertions of the sizes and offsets of all the structs in the source.
+*/
+fn layout_assertions(source: &str) -> String {
+    let mut sizes = HashMap::new();
+    let mut stack: Vec<Struct> = Vec::new();
+    let mut in_enum = false;
+    let mut out = String::new();
+    let mut assert = |cond: String, what: String| {
+        writeln!(
+            out,
+            "const _: () = assert!({}, \"{} differs from {}\");",
+            cond, what, HEADER
+        )
+        .unwrap();
+    };
+    for line in source.lines() {
+        let line = line.split("//").next().unwrap().trim();
+        if in_enum {
+            in_enum = !line.starts_with('}');
+            continue;
+        }
+        if line.starts_with("enum") {
+            in_enum = !line.ends_with(';');
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("struct ") {
+            let mut parts = rest.split(':').map(str::trim);
+            let short_name = parts.next().unwrap();
+            let name = match stack.last() {
+                Some(outer) => format!("{}_{}", outer.name, short_name),
+                None => short_name.to_string(),
+            };
+            let base = parts.next().map(|x| sizes[x]).unwrap_or(0);
+            stack.push(Struct {
+                name,
+                bytes: base,
+                bits: 0,
+                bitfields: 0,
+            });
+            continue;
+        }
+        if line.starts_with("};") {
+            if let Some(mut current) = stack.pop() {
+                flush_bits(&mut current);
+                assert(
+                    format!("size_of::<{}>() == {}", current.name, current.bytes),
+                    format!("the size of {}", current.name),
+                );
+                sizes.insert(current.name, current.bytes);
+            }
+            continue;
+        }
+        let outer = stack.last().map(|x| x.name.clone());
+        let Some(current) = stack.last_mut() else {
+            continue;
+        };
+        let member = line.split(['=', ';']).next().unwrap();
+        let mut words = member.split_whitespace();
+        let (Some(ty), Some(name)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let name = name.split(':').next().unwrap();
+        let (field, offset) = match member.split(':').nth(1) {
+            Some(bits) => {
+                let bits: usize = bits.trim().parse().unwrap();
+                let new_group = current.bits == 0;
+                current.bits += bits;
+                if !new_group {
+                    continue;
+                }
+                current.bitfields += 1;
+                (format!("_bitfield_{}", current.bitfields), current.bytes)
+            }
+            None => {
+                flush_bits(current);
+                let offset = current.bytes;
+                current.bytes += type_size(&sizes, outer.as_deref(), ty);
+                (rust_member(name).to_string(), offset)
+            }
+        };
+        assert(
+            format!("offset_of!({}, {}) == {}", current.name, field, offset),
+            format!("the offset of {}::{}", current.name, field),
+        );
+    }
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", HEADER);
+    println!("cargo:rerun-if-changed=build.rs");
+    let source = fs::read_to_string(HEADER).unwrap_or_else(|e| panic!("{}: {}", HEADER, e));
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("packets_h_layout.rs");
+    fs::write(out, layout_assertions(&source)).unwrap();
+}
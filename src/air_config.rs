@@ -0,0 +1,551 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::codec::Codec;
+use crate::packet_h_bind::{
+    Ground2Air_Config_Packet, Ground2Air_Config_Packet_Camera, Ground2Air_Header,
+    Ground2Air_Header_Type_Config, AIR2GROUND_MTU,
+};
+
+/*
+    Typed and validated config of the air unit.
+    The ranges are from the comments of Ground2Air_Config_Packet in packets.h.
+
+    let config = AirConfig::builder().resolution(Resolution::Vga).quality(12).build()?;
+    let packet = config.to_packet(); // size and crc are always filled
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    OutOfRange {
+        field: &'static str,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    InvalidEnum {
+        field: &'static str,
+        value: String,
+    },
+    InvalidFec {
+        k: u8,
+        n: u8,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } => write!(f, "{} out of range {}..={}: {}", field, min, max, value),
+            ConfigError::InvalidEnum { field, value } => write!(f, "invalid {}: {}", field, value),
+            ConfigError::InvalidFec { k, n } => {
+                write!(f, "invalid fec k:{} n:{}, should be 0 < k < n <= {}", k, n, FEC_N_MAX)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+pub const FEC_N_MAX: u8 = 32;
+
+/*
+    enum with the same values as the one in packets.h,
+    (de)serialized by the name in packets.h, or the raw value.
+*/
+macro_rules! c_enum {
+    ($(#[$meta:meta])* $name:ident, $field:expr, { $($variant:ident = $value:expr, $c_name:expr;)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(u8)]
+        pub enum $name {
+            $($variant = $value,)*
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $c_name,)*
+                }
+            }
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = ConfigError;
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok($name::$variant),)*
+                    _ => Err(ConfigError::InvalidEnum {
+                        field: $field,
+                        value: value.to_string(),
+                    }),
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(value: $name) -> u8 {
+                value as u8
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ConfigError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if let Ok(value) = s.parse::<u8>() {
+                    return $name::try_from(value);
+                }
+                $name::ALL
+                    .iter()
+                    .find(|x| x.name().eq_ignore_ascii_case(s))
+                    .copied()
+                    .ok_or(ConfigError::InvalidEnum {
+                        field: $field,
+                        value: s.to_string(),
+                    })
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.name())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.name())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #[derive(Deserialize)]
+                #[serde(untagged)]
+                enum Raw {
+                    Value(u8),
+                    Name(String),
+                }
+                let ret = match Raw::deserialize(deserializer)? {
+                    Raw::Value(value) => $name::try_from(value),
+                    Raw::Name(name) => name.parse(),
+                };
+                ret.map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+c_enum!(Resolution, "resolution", {
+    Qvga = 0, "QVGA";
+    Cif = 1, "CIF";
+    Hvga = 2, "HVGA";
+    Vga = 3, "VGA";
+    Svga = 4, "SVGA";
+    Xga = 5, "XGA";
+    Sxga = 6, "SXGA";
+    Uxga = 7, "UXGA";
+});
+
+impl Resolution {
+    pub fn dimensions(&self) -> (u16, u16) {
+        match self {
+            Resolution::Qvga => (320, 240),
+            Resolution::Cif => (400, 296),
+            Resolution::Hvga => (480, 320),
+            Resolution::Vga => (640, 480),
+            Resolution::Svga => (800, 600),
+            Resolution::Xga => (1024, 768),
+            Resolution::Sxga => (1280, 1024),
+            Resolution::Uxga => (1600, 1200),
+        }
+    }
+}
+
+c_enum!(WifiRate, "wifi_rate", {
+    B2mCck = 0, "RATE_B_2M_CCK";
+    B2mCckS = 1, "RATE_B_2M_CCK_S";
+    B5_5mCck = 2, "RATE_B_5_5M_CCK";
+    B5_5mCckS = 3, "RATE_B_5_5M_CCK_S";
+    B11mCck = 4, "RATE_B_11M_CCK";
+    B11mCckS = 5, "RATE_B_11M_CCK_S";
+    G6mOdfm = 6, "RATE_G_6M_ODFM";
+    G9mOdfm = 7, "RATE_G_9M_ODFM";
+    G12mOdfm = 8, "RATE_G_12M_ODFM";
+    G18mOdfm = 9, "RATE_G_18M_ODFM";
+    G24mOdfm = 10, "RATE_G_24M_ODFM";
+    G36mOdfm = 11, "RATE_G_36M_ODFM";
+    G48mOdfm = 12, "RATE_G_48M_ODFM";
+    G54mOdfm = 13, "RATE_G_54M_ODFM";
+    N6_5mMcs0 = 14, "RATE_N_6_5M_MCS0";
+    N7_2mMcs0S = 15, "RATE_N_7_2M_MCS0_S";
+    N13mMcs1 = 16, "RATE_N_13M_MCS1";
+    N14_4mMcs1S = 17, "RATE_N_14_4M_MCS1_S";
+    N19_5mMcs2 = 18, "RATE_N_19_5M_MCS2";
+    N21_7mMcs2S = 19, "RATE_N_21_7M_MCS2_S";
+    N26mMcs3 = 20, "RATE_N_26M_MCS3";
+    N28_9mMcs3S = 21, "RATE_N_28_9M_MCS3_S";
+    N39mMcs4 = 22, "RATE_N_39M_MCS4";
+    N43_3mMcs4S = 23, "RATE_N_43_3M_MCS4_S";
+    N52mMcs5 = 24, "RATE_N_52M_MCS5";
+    N57_8mMcs5S = 25, "RATE_N_57_8M_MCS5_S";
+    N58mMcs6 = 26, "RATE_N_58M_MCS6";
+    N65mMcs6S = 27, "RATE_N_65M_MCS6_S";
+    N65mMcs7 = 28, "RATE_N_65M_MCS7";
+    N72mMcs7S = 29, "RATE_N_72M_MCS7_S";
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraConfig {
+    pub resolution: Resolution,
+    pub fps_limit: u8,
+    pub quality: u8,
+    pub brightness: i8,
+    pub contrast: i8,
+    pub saturation: i8,
+    pub sharpness: i8,
+    pub denoise: u8,
+    pub special_effect: u8,
+    pub awb: bool,
+    pub awb_gain: bool,
+    pub wb_mode: u8,
+    pub aec: bool,
+    pub aec2: bool,
+    pub ae_level: i8,
+    pub aec_value: u16,
+    pub agc: bool,
+    pub agc_gain: u8,
+    pub gainceiling: u8,
+    pub bpc: bool,
+    pub wpc: bool,
+    pub raw_gma: bool,
+    pub lenc: bool,
+    pub hmirror: bool,
+    pub vflip: bool,
+    pub dcw: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AirConfig {
+    pub wifi_power: i8, // dBm
+    pub wifi_rate: WifiRate,
+    pub fec_codec_k: u8,
+    pub fec_codec_n: u8,
+    pub fec_codec_mtu: u16,
+    pub dvr_record: bool,
+    pub camera: CameraConfig,
+}
+
+fn check_range<T: Into<i64> + Copy>(
+    field: &'static str,
+    value: T,
+    min: T,
+    max: T,
+) -> Result<(), ConfigError> {
+    let (value, min, max) = (value.into(), min.into(), max.into());
+    if value < min || value > max {
+        return Err(ConfigError::OutOfRange {
+            field,
+            value,
+            min,
+            max,
+        });
+    }
+    Ok(())
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig::try_from(&Ground2Air_Config_Packet_Camera::default()).unwrap()
+    }
+}
+
+impl CameraConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_range("quality", self.quality, 0, 63)?;
+        check_range("brightness", self.brightness, -2, 2)?;
+        check_range("contrast", self.contrast, -2, 2)?;
+        check_range("saturation", self.saturation, -2, 2)?;
+        check_range("sharpness", self.sharpness, -1, 6)?;
+        check_range("special_effect", self.special_effect, 0, 6)?;
+        check_range("wb_mode", self.wb_mode, 0, 4)?;
+        check_range("ae_level", self.ae_level, -2, 2)?;
+        check_range("aec_value", self.aec_value, 0, 1200)?;
+        check_range("agc_gain", self.agc_gain, 0, 30)?;
+        check_range("gainceiling", self.gainceiling, 0, 6)?;
+        Ok(())
+    }
+}
+
+macro_rules! copy_camera_fields {
+    ($from:expr, $to:ident { resolution: $resolution:expr }) => {
+        $to {
+            resolution: $resolution,
+            fps_limit: $from.fps_limit,
+            quality: $from.quality,
+            brightness: $from.brightness,
+            contrast: $from.contrast,
+            saturation: $from.saturation,
+            sharpness: $from.sharpness,
+            denoise: $from.denoise,
+            special_effect: $from.special_effect,
+            awb: $from.awb,
+            awb_gain: $from.awb_gain,
+            wb_mode: $from.wb_mode,
+            aec: $from.aec,
+            aec2: $from.aec2,
+            ae_level: $from.ae_level,
+            aec_value: $from.aec_value,
+            agc: $from.agc,
+            agc_gain: $from.agc_gain,
+            gainceiling: $from.gainceiling,
+            bpc: $from.bpc,
+            wpc: $from.wpc,
+            raw_gma: $from.raw_gma,
+            lenc: $from.lenc,
+            hmirror: $from.hmirror,
+            vflip: $from.vflip,
+            dcw: $from.dcw,
+        }
+    };
+}
+
+impl TryFrom<&Ground2Air_Config_Packet_Camera> for CameraConfig {
+    type Error = ConfigError;
+
+    fn try_from(camera: &Ground2Air_Config_Packet_Camera) -> Result<Self, Self::Error> {
+        let camera = *camera; // the packed struct could not be borrowed by fields
+        let ret = copy_camera_fields!(camera, CameraConfig {
+            resolution: camera.resolution.try_into()?
+        });
+        ret.validate()?;
+        Ok(ret)
+    }
+}
+
+impl From<&CameraConfig> for Ground2Air_Config_Packet_Camera {
+    fn from(camera: &CameraConfig) -> Self {
+        copy_camera_fields!(camera, Ground2Air_Config_Packet_Camera {
+            resolution: camera.resolution.into()
+        })
+    }
+}
+
+impl Default for AirConfig {
+    fn default() -> Self {
+        AirConfig::try_from(&Ground2Air_Config_Packet::default()).unwrap()
+    }
+}
+
+impl AirConfig {
+    pub fn builder() -> AirConfigBuilder {
+        AirConfigBuilder {
+            config: AirConfig::default(),
+        }
+    }
+
+    pub fn to_builder(&self) -> AirConfigBuilder {
+        AirConfigBuilder { config: *self }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check_range("wifi_power", self.wifi_power, 0, 20)?;
+        if self.fec_codec_k == 0
+            || self.fec_codec_n <= self.fec_codec_k
+            || self.fec_codec_n > FEC_N_MAX
+        {
+            return Err(ConfigError::InvalidFec {
+                k: self.fec_codec_k,
+                n: self.fec_codec_n,
+            });
+        }
+        check_range("fec_codec_mtu", self.fec_codec_mtu, 1, AIR2GROUND_MTU as u16)?;
+        self.camera.validate()
+    }
+
+    /*
+        the packet to be injected, with the size and crc filled.
+        call validate() first if the fields are changed directly instead of by the builder.
+    */
+    pub fn to_packet(&self) -> Ground2Air_Config_Packet {
+        let mut ret = Ground2Air_Config_Packet {
+            _base: Ground2Air_Header {
+                type_: Ground2Air_Header_Type_Config,
+                size: Ground2Air_Config_Packet::SIZE as u32,
+                crc: 0,
+            },
+            ping: 0,
+            wifi_power: self.wifi_power,
+            wifi_rate: self.wifi_rate.into(),
+            fec_codec_k: self.fec_codec_k,
+            fec_codec_n: self.fec_codec_n,
+            fec_codec_mtu: self.fec_codec_mtu,
+            dvr_record: self.dvr_record,
+            camera: (&self.camera).into(),
+        };
+        ret.update_crc();
+        ret
+    }
+}
+
+impl TryFrom<&Ground2Air_Config_Packet> for AirConfig {
+    type Error = ConfigError;
+
+    fn try_from(packet: &Ground2Air_Config_Packet) -> Result<Self, Self::Error> {
+        let packet = *packet;
+        let ret = AirConfig {
+            wifi_power: packet.wifi_power,
+            wifi_rate: packet.wifi_rate.try_into()?,
+            fec_codec_k: packet.fec_codec_k,
+            fec_codec_n: packet.fec_codec_n,
+            fec_codec_mtu: packet.fec_codec_mtu,
+            dvr_record: packet.dvr_record,
+            camera: (&packet.camera).try_into()?,
+        };
+        ret.validate()?;
+        Ok(ret)
+    }
+}
+
+macro_rules! builder_setters {
+    ($sub:ident => $($field:ident: $type:ty),*) => {
+        $(
+            pub fn $field(mut self, value: $type) -> Self {
+                self.config.$sub.$field = value;
+                self
+            }
+        )*
+    };
+    ($($field:ident: $type:ty),*) => {
+        $(
+            pub fn $field(mut self, value: $type) -> Self {
+                self.config.$field = value;
+                self
+            }
+        )*
+    };
+}
+
+pub struct AirConfigBuilder {
+    config: AirConfig,
+}
+
+impl AirConfigBuilder {
+    builder_setters!(
+        wifi_power: i8, wifi_rate: WifiRate, dvr_record: bool, fec_codec_mtu: u16
+    );
+
+    builder_setters!(camera => resolution: Resolution, fps_limit: u8, quality: u8,
+        brightness: i8, contrast: i8, saturation: i8, sharpness: i8, denoise: u8,
+        special_effect: u8, awb: bool, awb_gain: bool, wb_mode: u8, aec: bool, aec2: bool,
+        ae_level: i8, aec_value: u16, agc: bool, agc_gain: u8, gainceiling: u8, bpc: bool,
+        wpc: bool, raw_gma: bool, lenc: bool, hmirror: bool, vflip: bool, dcw: bool
+    );
+
+    pub fn fec(mut self, k: u8, n: u8) -> Self {
+        self.config.fec_codec_k = k;
+        self.config.fec_codec_n = n;
+        self
+    }
+
+    pub fn camera(mut self, camera: CameraConfig) -> Self {
+        self.config.camera = camera;
+        self
+    }
+
+    pub fn build(self) -> Result<AirConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use crate::{
+            air_config::*,
+            packet_h_bind::{Ground2Air_Config_Packet, Resolution_QVGA, WIFI_Rate_RATE_G_48M_ODFM},
+        };
+
+        #[test]
+        fn test_builder() {
+            let config = AirConfig::builder()
+                .resolution(Resolution::Vga)
+                .quality(12)
+                .wifi_rate(WifiRate::G24mOdfm)
+                .fec(4, 6)
+                .build()
+                .unwrap();
+            let packet = config.to_packet();
+            assert_eq!({ packet.camera.resolution }, Resolution::Vga as u8);
+            assert_eq!({ packet.camera.quality }, 12);
+            assert_eq!({ packet.fec_codec_k }, 4);
+
+            let mut packet_check = packet;
+            packet_check.update_crc();
+            assert_eq!({ packet._base.crc }, { packet_check._base.crc });
+            assert_eq!(
+                { packet._base.size },
+                Ground2Air_Config_Packet::SIZE as u32
+            );
+            assert_eq!(AirConfig::try_from(&packet).unwrap(), config);
+        }
+
+        #[test]
+        fn test_default_same_as_packet() {
+            let packet = AirConfig::default().to_packet();
+            let default_packet = Ground2Air_Config_Packet::default();
+            assert_eq!({ packet.camera.resolution }, Resolution_QVGA);
+            assert_eq!({ packet.wifi_rate }, WIFI_Rate_RATE_G_48M_ODFM);
+            assert_eq!({ packet._base.crc }, { default_packet._base.crc });
+        }
+
+        #[test]
+        fn test_validate() {
+            let err = AirConfig::builder().brightness(3).build().unwrap_err();
+            assert_eq!(err.to_string(), "brightness out of range -2..=2: 3");
+            assert_eq!(
+                AirConfig::builder().aec_value(1201).build().unwrap_err(),
+                ConfigError::OutOfRange {
+                    field: "aec_value",
+                    value: 1201,
+                    min: 0,
+                    max: 1200
+                }
+            );
+            assert_eq!(
+                AirConfig::builder().fec(3, 3).build().unwrap_err(),
+                ConfigError::InvalidFec { k: 3, n: 3 }
+            );
+            assert!(Resolution::try_from(8).is_err());
+        }
+
+        #[test]
+        fn test_enum_names() {
+            assert_eq!("VGA".parse(), Ok(Resolution::Vga));
+            assert_eq!("vga".parse(), Ok(Resolution::Vga));
+            assert_eq!("3".parse(), Ok(Resolution::Vga));
+            assert_eq!("RATE_N_6_5M_MCS0".parse(), Ok(WifiRate::N6_5mMcs0));
+            assert_eq!(
+                serde_json::from_str::<WifiRate>("12").unwrap(),
+                WifiRate::G48mOdfm
+            );
+            assert_eq!(
+                serde_json::to_string(&WifiRate::G48mOdfm).unwrap(),
+                "\"RATE_G_48M_ODFM\""
+            );
+        }
+    }
+}
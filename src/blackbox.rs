@@ -0,0 +1,378 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::ConnectStats;
+
+/*
+    Blackbox of the raw captured packets, before the fec decoding, to find out what happened in the seconds
+    before the video froze. The packets of the last window are kept in memory, and dumped to a pcap file
+    (linktype 802.11 + radiotap, readable by wireshark, or replayed by the tests) when:
+        the link is lost(refer events::LinkMonitor),
+        the decode errors(lost blocks, crc errors, jpeg errors) in a second reach the threshold,
+        or on demand by the dump_blackbox command of the control port.
+    The automatic dumps are at most one per cooldown, so a bad link doesn't fill the disk.
+    The dumps are written by a worker thread(refer BlackboxDumper::spawn), the capture only waits for
+    the packets of the ring being shared, not copied.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPacket {
+    pub ts: Duration, // since the unix epoch, of the pcap header
+    pub orig_len: u32,
+    pub data: Vec<u8>,
+}
+
+pub const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+pub const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+const SNAPLEN: u32 = 65535;
+
+pub struct Blackbox {
+    window: Duration,
+    max_bytes: usize,
+    packets: VecDeque<Arc<RawPacket>>,
+    bytes: usize,
+}
+
+impl Blackbox {
+    pub fn new(window: Duration, max_bytes: usize) -> Self {
+        Blackbox {
+            window,
+            max_bytes,
+            packets: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+
+    /*
+        the packets older than the window of the newest one are dropped, or the oldest ones beyond max_bytes.
+    */
+    pub fn push(&mut self, ts: Duration, orig_len: u32, data: &[u8]) {
+        self.bytes += data.len();
+        self.packets.push_back(Arc::new(RawPacket {
+            ts,
+            orig_len,
+            data: data.to_vec(),
+        }));
+        while let Some(oldest) = self.packets.front() {
+            if self.bytes <= self.max_bytes && ts.saturating_sub(oldest.ts) <= self.window {
+                break;
+            }
+            self.bytes -= oldest.data.len();
+            self.packets.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /*
+        the packets are shared with the ring, so it's cheap under the lock.
+    */
+    pub fn snapshot(&self) -> Vec<Arc<RawPacket>> {
+        self.packets.iter().cloned().collect()
+    }
+}
+
+/*
+    the classic pcap format, in the native byte order as libpcap writes.
+*/
+pub fn write_pcap<W: Write>(writer: &mut W, packets: &[Arc<RawPacket>]) -> io::Result<()> {
+    writer.write_all(&PCAP_MAGIC.to_ne_bytes())?;
+    writer.write_all(&2u16.to_ne_bytes())?; // version 2.4
+    writer.write_all(&4u16.to_ne_bytes())?;
+    writer.write_all(&0i32.to_ne_bytes())?; // thiszone
+    writer.write_all(&0u32.to_ne_bytes())?; // sigfigs
+    writer.write_all(&SNAPLEN.to_ne_bytes())?;
+    writer.write_all(&LINKTYPE_IEEE802_11_RADIOTAP.to_ne_bytes())?;
+    for packet in packets {
+        writer.write_all(&(packet.ts.as_secs() as u32).to_ne_bytes())?;
+        writer.write_all(&packet.ts.subsec_micros().to_ne_bytes())?;
+        writer.write_all(&(packet.data.len() as u32).to_ne_bytes())?;
+        writer.write_all(&packet.orig_len.to_ne_bytes())?;
+        writer.write_all(&packet.data)?;
+    }
+    writer.flush()
+}
+
+/*
+    dump the blackbox into the directory, the files are named by the session, the sequence and the reason,
+    e.g. blackbox_1760000000_003_link_lost.pcap
+*/
+pub struct BlackboxDumper {
+    blackbox: Arc<Mutex<Blackbox>>,
+    dir: PathBuf,
+    session: String,
+    cooldown: Duration,
+    last_auto_dump: Option<Instant>,
+    count: u32,
+}
+
+impl BlackboxDumper {
+    pub fn new(
+        blackbox: Arc<Mutex<Blackbox>>,
+        dir: PathBuf,
+        session: String,
+        cooldown: Duration,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(BlackboxDumper {
+            blackbox,
+            dir,
+            session,
+            cooldown,
+            last_auto_dump: None,
+            count: 0,
+        })
+    }
+
+    /*
+        an automatic dump is skipped(None) in the cooldown of the last automatic one, the manual ones never.
+        The packets are shared out of the blackbox, so the capture isn't blocked while writing.
+    */
+    pub fn dump(&mut self, reason: &str, auto: bool) -> io::Result<Option<PathBuf>> {
+        let now = Instant::now();
+        if auto {
+            if self
+                .last_auto_dump
+                .is_some_and(|x| now.duration_since(x) < self.cooldown)
+            {
+                return Ok(None);
+            }
+            self.last_auto_dump = Some(now);
+        }
+        let packets = self.blackbox.lock().unwrap().snapshot();
+        self.count += 1;
+        let path = self.dir.join(format!(
+            "blackbox_{}_{:03}_{}.pcap",
+            self.session, self.count, reason
+        ));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        write_pcap(&mut writer, &packets)?;
+        log::info!(
+            "blackbox dumped: {}, {} packets, reason:{}",
+            path.display(),
+            packets.len(),
+            reason
+        );
+        Ok(Some(path))
+    }
+
+    /*
+        write the dumps on a worker thread, which ends when all the handles are dropped.
+    */
+    pub fn spawn(mut self) -> BlackboxHandle {
+        let (sender, receiver) = mpsc::channel::<DumpRequest>();
+        std::thread::spawn(move || {
+            for request in receiver {
+                let ret = self.dump(&request.reason, request.reply.is_none());
+                match request.reply {
+                    Some(reply) => {
+                        let _ = reply.send(ret);
+                    }
+                    None => {
+                        if let Err(e) = ret {
+                            log::warn!("blackbox dump failed:{}", e);
+                        }
+                    }
+                }
+            }
+        });
+        BlackboxHandle { sender }
+    }
+}
+
+// the automatic dumps have no reply
+struct DumpRequest {
+    reason: String,
+    reply: Option<Sender<io::Result<Option<PathBuf>>>>,
+}
+
+#[derive(Clone)]
+pub struct BlackboxHandle {
+    sender: Sender<DumpRequest>,
+}
+
+impl BlackboxHandle {
+    /*
+        request an automatic dump without waiting for it, e.g. from the event handlers.
+    */
+    pub fn trigger(&self, reason: &str) {
+        let _ = self.sender.send(DumpRequest {
+            reason: reason.to_string(),
+            reply: None,
+        });
+    }
+
+    /*
+        a manual dump, waiting for the path of the file.
+    */
+    pub fn dump(&self, reason: &str) -> io::Result<Option<PathBuf>> {
+        let (reply, wait) = mpsc::channel();
+        let request = DumpRequest {
+            reason: reason.to_string(),
+            reply: Some(reply),
+        };
+        let worker_gone =
+            || io::Error::new(io::ErrorKind::BrokenPipe, "the blackbox worker is gone");
+        self.sender.send(request).map_err(|_| worker_gone())?;
+        wait.recv().map_err(|_| worker_gone())?
+    }
+}
+
+/*
+    The decode errors reach the threshold when they increase by threshold in a period.
+*/
+pub struct ErrorTrigger {
+    threshold: u32,
+    last_errors: Option<u32>,
+}
+
+impl ErrorTrigger {
+    pub const PERIOD: Duration = Duration::from_secs(1);
+
+    pub fn new(threshold: u32) -> Self {
+        ErrorTrigger {
+            threshold,
+            last_errors: None,
+        }
+    }
+
+    /*
+        observe the stats once a period.
+    */
+    pub fn observe(&mut self, stats: &ConnectStats) -> bool {
+        let errors = stats.broken_block_count + stats.crc_error_count + stats.jpeg_error_count;
+        let last_errors = self.last_errors.replace(errors);
+        last_errors.is_some_and(|x| errors.saturating_sub(x) >= self.threshold)
+    }
+
+    /*
+        check the shared stats every period and dump the blackbox on the errors, it never returns.
+    */
+    pub fn run(mut self, stats: Arc<RwLock<ConnectStats>>, dumper: BlackboxHandle) {
+        loop {
+            if self.observe(&stats.read().unwrap()) {
+                dumper.trigger("decode_errors");
+            }
+            std::thread::sleep(Self::PERIOD);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            sync::{Arc, Mutex},
+            time::Duration,
+        };
+
+        use crate::{
+            blackbox::{Blackbox, BlackboxDumper, ErrorTrigger, PCAP_MAGIC},
+            ConnectStats,
+        };
+
+        #[test]
+        fn test_ring() {
+            let mut blackbox = Blackbox::new(Duration::from_secs(2), 1000);
+            for i in 0..5 {
+                blackbox.push(Duration::from_secs(100 + i), 100, &[i as u8; 100]);
+            }
+            // 102..=104 are in the window
+            assert_eq!(blackbox.len(), 3);
+            assert_eq!(blackbox.bytes(), 300);
+            assert_eq!(blackbox.snapshot()[0].data[0], 2);
+
+            blackbox.push(Duration::from_secs(104), 900, &[5; 900]);
+            assert_eq!(blackbox.len(), 2);
+            assert_eq!(blackbox.bytes(), 1000);
+        }
+
+        #[test]
+        fn test_dump() {
+            let dir = std::env::temp_dir().join(format!("blackbox_test_{}", std::process::id()));
+            let blackbox = Arc::new(Mutex::new(Blackbox::new(Duration::from_secs(5), 1 << 20)));
+            blackbox
+                .lock()
+                .unwrap()
+                .push(Duration::new(1760000000, 123456000), 60, &[0xab; 50]);
+            let mut dumper = BlackboxDumper::new(
+                blackbox,
+                dir.clone(),
+                "s".to_string(),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+
+            let path = dumper.dump("link_lost", true).unwrap().unwrap();
+            assert_eq!(path, dir.join("blackbox_s_001_link_lost.pcap"));
+            assert_eq!(dumper.dump("link_lost", true).unwrap(), None); // in the cooldown
+            assert!(dumper.dump("manual", false).unwrap().is_some());
+
+            let data = std::fs::read(&path).unwrap();
+            assert_eq!(data.len(), 24 + 16 + 50);
+            let word =
+                |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+            assert_eq!(word(0), PCAP_MAGIC);
+            assert_eq!(word(20), 127);
+            assert_eq!((word(24), word(28)), (1760000000, 123456));
+            assert_eq!((word(32), word(36)), (50, 60));
+            assert_eq!(data[40..], [0xab; 50]);
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+
+        #[test]
+        fn test_dump_worker() {
+            let dir = std::env::temp_dir().join(format!("blackbox_worker_{}", std::process::id()));
+            let blackbox = Arc::new(Mutex::new(Blackbox::new(Duration::from_secs(5), 1 << 20)));
+            let dumper = BlackboxDumper::new(
+                blackbox.clone(),
+                dir.clone(),
+                "s".to_string(),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+            let handle = dumper.spawn();
+            blackbox
+                .lock()
+                .unwrap()
+                .push(Duration::from_secs(1760000000), 50, &[0xab; 50]);
+            handle.trigger("link_lost");
+            // the requests are handled in order, so the automatic one is written before the reply
+            let path = handle.dump("manual").unwrap().unwrap();
+            assert_eq!(path, dir.join("blackbox_s_002_manual.pcap"));
+            assert!(dir.join("blackbox_s_001_link_lost.pcap").exists());
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+
+        #[test]
+        fn test_error_trigger() {
+            let mut trigger = ErrorTrigger::new(10);
+            let mut stats = ConnectStats::new();
+            stats.crc_error_count = 100;
+            assert!(!trigger.observe(&stats)); // the errors before are not counted
+            stats.broken_block_count += 5;
+            assert!(!trigger.observe(&stats));
+            stats.broken_block_count += 5;
+            stats.jpeg_error_count += 5;
+            assert!(trigger.observe(&stats));
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    packet_h_bind::{
+        Air2Ground_Header, Air2Ground_Video_Packet, Ground2Air_Config_Packet,
+        Ground2Air_Config_Packet_Camera, Ground2Air_Data_Packet, Ground2Air_Header,
+    },
+    VtxPacketHeader, VTX_PACKET_HEADER_SIZE,
+};
+
+/*
+    Byte level encode/decode of the structs in packets.h.
+    The layout is written field by field in little-endian(the esp32 side), the same as the packed c structs,
+    so it does not depend on the bindgen layout or the host endianness.
+
+    Ground2Air_Header:        type(1) size(4) crc(1)
+    Air2Ground_Header:        type(1) size(4) pong(1) crc(1)
+    Air2Ground_Video_Packet:  Air2Ground_Header resolution(1) part_index:7|last_part:1(1) frame_index(4)
+    VtxPacketHeader:          block_index:24|packet_index:8(4) size(2)
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    TooShort { expected: usize, actual: usize },
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::TooShort { expected, actual } => {
+                write!(f, "data too short: expected {} bytes, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+pub trait Codec: Sized {
+    const SIZE: usize;
+
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /*
+        decode from the beginning of data, the bytes after SIZE are ignored.
+    */
+    fn decode(data: &[u8]) -> Result<Self, CodecError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(Self::SIZE);
+        self.encode(&mut ret);
+        ret
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], size: usize) -> Result<Self, CodecError> {
+        if data.len() < size {
+            return Err(CodecError::TooShort {
+                expected: size,
+                actual: data.len(),
+            });
+        }
+        Ok(Reader { data, pos: 0 })
+    }
+
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let ret = self.data[self.pos..self.pos + N].try_into().unwrap();
+        self.pos += N;
+        ret
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes::<1>()[0]
+    }
+
+    fn i8(&mut self) -> i8 {
+        self.u8() as i8
+    }
+
+    fn bool(&mut self) -> bool {
+        self.u8() != 0
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes())
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+impl Codec for Ground2Air_Header {
+    const SIZE: usize = 6;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let size = self.size;
+        out.push(self.type_);
+        out.extend_from_slice(&size.to_le_bytes());
+        out.push(self.crc);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let mut r = Reader::new(data, Self::SIZE)?;
+        Ok(Ground2Air_Header {
+            type_: r.u8(),
+            size: r.u32(),
+            crc: r.u8(),
+        })
+    }
+}
+
+impl Codec for Ground2Air_Data_Packet {
+    const SIZE: usize = Ground2Air_Header::SIZE;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        self._base.encode(out);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        Ok(Ground2Air_Data_Packet {
+            _base: Ground2Air_Header::decode(data)?,
+        })
+    }
+}
+
+impl Codec for Ground2Air_Config_Packet_Camera {
+    const SIZE: usize = 27;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let aec_value = self.aec_value;
+        out.extend_from_slice(&[
+            self.resolution,
+            self.fps_limit,
+            self.quality,
+            self.brightness as u8,
+            self.contrast as u8,
+            self.saturation as u8,
+            self.sharpness as u8,
+            self.denoise,
+            self.special_effect,
+            self.awb as u8,
+            self.awb_gain as u8,
+            self.wb_mode,
+            self.aec as u8,
+            self.aec2 as u8,
+            self.ae_level as u8,
+        ]);
+        out.extend_from_slice(&aec_value.to_le_bytes());
+        out.extend_from_slice(&[
+            self.agc as u8,
+            self.agc_gain,
+            self.gainceiling,
+            self.bpc as u8,
+            self.wpc as u8,
+            self.raw_gma as u8,
+            self.lenc as u8,
+            self.hmirror as u8,
+            self.vflip as u8,
+            self.dcw as u8,
+        ]);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let mut r = Reader::new(data, Self::SIZE)?;
+        Ok(Ground2Air_Config_Packet_Camera {
+            resolution: r.u8(),
+            fps_limit: r.u8(),
+            quality: r.u8(),
+            brightness: r.i8(),
+            contrast: r.i8(),
+            saturation: r.i8(),
+            sharpness: r.i8(),
+            denoise: r.u8(),
+            special_effect: r.u8(),
+            awb: r.bool(),
+            awb_gain: r.bool(),
+            wb_mode: r.u8(),
+            aec: r.bool(),
+            aec2: r.bool(),
+            ae_level: r.i8(),
+            aec_value: r.u16(),
+            agc: r.bool(),
+            agc_gain: r.u8(),
+            gainceiling: r.u8(),
+            bpc: r.bool(),
+            wpc: r.bool(),
+            raw_gma: r.bool(),
+            lenc: r.bool(),
+            hmirror: r.bool(),
+            vflip: r.bool(),
+            dcw: r.bool(),
+        })
+    }
+}
+
+impl Codec for Ground2Air_Config_Packet {
+    const SIZE: usize = Ground2Air_Header::SIZE + 8 + Ground2Air_Config_Packet_Camera::SIZE;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let fec_codec_mtu = self.fec_codec_mtu;
+        self._base.encode(out);
+        out.extend_from_slice(&[
+            self.ping,
+            self.wifi_power as u8,
+            self.wifi_rate,
+            self.fec_codec_k,
+            self.fec_codec_n,
+        ]);
+        out.extend_from_slice(&fec_codec_mtu.to_le_bytes());
+        out.push(self.dvr_record as u8);
+        self.camera.encode(out);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let mut r = Reader::new(data, Self::SIZE)?;
+        let _base = Ground2Air_Header::decode(r.rest())?;
+        r.pos += Ground2Air_Header::SIZE;
+        Ok(Ground2Air_Config_Packet {
+            _base,
+            ping: r.u8(),
+            wifi_power: r.i8(),
+            wifi_rate: r.u8(),
+            fec_codec_k: r.u8(),
+            fec_codec_n: r.u8(),
+            fec_codec_mtu: r.u16(),
+            dvr_record: r.bool(),
+            camera: Ground2Air_Config_Packet_Camera::decode(r.rest())?,
+        })
+    }
+}
+
+impl Codec for Air2Ground_Header {
+    const SIZE: usize = 7;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let size = self.size;
+        out.push(self.type_);
+        out.extend_from_slice(&size.to_le_bytes());
+        out.push(self.pong);
+        out.push(self.crc);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let mut r = Reader::new(data, Self::SIZE)?;
+        Ok(Air2Ground_Header {
+            type_: r.u8(),
+            size: r.u32(),
+            pong: r.u8(),
+            crc: r.u8(),
+        })
+    }
+}
+
+impl Codec for Air2Ground_Video_Packet {
+    const SIZE: usize = Air2Ground_Header::SIZE + 6;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let frame_index = self.frame_index;
+        self._base.encode(out);
+        out.push(self.resolution);
+        // gcc allocates the bitfields from the lowest bit
+        out.push((self.part_index() & 0x7F) | (self.last_part() << 7));
+        out.extend_from_slice(&frame_index.to_le_bytes());
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        let mut r = Reader::new(data, Self::SIZE)?;
+        let _base = Air2Ground_Header::decode(r.rest())?;
+        r.pos += Air2Ground_Header::SIZE;
+        let resolution = r.u8();
+        let bits = r.u8();
+        Ok(Air2Ground_Video_Packet {
+            _base,
+            resolution,
+            _bitfield_1: Air2Ground_Video_Packet::new_bitfield_1(bits & 0x7F, bits >> 7),
+            frame_index: r.u32(),
+        })
+    }
+}
+
+impl Codec for VtxPacketHeader<Vec<u8>> {
+    const SIZE: usize = VTX_PACKET_HEADER_SIZE;
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut data = self.0.clone();
+        data.resize(Self::SIZE, 0);
+        out.extend_from_slice(&data);
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, CodecError> {
+        Reader::new(data, Self::SIZE)?;
+        Ok(VtxPacketHeader(data[..Self::SIZE].to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{mem::size_of, slice};
+
+        use crate::{
+            codec::{Codec, CodecError},
+            packet_h_bind::*,
+            VtxPacketHeader,
+        };
+
+        // the bytes of the packed c struct in memory, which is what the air unit sees
+        fn raw_bytes<T>(origin: &T) -> Vec<u8> {
+            unsafe { slice::from_raw_parts(origin as *const T as *const u8, size_of::<T>()).to_vec() }
+        }
+
+        #[test]
+        fn test_sizes() {
+            assert_eq!(Ground2Air_Header::SIZE, size_of::<Ground2Air_Header>());
+            assert_eq!(Ground2Air_Data_Packet::SIZE, size_of::<Ground2Air_Data_Packet>());
+            assert_eq!(Ground2Air_Config_Packet::SIZE, size_of::<Ground2Air_Config_Packet>());
+            assert_eq!(Air2Ground_Header::SIZE, size_of::<Air2Ground_Header>());
+            assert_eq!(Air2Ground_Video_Packet::SIZE, size_of::<Air2Ground_Video_Packet>());
+        }
+
+        #[test]
+        fn test_config_packet() {
+            // a config packet captured from the old transmute based injection
+            let origin = [
+                1, 41, 0, 0, 0, 182, 0, 20, 12, 2, 3, 190, 5, 0, 0, 0, 8, 0, 0, 0, 255, 0, 0, 1, 1,
+                0, 1, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1,
+            ];
+            let packet = Ground2Air_Config_Packet::decode(&origin).unwrap();
+            assert_eq!({ packet.fec_codec_mtu }, 1470);
+            assert_eq!(packet.camera.sharpness, -1);
+            assert_eq!(packet.to_bytes(), origin);
+            assert_eq!(raw_bytes(&packet), origin);
+
+            let mut packet = Ground2Air_Config_Packet::default();
+            packet.camera.aec_value = 0x1234;
+            packet.wifi_power = -3;
+            assert_eq!(packet.to_bytes(), raw_bytes(&packet));
+
+            assert_eq!(
+                Ground2Air_Config_Packet::decode(&origin[..40]).err(),
+                Some(CodecError::TooShort {
+                    expected: 41,
+                    actual: 40
+                })
+            );
+        }
+
+        #[test]
+        fn test_video_packet() {
+            let origin = [0, 0xB2, 0x05, 0, 0, 1, 0x5A, 3, 0x85, 0x78, 0x56, 0x34, 0x12];
+            let packet = Air2Ground_Video_Packet::decode(&origin).unwrap();
+            assert_eq!({ packet._base.size }, 1458);
+            assert_eq!(packet.resolution, Resolution_VGA);
+            assert_eq!(packet.part_index(), 5);
+            assert_eq!(packet.last_part(), 1);
+            assert_eq!({ packet.frame_index }, 0x12345678);
+            assert_eq!(packet.to_bytes(), origin);
+            assert_eq!(raw_bytes(&packet), origin);
+            assert!(Air2Ground_Video_Packet::decode(&origin[..12]).is_err());
+        }
+
+        #[test]
+        fn test_vtx_packet_header() {
+            let mut header = VtxPacketHeader(vec![0; 6]);
+            header.set_block_index(0x123456);
+            header.set_packet_index(2);
+            header.set_size(1476);
+            let data = header.to_bytes();
+            assert_eq!(data, [0x56, 0x34, 0x12, 2, 0xC4, 0x05]);
+            let decoded = VtxPacketHeader::decode(&data).unwrap();
+            assert_eq!(decoded.block_index(), 0x123456);
+            assert_eq!(decoded.packet_index(), 2);
+            assert_eq!(decoded.size(), 1476);
+            assert!(VtxPacketHeader::decode(&data[..5]).is_err());
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::{
+    air_config::AirConfig,
+    events::{Event, EventBus},
+    packet_h_bind::Ground2Air_Config_Packet,
+};
+
+/*
+    Track whether the air unit applied the requested config.
+    It's the only owner of the requested config, the control port and the dashboard request the changes to it,
+    and the uplink sends its packet.
+
+    Each new config is sent with a new ping, a config is confirmed when:
+    1. the air unit echoes the ping in the pong of the video packets, or
+    2. only the resolution and fec_n are changed from the last confirmed config,
+       and both of them are observed in the stream after the request:
+       the resolution from the video headers, fec_n from the max packet index of the blocks.
+    fec_k and the other camera fields could only be confirmed by the pong.
+
+    The config is retransmitted faster while it is pending, until it is confirmed or timed out.
+    The time from the first send of a ping to its pong is the round trip time of the uplink and the video link,
+    the ping of a confirmed config is renewed every PROBE_INTERVAL to keep it fresh.
+    The decoder runs with the fec codec of the confirmed config, refer decoder_fec.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigState {
+    Pending,
+    Confirmed,
+    TimedOut,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Observed {
+    resolution: Option<u8>,
+    fec_n: Option<u8>,
+}
+
+pub struct ConfigTracker {
+    requested: AirConfig,
+    confirmed: Option<AirConfig>,
+    ping: u8,
+    state: ConfigState,
+    requested_time: Instant,
+    ping_time: Instant,         // the ping is renewed
+    sent_time: Option<Instant>, // the first send of the ping, taken by its pong
+    timeout: Duration,
+    observed: Observed,
+    rtt: Option<(Duration, Instant)>, // the last sample and when it's observed
+    events: EventBus,
+}
+
+impl ConfigTracker {
+    // of the decoder switching between the confirmed and the requested fec_k while pending
+    pub const FEC_PROBE_PERIOD: Duration = Duration::from_millis(500);
+    // the ping of a confirmed config is renewed to sample the rtt
+    pub const PROBE_INTERVAL: Duration = Duration::from_secs(2);
+    // the rtt is unknown without a sample in this time
+    pub const RTT_MAX_AGE: Duration = Duration::from_secs(5);
+
+    pub fn new(config: AirConfig, timeout: Duration) -> Self {
+        let now = Instant::now();
+        ConfigTracker {
+            requested: config,
+            confirmed: None,
+            ping: 1, // the air unit reports pong 0 before any config is received
+            state: ConfigState::Pending,
+            requested_time: now,
+            ping_time: now,
+            sent_time: None,
+            timeout,
+            observed: Observed::default(),
+            rtt: None,
+            events: EventBus::default(),
+        }
+    }
+
+    /*
+        publish the confirmed and timed out configs, and the dvr of the air unit started or stopped by them.
+    */
+    pub fn set_event_bus(&mut self, events: EventBus) {
+        self.events = events;
+    }
+
+    /*
+        request a new config, nothing changes if it is the same as the requested one.
+    */
+    pub fn request(&mut self, config: AirConfig, now: Instant) {
+        if config == self.requested {
+            return;
+        }
+        self.requested = config;
+        self.renew_ping(now);
+        self.state = ConfigState::Pending;
+        self.requested_time = now;
+        self.observed = Observed::default();
+    }
+
+    fn renew_ping(&mut self, now: Instant) {
+        self.ping = match self.ping.wrapping_add(1) {
+            0 => 1,
+            x => x,
+        };
+        self.ping_time = now;
+        self.sent_time = None;
+    }
+
+    /*
+        renew the ping of the confirmed config every PROBE_INTERVAL, called by the uplink before sending.
+    */
+    pub fn probe(&mut self, now: Instant) {
+        if self.state == ConfigState::Confirmed
+            && now.saturating_duration_since(self.ping_time) >= Self::PROBE_INTERVAL
+        {
+            self.renew_ping(now);
+        }
+    }
+
+    /*
+        the uplink sent the packet of the ping, the rtt is measured from its first send.
+    */
+    pub fn ping_sent(&mut self, ping: u8, now: Instant) {
+        if ping == self.ping && self.sent_time.is_none() {
+            self.sent_time = Some(now);
+        }
+    }
+
+    /*
+        the packet to send, carrying the ping of the requested config.
+    */
+    pub fn packet(&self) -> Ground2Air_Config_Packet {
+        let mut ret = self.requested.to_packet();
+        ret.ping = self.ping;
+        ret.update_crc();
+        ret
+    }
+
+    pub fn requested(&self) -> AirConfig {
+        self.requested
+    }
+
+    pub fn confirmed(&self) -> Option<AirConfig> {
+        self.confirmed
+    }
+
+    pub fn state(&mut self, now: Instant) -> ConfigState {
+        if self.state == ConfigState::Pending
+            && now.saturating_duration_since(self.requested_time) >= self.timeout
+        {
+            self.state = ConfigState::TimedOut;
+            self.events
+                .publish(Event::ConfigTimedOut { ping: self.ping });
+        }
+        self.state
+    }
+
+    /*
+        the fec codec(k, n) the decoder should run with, which is the one of the confirmed config.
+        The pong of a changed fec_k could only be decoded with the new codec, so while it's pending,
+        the decoder switches to the requested codec every other FEC_PROBE_PERIOD to catch the pong.
+        A changed fec_n alone is decoded by the old codec and confirmed by the packet index.
+        The requested codec is used before any config is confirmed.
+    */
+    pub fn decoder_fec(&mut self, now: Instant) -> (u8, u8) {
+        let requested = (self.requested.fec_codec_k, self.requested.fec_codec_n);
+        let Some(confirmed) = self.confirmed else {
+            return requested;
+        };
+        let confirmed = (confirmed.fec_codec_k, confirmed.fec_codec_n);
+        if confirmed.0 == requested.0 || self.state(now) != ConfigState::Pending {
+            return confirmed;
+        }
+        let elapsed = now.saturating_duration_since(self.requested_time);
+        match (elapsed.as_millis() / Self::FEC_PROBE_PERIOD.as_millis()) % 2 {
+            0 => confirmed,
+            _ => requested,
+        }
+    }
+
+    /*
+        None without a fresh sample, e.g. the link is lost.
+    */
+    pub fn rtt(&self, now: Instant) -> Option<Duration> {
+        self.rtt
+            .filter(|(_, time)| now.saturating_duration_since(*time) < Self::RTT_MAX_AGE)
+            .map(|(rtt, _)| rtt)
+    }
+
+    pub fn is_pending(&mut self, now: Instant) -> bool {
+        self.state(now) == ConfigState::Pending
+    }
+
+    /*
+        observe a completed frame, with the resolution and pong in its video header.
+        A round trip longer than the timeout is not sampled, e.g. the air unit is powered on later.
+    */
+    pub fn observe_frame(&mut self, resolution: Option<u8>, pong: Option<u8>) {
+        if pong == Some(self.ping) {
+            let now = Instant::now();
+            let rtt = self
+                .sent_time
+                .take()
+                .map(|x| now.saturating_duration_since(x))
+                .filter(|x| *x < self.timeout);
+            if let Some(rtt) = rtt {
+                self.rtt = Some((rtt, now));
+            }
+            if self.state != ConfigState::Confirmed {
+                self.confirm(rtt);
+            }
+            return;
+        }
+        if resolution.is_some() {
+            self.observed.resolution = resolution;
+        }
+        self.check_inferred();
+    }
+
+    /*
+        observe the max packet index of the blocks received in a while.
+    */
+    pub fn observe_max_packet_index(&mut self, index: u32) {
+        self.observed.fec_n = u8::try_from(index + 1).ok();
+        self.check_inferred();
+    }
+
+    fn check_inferred(&mut self) {
+        if self.state == ConfigState::Confirmed {
+            return;
+        }
+        let Some(mut inferable) = self.confirmed else {
+            return;
+        };
+        inferable.camera.resolution = self.requested.camera.resolution;
+        inferable.fec_codec_n = self.requested.fec_codec_n;
+        if inferable == self.requested
+            && self.observed.resolution == Some(self.requested.camera.resolution.into())
+            && self.observed.fec_n == Some(self.requested.fec_codec_n)
+        {
+            self.confirm(None);
+        }
+    }
+
+    fn confirm(&mut self, rtt: Option<Duration>) {
+        let recording = self.confirmed.is_some_and(|x| x.dvr_record);
+        self.confirmed = Some(self.requested);
+        self.state = ConfigState::Confirmed;
+        self.events.publish(Event::ConfigConfirmed {
+            ping: self.ping,
+            rtt_ms: rtt.map(|x| x.as_secs_f64() * 1000.0),
+        });
+        match (recording, self.requested.dvr_record) {
+            (false, true) => self
+                .events
+                .publish(Event::RecordingStart { recorder: "air" }),
+            (true, false) => self
+                .events
+                .publish(Event::RecordingStop { recorder: "air" }),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            sync::{Arc, Mutex},
+            time::{Duration, Instant},
+        };
+
+        use crate::{
+            air_config::{AirConfig, Resolution},
+            config_tracker::{ConfigState, ConfigTracker},
+            events::{Event, EventBus},
+        };
+
+        #[test]
+        fn test_confirm_by_pong() {
+            let now = Instant::now();
+            let mut tracker = ConfigTracker::new(AirConfig::default(), Duration::from_secs(3));
+            let events = EventBus::new();
+            let published = Arc::new(Mutex::new(Vec::new()));
+            let published_rx = published.clone();
+            events.subscribe(move |record| published_rx.lock().unwrap().push(record.event.clone()));
+            tracker.set_event_bus(events.clone());
+            let ping = tracker.packet().ping;
+            tracker.ping_sent(ping, Instant::now());
+            tracker.observe_frame(Some(0), Some(0));
+            assert_eq!(tracker.state(now), ConfigState::Pending);
+            tracker.observe_frame(Some(0), Some(ping));
+            assert_eq!(tracker.state(now), ConfigState::Confirmed);
+            assert_eq!(tracker.confirmed(), Some(AirConfig::default()));
+            assert!(tracker.rtt(Instant::now()).is_some());
+
+            let mut config = AirConfig::default();
+            config.camera.quality = 20;
+            config.dvr_record = true;
+            tracker.request(config, now);
+            assert_ne!(tracker.packet().ping, ping);
+            assert!(tracker.is_pending(now));
+            assert!(!tracker.is_pending(now + Duration::from_secs(3)));
+            assert_eq!(tracker.state(now), ConfigState::TimedOut);
+            // a late confirmation is still accepted
+            tracker.observe_frame(None, Some(tracker.packet().ping));
+            assert_eq!(tracker.confirmed(), Some(config));
+
+            events.flush();
+            let published = published.lock().unwrap();
+            assert_eq!(published.len(), 4);
+            assert!(matches!(
+                published[0],
+                Event::ConfigConfirmed {
+                    rtt_ms: Some(_),
+                    ..
+                }
+            ));
+            assert_eq!(published[1], Event::ConfigTimedOut { ping: ping + 1 });
+            assert!(
+                matches!(published[2], Event::ConfigConfirmed { ping: x, .. } if x == ping + 1)
+            );
+            assert_eq!(published[3], Event::RecordingStart { recorder: "air" });
+        }
+
+        #[test]
+        fn test_rtt_probe() {
+            let mut tracker = ConfigTracker::new(AirConfig::default(), Duration::from_secs(3));
+            let ping = tracker.packet().ping;
+            // the air unit is powered on long after the first send
+            tracker.ping_sent(ping, Instant::now() - Duration::from_secs(60));
+            tracker.observe_frame(None, Some(ping));
+            assert_eq!(tracker.confirmed(), Some(AirConfig::default()));
+            assert_eq!(tracker.rtt(Instant::now()), None);
+
+            let now = Instant::now();
+            tracker.probe(now);
+            assert_eq!(tracker.packet().ping, ping); // not due
+            let later = now + ConfigTracker::PROBE_INTERVAL;
+            tracker.probe(later);
+            let probe = tracker.packet().ping;
+            assert_ne!(probe, ping);
+            assert_eq!(tracker.state(later), ConfigState::Confirmed);
+            tracker.ping_sent(probe, Instant::now());
+            tracker.observe_frame(None, Some(probe));
+            let rtt = tracker.rtt(Instant::now()).unwrap();
+            assert!(rtt < Duration::from_secs(1));
+            // until the next sample
+            assert_eq!(
+                tracker.rtt(Instant::now() + ConfigTracker::RTT_MAX_AGE),
+                None
+            );
+        }
+
+        #[test]
+        fn test_decoder_fec() {
+            let now = Instant::now();
+            let probe = ConfigTracker::FEC_PROBE_PERIOD;
+            let config = AirConfig::builder().fec(2, 3).build().unwrap();
+            let mut tracker = ConfigTracker::new(config, Duration::from_secs(3));
+            assert_eq!(tracker.decoder_fec(now), (2, 3)); // nothing confirmed yet
+            tracker.observe_frame(None, Some(tracker.packet().ping));
+
+            // fec_n alone is kept until confirmed
+            tracker.request(config.to_builder().fec(2, 4).build().unwrap(), now);
+            assert_eq!(tracker.decoder_fec(now + probe), (2, 3));
+            tracker.observe_frame(Some(config.camera.resolution.into()), None);
+            tracker.observe_max_packet_index(3);
+            assert_eq!(tracker.decoder_fec(now + probe), (2, 4));
+
+            // fec_k is probed while pending
+            let config = config.to_builder().fec(4, 6).build().unwrap();
+            tracker.request(config, now);
+            assert_eq!(tracker.decoder_fec(now), (2, 4));
+            assert_eq!(tracker.decoder_fec(now + probe), (4, 6));
+            assert_eq!(tracker.decoder_fec(now + probe * 2), (2, 4));
+            // the air unit doesn't apply it in time
+            assert_eq!(
+                tracker.decoder_fec(now + Duration::from_secs(3) + probe),
+                (2, 4)
+            );
+            tracker.observe_frame(None, Some(tracker.packet().ping));
+            assert_eq!(tracker.decoder_fec(now + Duration::from_secs(4)), (4, 6));
+        }
+
+        #[test]
+        fn test_confirm_by_stream() {
+            let now = Instant::now();
+            let mut tracker = ConfigTracker::new(AirConfig::default(), Duration::from_secs(3));
+            let ping = tracker.packet().ping;
+            tracker.observe_frame(None, Some(ping));
+
+            let config = AirConfig::builder()
+                .resolution(Resolution::Vga)
+                .fec(2, 4)
+                .build()
+                .unwrap();
+            tracker.request(config, now);
+            tracker.observe_frame(Some(Resolution::Vga.into()), Some(ping));
+            assert!(tracker.is_pending(now));
+            tracker.observe_max_packet_index(3);
+            assert_eq!(tracker.state(now), ConfigState::Confirmed);
+
+            // the quality could not be inferred from the stream
+            let config = config.to_builder().quality(30).build().unwrap();
+            tracker.request(config, now);
+            tracker.observe_frame(Some(Resolution::Vga.into()), Some(ping));
+            tracker.observe_max_packet_index(3);
+            assert!(tracker.is_pending(now));
+        }
+    }
+}
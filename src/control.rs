@@ -0,0 +1,459 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    air_config::{AirConfig, ConfigError, Resolution, WifiRate},
+    blackbox::BlackboxHandle,
+    config_tracker::{ConfigState, ConfigTracker},
+    ConnectStats,
+};
+
+/*
+    UDP control protocol, listening on --control-port.
+    Each datagram is a json request, and a json reply is sent back to the sender.
+
+    request:
+        {"id": 1, "cmd": "set_camera", "quality": 12, "resolution": 3}
+        {"id": 2, "cmd": "set_radio", "wifi_rate": 12, "wifi_power": 18}
+        {"id": 3, "cmd": "set_fec", "k": 2, "n": 3}
+        {"id": 4, "cmd": "set_dvr", "record": true}
+        {"id": 5, "cmd": "get_config"}
+        {"id": 6, "cmd": "get_stats"}
+        {"id": 7, "cmd": "dump_blackbox"}
+    reply:
+        {"id": 1, "ok": true, "config": {...}, "confirmed": {...}, "config_state": "pending"}
+        {"id": 6, "ok": true, "stats": {...}}
+        {"id": 7, "ok": true, "blackbox": "/var/log/vtx/blackbox_1760000000_001_manual.pcap"}
+        {"id": 1, "ok": false, "error": "quality out of range 0..=63: 70"}
+
+    "id" is optional and echoed back. Fields omitted in set_* commands keep their current values.
+    Enums(resolution, wifi_rate) could be either the names in packets.h or the raw values.
+    The changed config is requested to the ConfigTracker, whose packet the uplink transmits.
+    "confirmed" is the config applied by the air unit, "config_state" is pending, confirmed or timed_out.
+*/
+
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraParams {
+    pub resolution: Option<Resolution>,
+    pub fps_limit: Option<u8>,
+    pub quality: Option<u8>,
+    pub brightness: Option<i8>,
+    pub contrast: Option<i8>,
+    pub saturation: Option<i8>,
+    pub sharpness: Option<i8>,
+    pub denoise: Option<u8>,
+    pub special_effect: Option<u8>,
+    pub awb: Option<bool>,
+    pub awb_gain: Option<bool>,
+    pub wb_mode: Option<u8>,
+    pub aec: Option<bool>,
+    pub aec2: Option<bool>,
+    pub ae_level: Option<i8>,
+    pub aec_value: Option<u16>,
+    pub agc: Option<bool>,
+    pub agc_gain: Option<u8>,
+    pub gainceiling: Option<u8>,
+    pub bpc: Option<bool>,
+    pub wpc: Option<bool>,
+    pub raw_gma: Option<bool>,
+    pub lenc: Option<bool>,
+    pub hmirror: Option<bool>,
+    pub vflip: Option<bool>,
+    pub dcw: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ControlCommand {
+    SetCamera(CameraParams),
+    SetRadio {
+        wifi_rate: Option<WifiRate>,
+        wifi_power: Option<i8>,
+    },
+    SetFec {
+        k: u8,
+        n: u8,
+    },
+    SetDvr {
+        record: bool,
+    },
+    GetConfig,
+    GetStats,
+    DumpBlackbox,
+}
+
+#[derive(Deserialize, Debug)]
+struct ControlRequest {
+    id: Option<u32>,
+    #[serde(flatten)]
+    command: ControlCommand,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StatsView {
+    pub uptime_ms: u64,
+    pub packet_count: u32,
+    pub frame_count: u32,
+    pub dropped_frame_count: u32,
+    pub broken_block_count: u32,
+    pub jpeg_error_count: u32,
+    pub rssi: Option<i8>,
+}
+
+#[derive(Serialize, Debug)]
+struct ControlReply {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<AirConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmed: Option<AirConfig>, // the config applied by the air unit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_state: Option<ConfigState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<StatsView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blackbox: Option<PathBuf>, // the dumped pcap file
+}
+
+macro_rules! apply_params {
+    ($params:expr, $camera:expr, $($field:ident),*) => {
+        $(
+            if let Some(v) = $params.$field {
+                $camera.$field = v;
+            }
+        )*
+    };
+}
+
+impl ControlCommand {
+    /*
+        apply the command to the config, the config is untouched if the command is invalid.
+    */
+    pub fn apply(&self, config: &mut AirConfig) -> Result<(), ConfigError> {
+        let mut new_config = *config;
+        match self {
+            ControlCommand::SetCamera(params) => {
+                apply_params!(
+                    params, new_config.camera, resolution, fps_limit, quality, brightness,
+                    contrast, saturation, sharpness, denoise, special_effect, awb, awb_gain,
+                    wb_mode, aec, aec2, ae_level, aec_value, agc, agc_gain, gainceiling, bpc, wpc,
+                    raw_gma, lenc, hmirror, vflip, dcw
+                );
+            }
+            ControlCommand::SetRadio {
+                wifi_rate,
+                wifi_power,
+            } => {
+                if let Some(rate) = wifi_rate {
+                    new_config.wifi_rate = *rate;
+                }
+                if let Some(power) = wifi_power {
+                    new_config.wifi_power = *power;
+                }
+            }
+            ControlCommand::SetFec { k, n } => {
+                new_config.fec_codec_k = *k;
+                new_config.fec_codec_n = *n;
+            }
+            ControlCommand::SetDvr { record } => {
+                new_config.dvr_record = *record;
+            }
+            ControlCommand::GetConfig
+            | ControlCommand::GetStats
+            | ControlCommand::DumpBlackbox => return Ok(()),
+        }
+        new_config.validate()?;
+        *config = new_config;
+        Ok(())
+    }
+}
+
+impl StatsView {
+    pub fn from_stats(stats: &ConnectStats) -> Self {
+        StatsView {
+            uptime_ms: stats
+                .start_time
+                .elapsed()
+                .map(|x| x.as_millis() as u64)
+                .unwrap_or(0),
+            packet_count: stats.packet_count,
+            frame_count: stats.frame_count,
+            dropped_frame_count: stats.dropped_frame_count,
+            broken_block_count: stats.broken_block_count,
+            jpeg_error_count: stats.jpeg_error_count,
+            rssi: stats.rssi,
+        }
+    }
+}
+
+type ConfigChangedCallback = Box<dyn FnMut(&AirConfig) + Send>;
+
+pub struct ControlServer {
+    socket: UdpSocket,
+    config_tracker: Arc<Mutex<ConfigTracker>>,
+    stats: Arc<RwLock<ConnectStats>>,
+    config_changed_callback: Option<ConfigChangedCallback>,
+    blackbox: Option<BlackboxHandle>,
+}
+
+impl ControlServer {
+    pub fn new(
+        port: u16,
+        config_tracker: Arc<Mutex<ConfigTracker>>,
+        stats: Arc<RwLock<ConnectStats>>,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+        Ok(ControlServer {
+            socket,
+            config_tracker,
+            stats,
+            config_changed_callback: None,
+            blackbox: None,
+        })
+    }
+
+    pub fn do_when_config_changed<F>(&mut self, func: F)
+    where
+        F: FnMut(&AirConfig) + Send + 'static,
+    {
+        self.config_changed_callback = Some(Box::new(func))
+    }
+
+    /*
+        without the blackbox, dump_blackbox is replied with an error.
+    */
+    pub fn set_blackbox(&mut self, dumper: BlackboxHandle) {
+        self.blackbox = Some(dumper);
+    }
+
+    /*
+        handle one request and return the reply.
+    */
+    pub fn handle_request(&mut self, request: &[u8]) -> Vec<u8> {
+        let reply = match serde_json::from_slice::<ControlRequest>(request) {
+            Ok(request) => self.handle_command(request.id, &request.command),
+            Err(e) => ControlReply::error(None, format!("invalid request: {}", e)),
+        };
+        serde_json::to_vec(&reply).unwrap()
+    }
+
+    fn handle_command(&mut self, id: Option<u32>, command: &ControlCommand) -> ControlReply {
+        if *command == ControlCommand::GetStats {
+            let stats = StatsView::from_stats(&self.stats.read().unwrap());
+            return ControlReply {
+                stats: Some(stats),
+                ..ControlReply::ok(id)
+            };
+        }
+        if *command == ControlCommand::DumpBlackbox {
+            let Some(dumper) = &self.blackbox else {
+                return ControlReply::error(id, "the blackbox is disabled".to_string());
+            };
+            return match dumper.dump("manual") {
+                Ok(path) => ControlReply {
+                    blackbox: path,
+                    ..ControlReply::ok(id)
+                },
+                Err(e) => ControlReply::error(id, format!("blackbox dump failed: {}", e)),
+            };
+        }
+
+        // the changed config is requested before the callback, which wakes the uplink to send the new ping at once
+        let now = Instant::now();
+        let mut tracker = self.config_tracker.lock().unwrap();
+        let old_config = tracker.requested();
+        let mut new_config = old_config;
+        match command.apply(&mut new_config) {
+            Ok(()) => {
+                tracker.request(new_config, now);
+                let (confirmed, config_state) = (tracker.confirmed(), Some(tracker.state(now)));
+                drop(tracker);
+                if new_config != old_config {
+                    if let Some(callback) = self.config_changed_callback.as_mut() {
+                        callback(&new_config);
+                    }
+                }
+                ControlReply {
+                    config: Some(new_config),
+                    confirmed,
+                    config_state,
+                    ..ControlReply::ok(id)
+                }
+            }
+            Err(e) => ControlReply::error(id, e.to_string()),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, src) = match self.socket.recv_from(&mut buf) {
+                Ok(ret) => ret,
+                Err(e) => {
+                    crate::warn_limited!("control port recv failed:{}", e);
+                    continue;
+                }
+            };
+            let reply = self.handle_request(&buf[..len]);
+            if let Err(e) = self.socket.send_to(&reply, src) {
+                log::warn!("control port reply to {} failed:{}", src, e);
+            }
+        }
+    }
+}
+
+impl ControlReply {
+    fn ok(id: Option<u32>) -> Self {
+        ControlReply {
+            id,
+            ok: true,
+            error: None,
+            config: None,
+            confirmed: None,
+            config_state: None,
+            stats: None,
+            blackbox: None,
+        }
+    }
+
+    fn error(id: Option<u32>, error: String) -> Self {
+        ControlReply {
+            ok: false,
+            error: Some(error),
+            ..ControlReply::ok(id)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            sync::{Arc, Mutex, RwLock},
+            time::Duration,
+        };
+
+        use serde_json::Value;
+
+        use crate::{
+            air_config::AirConfig,
+            blackbox::{Blackbox, BlackboxDumper},
+            config_tracker::ConfigTracker,
+            control::ControlServer,
+            ConnectStats,
+        };
+
+        fn new_server() -> ControlServer {
+            ControlServer::new(
+                0,
+                Arc::new(Mutex::new(ConfigTracker::new(
+                    AirConfig::default(),
+                    Duration::from_secs(3),
+                ))),
+                Arc::new(RwLock::new(ConnectStats::new())),
+            )
+            .unwrap()
+        }
+
+        fn request(server: &mut ControlServer, req: &str) -> Value {
+            serde_json::from_slice(&server.handle_request(req.as_bytes())).unwrap()
+        }
+
+        #[test]
+        fn test_set_camera() {
+            let mut server = new_server();
+            let reply = request(
+                &mut server,
+                r#"{"id":7,"cmd":"set_camera","quality":20,"vflip":true,"resolution":"VGA"}"#,
+            );
+            assert_eq!(reply["id"], 7);
+            assert_eq!(reply["ok"], true);
+            assert_eq!(reply["config"]["camera"]["quality"], 20);
+            assert_eq!(reply["config"]["camera"]["resolution"], "VGA");
+
+            let config = server.config_tracker.lock().unwrap().requested();
+            assert_eq!(config.camera.quality, 20);
+            assert!(config.camera.vflip);
+        }
+
+        #[test]
+        fn test_invalid_request() {
+            let mut server = new_server();
+            let reply = request(&mut server, r#"{"id":1,"cmd":"set_camera","quality":20,"brightness":3}"#);
+            assert_eq!(reply["ok"], false);
+            assert_eq!(reply["error"], "brightness out of range -2..=2: 3");
+            // the config is untouched if any field is invalid
+            assert_eq!(server.config_tracker.lock().unwrap().requested().camera.quality, 8);
+
+            let reply = request(&mut server, r#"{"cmd":"set_fec","k":3,"n":3}"#);
+            assert_eq!(reply["ok"], false);
+            let reply = request(&mut server, r#"{"cmd":"reboot"}"#);
+            assert_eq!(reply["ok"], false);
+            let reply = request(&mut server, "not json");
+            assert_eq!(reply["ok"], false);
+        }
+
+        #[test]
+        fn test_config_state() {
+            let mut server = new_server();
+            let tracker = server.config_tracker.clone();
+            let ping = tracker.lock().unwrap().packet().ping;
+            tracker.lock().unwrap().observe_frame(None, Some(ping));
+            // the callback wakes the uplink, which sends the ping of the requested config
+            let tracker_rx = tracker.clone();
+            let requested_pings = Arc::new(Mutex::new(Vec::new()));
+            let requested_pings_rx = requested_pings.clone();
+            server.do_when_config_changed(move |_| {
+                let ping = tracker_rx.lock().unwrap().packet().ping;
+                requested_pings_rx.lock().unwrap().push(ping);
+            });
+
+            let reply = request(&mut server, r#"{"cmd":"get_config"}"#);
+            assert_eq!(reply["config_state"], "confirmed");
+            let reply = request(&mut server, r#"{"cmd":"set_dvr","record":true}"#);
+            assert_eq!(reply["config_state"], "pending");
+            assert_eq!(reply["confirmed"]["dvr_record"], false);
+            assert!(tracker.lock().unwrap().requested().dvr_record);
+            assert_eq!(*requested_pings.lock().unwrap(), vec![ping + 1]);
+        }
+
+        #[test]
+        fn test_get_stats() {
+            let mut server = new_server();
+            let reply = request(&mut server, r#"{"cmd":"get_stats"}"#);
+            assert_eq!(reply["ok"], true);
+            assert_eq!(reply["stats"]["packet_count"], 0);
+        }
+
+        #[test]
+        fn test_dump_blackbox() {
+            let mut server = new_server();
+            let reply = request(&mut server, r#"{"id":3,"cmd":"dump_blackbox"}"#);
+            assert_eq!(reply["ok"], false);
+            assert_eq!(reply["error"], "the blackbox is disabled");
+
+            let dir = std::env::temp_dir().join(format!("control_blackbox_{}", std::process::id()));
+            let blackbox = Arc::new(Mutex::new(Blackbox::new(Duration::from_secs(5), 1 << 20)));
+            let dumper = BlackboxDumper::new(blackbox, dir.clone(), "s".to_string(), Duration::ZERO);
+            server.set_blackbox(dumper.unwrap().spawn());
+            let reply = request(&mut server, r#"{"id":3,"cmd":"dump_blackbox"}"#);
+            assert_eq!(reply["ok"], true);
+            let path = dir.join("blackbox_s_001_manual.pcap");
+            assert_eq!(reply["blackbox"], path.to_str().unwrap());
+            assert!(path.exists());
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
@@ -41,10 +41,22 @@ impl Device {
         let mut active_cap = cap.open().unwrap();
 
         active_cap.filter("ether[0x0a:4]==0x11223344 && ether[0x0e:2] == 0x5566", true).unwrap();
+        log::info!("capture device opened: {}, datalink:{:?}", dev_name, active_cap.get_datalink());
         Device {
             dev_name,
             cap: active_cap,
         }
     }
 
+    /*
+        another handle of the device only for injection,
+        so the injection does not wait for the blocking capture.
+    */
+    pub fn open_inject(&self) -> Result<Capture<Active>, pcap::Error> {
+        log::debug!("open the injection handle of {}", self.dev_name);
+        Capture::from_device(self.dev_name.as_str())?
+            .immediate_mode(true)
+            .open()
+    }
+
 }
@@ -0,0 +1,396 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::{
+    air_config::AirConfig, output::unix_millis, protocol::ProtocolVersion, stats::LinkReport,
+    ConnectStats,
+};
+
+/*
+    Timeline of a session for the review after a flight, e.g.
+        {"seq":0,"mono_ms":0.0,"time_ms":1760000000123,"event":"session_start","device":"wlan0","version":"0.1.0","air":{...}}
+        {"seq":1,"mono_ms":12.5,"time_ms":1760000000135,"event":"config_sent","ping":2,"config":{...}}
+        {"seq":2,"mono_ms":48.1,"time_ms":1760000000171,"event":"config_confirmed","ping":2,"rtt_ms":35.6}
+        {"seq":3,"mono_ms":10012.0,"time_ms":1760000010135,"event":"stats","window_ms":5000,"packets":...}
+
+    mono_ms is the monotonic time since the bus is created, which never jumps with the wall clock,
+    time_ms is the unix time.
+*/
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    SessionStart {
+        device: String,
+        version: &'static str,
+        air: AirConfig,
+    },
+    // the frame index restarted from a smaller one
+    AirRestart {
+        frame_index: u32,
+        last_frame_index: u32,
+    },
+    // the protocol of the air unit is detected from the video headers
+    ProtocolDetected {
+        version: ProtocolVersion,
+    },
+    // a new config is sent for the first time, it's resent until confirmed.
+    // The ping is also renewed to sample the rtt, which is not a new config
+    ConfigSent {
+        ping: u8,
+        config: AirConfig,
+    },
+    ConfigConfirmed {
+        ping: u8,
+        rtt_ms: Option<f64>, // None if confirmed by the stream, or the round trip is longer than the timeout
+    },
+    ConfigTimedOut {
+        ping: u8,
+    },
+    LinkLost {
+        timeout_ms: u64,
+    },
+    LinkRegained {
+        lost_ms: u64,
+    },
+    DeviceError {
+        device: String,
+        error: String,
+    },
+    // recorder is "air"(the dvr of the air unit) or "dump"(the frame dump of the ground)
+    RecordingStart {
+        recorder: &'static str,
+    },
+    RecordingStop {
+        recorder: &'static str,
+    },
+    Stats(LinkReport),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub mono_ms: f64,
+    pub time_ms: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+type EventHandler = Box<dyn FnMut(&EventRecord) + Send>;
+
+enum Message {
+    Record(EventRecord),
+    Flush(Sender<()>),
+}
+
+struct BusState {
+    start: Instant,
+    seq: u64,
+    sender: Option<Sender<Message>>, // to the dispatcher, started by the first subscriber
+}
+
+/*
+    The subsystems publish their events to the bus, which stamps them and passes them to the dispatcher thread,
+    it calls the subscribers in order. So the publishers are never blocked by the handlers,
+    even if they publish with their own locks held, and the handlers could publish too.
+    It's cloned to each publisher, the events are dropped if nobody subscribes.
+*/
+#[derive(Clone)]
+pub struct EventBus {
+    state: Arc<Mutex<BusState>>,
+    handlers: Arc<Mutex<Vec<EventHandler>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus {
+            state: Arc::new(Mutex::new(BusState {
+                start: Instant::now(),
+                seq: 0,
+                sender: None,
+            })),
+            handlers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+        the handlers are called on the dispatcher thread, a slow one delays the later events but not the publishers.
+    */
+    pub fn subscribe<F>(&self, func: F)
+    where
+        F: FnMut(&EventRecord) + Send + 'static,
+    {
+        self.handlers.lock().unwrap().push(Box::new(func));
+        let mut state = self.state.lock().unwrap();
+        if state.sender.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            let handlers = self.handlers.clone();
+            std::thread::spawn(move || {
+                // ends when all the buses are dropped
+                for message in receiver {
+                    match message {
+                        Message::Record(record) => {
+                            for handler in handlers.lock().unwrap().iter_mut() {
+                                handler(&record);
+                            }
+                        }
+                        Message::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            });
+            state.sender = Some(sender);
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let mut state = self.state.lock().unwrap();
+        let Some(sender) = &state.sender else {
+            state.seq += 1;
+            return;
+        };
+        let record = EventRecord {
+            seq: state.seq,
+            mono_ms: state.start.elapsed().as_secs_f64() * 1000.0,
+            time_ms: unix_millis(SystemTime::now()),
+            event,
+        };
+        let _ = sender.send(Message::Record(record));
+        state.seq += 1;
+    }
+
+    /*
+        wait until the events published before are handled, e.g. before the process exits.
+    */
+    pub fn flush(&self) {
+        let Some(sender) = self.state.lock().unwrap().sender.clone() else {
+            return;
+        };
+        let (done, wait) = mpsc::channel();
+        if sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+/*
+    write the events as json lines, each line is flushed so the log is complete even if the process is killed.
+*/
+pub struct EventWriter<W: Write> {
+    writer: W,
+}
+
+impl EventWriter<BufWriter<File>> {
+    /*
+        the events are appended if the file exists.
+    */
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventWriter::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(writer: W) -> Self {
+        EventWriter { writer }
+    }
+
+    pub fn write(&mut self, record: &EventRecord) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send + 'static> EventWriter<W> {
+    pub fn subscribe_to(mut self, bus: &EventBus) {
+        bus.subscribe(move |record| {
+            if let Err(e) = self.write(record) {
+                crate::warn_limited!("write the event log failed:{}", e);
+            }
+        });
+    }
+}
+
+/*
+    The link is lost when no packet is captured in the timeout, and regained at the next packet.
+*/
+pub struct LinkMonitor {
+    timeout: Duration,
+    packet_count: u32,
+    last_packet_time: Instant,
+    lost: bool,
+}
+
+impl LinkMonitor {
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        LinkMonitor {
+            timeout,
+            packet_count: 0,
+            last_packet_time: now,
+            lost: false,
+        }
+    }
+
+    pub fn observe(&mut self, now: Instant, packet_count: u32) -> Option<Event> {
+        if packet_count != self.packet_count {
+            self.packet_count = packet_count;
+            let lost_time = now.saturating_duration_since(self.last_packet_time);
+            self.last_packet_time = now;
+            if self.lost {
+                self.lost = false;
+                return Some(Event::LinkRegained {
+                    lost_ms: lost_time.as_millis() as u64,
+                });
+            }
+        } else if !self.lost && now.saturating_duration_since(self.last_packet_time) >= self.timeout
+        {
+            self.lost = true;
+            return Some(Event::LinkLost {
+                timeout_ms: self.timeout.as_millis() as u64,
+            });
+        }
+        None
+    }
+
+    /*
+        check the shared stats several times in the timeout, it never returns.
+        The link is not lost before the first packet.
+    */
+    pub fn run(mut self, stats: Arc<RwLock<ConnectStats>>, events: EventBus) {
+        self.packet_count = stats.read().unwrap().packet_count;
+        loop {
+            std::thread::sleep(self.timeout / 4);
+            let packet_count = stats.read().unwrap().packet_count;
+            if packet_count == 0 {
+                continue;
+            }
+            if let Some(event) = self.observe(Instant::now(), packet_count) {
+                events.publish(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            sync::{Arc, Mutex},
+            time::{Duration, Instant},
+        };
+
+        use crate::{
+            air_config::AirConfig,
+            events::{Event, EventBus, EventWriter, LinkMonitor},
+            stats::LinkReport,
+        };
+
+        #[test]
+        fn test_event_log() {
+            let bus = EventBus::new();
+            bus.publish(Event::LinkLost { timeout_ms: 1000 }); // nobody subscribes
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let received_rx = received.clone();
+            bus.subscribe(move |record| received_rx.lock().unwrap().push(record.clone()));
+            bus.publish(Event::SessionStart {
+                device: "wlan0".to_string(),
+                version: "0.1.0",
+                air: AirConfig::default(),
+            });
+            bus.publish(Event::Stats(LinkReport::default()));
+            bus.flush();
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0].seq, 1);
+            assert!(received[1].mono_ms >= received[0].mono_ms);
+
+            let mut writer = EventWriter::new(Vec::new());
+            for record in received.iter() {
+                writer.write(record).unwrap();
+            }
+            let out = String::from_utf8(writer.into_inner()).unwrap();
+            let lines: Vec<serde_json::Value> = out
+                .lines()
+                .map(|x| serde_json::from_str(x).unwrap())
+                .collect();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(lines[0]["event"], "session_start");
+            assert_eq!(lines[0]["device"], "wlan0");
+            assert!(lines[0]["time_ms"].as_u64().unwrap() > 0);
+            assert_eq!(lines[1]["event"], "stats");
+            assert_eq!(lines[1]["seq"], 2);
+            assert_eq!(lines[1]["packets"], 0);
+        }
+
+        #[test]
+        fn test_publish_in_handler() {
+            let bus = EventBus::new();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let received_rx = received.clone();
+            let bus_rx = bus.clone();
+            // the publisher holds its lock while publishing, and the handler takes it
+            let lock = Arc::new(Mutex::new(()));
+            let lock_rx = lock.clone();
+            bus.subscribe(move |record| {
+                let _guard = lock_rx.lock().unwrap();
+                received_rx.lock().unwrap().push(record.event.clone());
+                if let Event::LinkLost { .. } = record.event {
+                    bus_rx.publish(Event::LinkRegained { lost_ms: 10 });
+                }
+            });
+            {
+                let _guard = lock.lock().unwrap();
+                bus.publish(Event::LinkLost { timeout_ms: 1000 });
+            }
+            bus.flush();
+            bus.flush(); // the event published by the handler is behind the first flush
+            assert_eq!(
+                *received.lock().unwrap(),
+                vec![
+                    Event::LinkLost { timeout_ms: 1000 },
+                    Event::LinkRegained { lost_ms: 10 }
+                ]
+            );
+        }
+
+        #[test]
+        fn test_link_monitor() {
+            let start = Instant::now();
+            let ms = |x: u64| start + Duration::from_millis(x);
+            let mut monitor = LinkMonitor::new(Duration::from_secs(1), start);
+            assert_eq!(monitor.observe(ms(500), 10), None);
+            assert_eq!(monitor.observe(ms(1400), 10), None);
+            assert_eq!(
+                monitor.observe(ms(1500), 10),
+                Some(Event::LinkLost { timeout_ms: 1000 })
+            );
+            assert_eq!(monitor.observe(ms(3000), 10), None);
+            assert_eq!(
+                monitor.observe(ms(3500), 11),
+                Some(Event::LinkRegained { lost_ms: 3000 })
+            );
+            assert_eq!(monitor.observe(ms(3600), 12), None);
+        }
+    }
+}
@@ -1,11 +1,44 @@
 use std::{
+    error::Error,
+    fmt::Display,
     net::{Ipv4Addr, SocketAddr, UdpSocket},
-    slice,
+    time::{Duration, Instant},
 };
 
+use tx_params::TxParams;
 use zfec_rs::Fec;
 
-use crate::{packet_h_bind::Ground2Air_Config_Packet, VtxPacketHeader, VTX_PACKET_HEADER_SIZE};
+use crate::{
+    packet_h_bind::{
+        Ground2Air_Config_Packet, GROUND2AIR_DATA_MAX_SIZE, WLAN_IEEE_HEADER_GROUND2AIR,
+    },
+    protocol::ProtocolVersion,
+    VtxPacketHeader, VTX_PACKET_HEADER_SIZE,
+};
+
+pub mod tx_params;
+
+// the max payload of a ground2air data packet of every protocol version,
+// every uplink packet is padded to GROUND2AIR_DATA_MAX_SIZE
+pub const GROUND2AIR_DATA_PAYLOAD_MAX_SIZE: usize =
+    GROUND2AIR_DATA_MAX_SIZE - ProtocolVersion::V1.ground2air_header_size();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InjectError {
+    PayloadTooLarge { len: usize, max: usize },
+}
+
+impl Display for InjectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InjectError::PayloadTooLarge { len, max } => {
+                write!(f, "uplink payload too large: {} > {}", len, max)
+            }
+        }
+    }
+}
+
+impl Error for InjectError {}
 
 /*
    To prevent misunderstanding, "send data to air frame" would be named as inject here.
@@ -17,30 +50,15 @@ pub struct InjectHandler {
     cur_block_index: u32,
     packet_cnt: u32,
     raw_data: Vec<u8>,
+    tx_params: TxParams,
+    protocol: ProtocolVersion,
+    flush_timeout: Option<Duration>,
+    block_start: Option<Instant>, // when the first packet of the current block is pushed
 }
 
 type VtxPacketHeaderRaw = Vec<u8>;
 type VtxPacketRaw = Vec<u8>;
 
-#[derive(Default)]
-#[repr(C, packed)]
-struct RadiotapHeader {
-    version: u8,
-    pad: u8,
-    it_len: u16,
-    it_present: u32,
-}
-
-const WLAN_IEEE_HEADER_GROUND2AIR: [u8; 24] = [
-    0x08, 0x01, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11,
-    0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x10, 0x86,
-];
-
-unsafe fn to_u8_slice<T>(origin: &T) -> &[u8] {
-    let ptr = origin as *const T as *const u8;
-    slice::from_raw_parts(ptr, std::mem::size_of::<T>())
-}
-
 impl InjectHandler {
     pub fn new(fec_k: u32, fec_n: u32) -> Self {
         InjectHandler {
@@ -50,6 +68,10 @@ impl InjectHandler {
             cur_block_index: 0,
             packet_cnt: 0,
             raw_data: Vec::new(),
+            tx_params: TxParams::default(),
+            protocol: ProtocolVersion::V1,
+            flush_timeout: None,
+            block_start: None,
         }
     }
 
@@ -65,23 +87,17 @@ impl InjectHandler {
         header.0
     }
 
-    fn new_raw_radiotap() -> Vec<u8> {
-        let mut ret = Vec::new();
-        let mut header = RadiotapHeader::default();
-        header.it_present = (1 << 15) | (1 << 17); //  IEEE80211_RADIOTAP_DATA_RETRIES and IEEE80211_RADIOTAP_TX_FLAGS
-        header.it_len = (std::mem::size_of::<RadiotapHeader>() + 3) as u16; // cal the len here, after we add header into vec, we could not edit it easily
-
-        ret.extend_from_slice(unsafe { to_u8_slice(&header) });
-        let f_t_no_ack = 0x08 as u16;
-        ret.extend_from_slice(unsafe { to_u8_slice(&f_t_no_ack) }); // IEEE80211_RADIOTAP_TX_FLAGS
-        ret.push(0x0); //  IEEE80211_RADIOTAP_DATA_RETRIES
+    pub fn set_tx_params(&mut self, tx_params: TxParams) {
+        log::debug!("uplink tx params: {:?}", tx_params);
+        self.tx_params = tx_params;
+    }
 
-        ret
+    pub fn set_protocol(&mut self, protocol: ProtocolVersion) {
+        self.protocol = protocol;
     }
 
-    fn new_inject_packet() -> VtxPacketRaw {
-        let mut ret = Vec::new();
-        ret.append(&mut Self::new_raw_radiotap());
+    fn new_inject_packet(&self) -> VtxPacketRaw {
+        let mut ret = self.tx_params.radiotap_header();
         ret.extend_from_slice(&WLAN_IEEE_HEADER_GROUND2AIR);
 
         ret
@@ -91,68 +107,206 @@ impl InjectHandler {
         &mut self,
         packet: &Ground2Air_Config_Packet,
     ) -> Vec<VtxPacketRaw> {
-        let mut data = unsafe {
-            let ptr = packet as *const Ground2Air_Config_Packet as *const u8;
-            slice::from_raw_parts(ptr, std::mem::size_of::<Ground2Air_Config_Packet>()).to_vec()
-        };
-        data.resize(64, 0);
-        self.push_data(&data)
+        // the config packet is always smaller than the block size, refer the static_assert in packets.h
+        self.push_data(&self.protocol.encode_config_packet(packet))
+            .unwrap_or_default()
+    }
+
+    /*
+        frame an arbitrary payload(rc commands, mavlink...) as a ground2air data packet.
+    */
+    pub fn push_ground2air_data_packet(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<Vec<VtxPacketRaw>, InjectError> {
+        if payload.len() > GROUND2AIR_DATA_PAYLOAD_MAX_SIZE {
+            return Err(InjectError::PayloadTooLarge {
+                len: payload.len(),
+                max: GROUND2AIR_DATA_PAYLOAD_MAX_SIZE,
+            });
+        }
+        self.push_data(&self.protocol.encode_data_packet(payload))
+    }
+
+    /*
+        a partially filled block is flushed when no more data is pushed in this time,
+        so the packets in it are protected by the fec packets. None means never.
+    */
+    pub fn set_flush_timeout(&mut self, timeout: Option<Duration>) {
+        self.flush_timeout = timeout;
+    }
+
+    /*
+        when the current block should be flushed, None if there is nothing to flush.
+    */
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        Some(self.block_start? + self.flush_timeout?)
+    }
+
+    /*
+        flush the current block if the deadline is passed.
+    */
+    pub fn poll(&mut self, now: Instant) -> Vec<VtxPacketRaw> {
+        match self.flush_deadline() {
+            Some(deadline) if deadline <= now => self.flush(),
+            _ => Vec::new(),
+        }
     }
-    pub fn push_data(&mut self, data: &[u8]) -> Vec<VtxPacketRaw> {
-        assert_eq!(
-            self.packet_cnt * data.len() as u32,
-            self.raw_data.len() as u32
-        ); // to make sure all the packet size is the same
-        self.raw_data.extend_from_slice(data);
+
+    /*
+        fill the rest of the current block with zero packets, and return the fec packets of it.
+        the zero packets themselves are not sent, the air side could recover them but drops them as the size is 0.
+    */
+    pub fn flush(&mut self) -> Vec<VtxPacketRaw> {
+        if self.packet_cnt == 0 {
+            return Vec::new();
+        }
+        self.raw_data.resize(self.fec_k as usize * GROUND2AIR_DATA_MAX_SIZE, 0);
+        self.encode_block(self.fec_k)
+    }
+
+    /*
+        the data is padded to GROUND2AIR_DATA_MAX_SIZE, as all the packets in a block must be the same size.
+    */
+    pub fn push_data(&mut self, data: &[u8]) -> Result<Vec<VtxPacketRaw>, InjectError> {
+        if data.len() > GROUND2AIR_DATA_MAX_SIZE {
+            return Err(InjectError::PayloadTooLarge {
+                len: data.len(),
+                max: GROUND2AIR_DATA_MAX_SIZE,
+            });
+        }
+        let mut data = data.to_vec();
+        data.resize(GROUND2AIR_DATA_MAX_SIZE, 0);
+        self.raw_data.extend_from_slice(&data);
 
         if self.packet_cnt == self.fec_k - 1 {
             // we can start doing fec encode when recv fec_k packets
             // handle packet id [fec_k - 1 , fec_n)
             // we will get fec packets + 1 normal packet
-            let fec_ret = self.fec.encode(&self.raw_data).unwrap();
-            let mut ret: Vec<VtxPacketRaw> = Vec::new();
-            for mut chunk in fec_ret.0 {
-                if chunk.index < self.fec_k as usize - 1 {
-                    continue;
-                }
-                let mut packet = Self::new_inject_packet();
-                let mut header = Self::new_raw_vtx_packet_header(
-                    self.cur_block_index,
-                    chunk.index as u32,
-                    chunk.data.len() as u16,
-                );
-
-                packet.append(&mut header);
-                packet.append(&mut chunk.data);
-                ret.push(packet);
-            }
-            self.cur_block_index += 1;
-            self.packet_cnt = 0;
-            self.raw_data.clear();
-            return ret;
+            Ok(self.encode_block(self.fec_k - 1))
         } else {
             // we cannot do fec encode now
             // while we can directly return the packet out and inject it
             // just need to make sure the packet afterwards will be the same size.
-            let mut packet: VtxPacketRaw = Self::new_inject_packet();
+            let mut packet: VtxPacketRaw = self.new_inject_packet();
             let mut header = Self::new_raw_vtx_packet_header(
                 self.cur_block_index,
                 self.packet_cnt,
                 (data.len() + VTX_PACKET_HEADER_SIZE) as u16,
             );
             packet.append(&mut header);
-            packet.extend_from_slice(data);
+            packet.append(&mut data);
+            if self.packet_cnt == 0 {
+                self.block_start = Some(Instant::now());
+            }
             self.packet_cnt += 1;
-            vec![packet]
+            Ok(vec![packet])
+        }
+    }
+
+    /*
+        encode the full block in raw_data, return the packets from first_index.
+    */
+    fn encode_block(&mut self, first_index: u32) -> Vec<VtxPacketRaw> {
+        let fec_ret = self.fec.encode(&self.raw_data).unwrap(); // raw_data is always fec_k packets of the same size here
+        let mut ret: Vec<VtxPacketRaw> = Vec::new();
+        for mut chunk in fec_ret.0 {
+            if chunk.index < first_index as usize {
+                continue;
+            }
+            let mut packet = self.new_inject_packet();
+            let mut header = Self::new_raw_vtx_packet_header(
+                self.cur_block_index,
+                chunk.index as u32,
+                chunk.data.len() as u16,
+            );
+
+            packet.append(&mut header);
+            packet.append(&mut chunk.data);
+            ret.push(packet);
         }
+        log::trace!("uplink block {} encoded, {} packets", self.cur_block_index, ret.len());
+        self.cur_block_index += 1;
+        self.packet_cnt = 0;
+        self.block_start = None;
+        self.raw_data.clear();
+        ret
     }
 }
 
 #[cfg(test)]
 mod tests {
     mod unittest {
+        use std::time::Duration;
+
         use zfec_rs::Fec;
 
+        use crate::{
+            inject::{InjectError, InjectHandler, GROUND2AIR_DATA_PAYLOAD_MAX_SIZE},
+            packet_h_bind::GROUND2AIR_DATA_MAX_SIZE,
+            VTX_PACKET_HEADER_SIZE,
+        };
+
+        #[test]
+        fn test_push_data_packet() {
+            let mut handler = InjectHandler::new(2, 3);
+            let packets = handler.push_ground2air_data_packet(&[1, 2, 3]).unwrap();
+            assert_eq!(packets.len(), 1);
+            let packet = &packets[0];
+            let data = &packet[packet.len() - GROUND2AIR_DATA_MAX_SIZE..];
+            assert_eq!(data[0], 0); // Ground2Air_Header_Type_Data
+            assert_eq!(u32::from_le_bytes(data[1..5].try_into().unwrap()), 6 + 3);
+            assert_eq!(&data[6..9], &[1, 2, 3]);
+            let vtx_header = &packet[packet.len() - GROUND2AIR_DATA_MAX_SIZE - VTX_PACKET_HEADER_SIZE..];
+            assert_eq!(vtx_header[3], 0); // packet index
+
+            // the second packet completes the block, with the fec packet
+            let packets = handler.push_ground2air_data_packet(&[4]).unwrap();
+            assert_eq!(packets.len(), 2);
+
+            let ret = handler.push_ground2air_data_packet(&[0; GROUND2AIR_DATA_PAYLOAD_MAX_SIZE + 1]);
+            assert_eq!(
+                ret,
+                Err(InjectError::PayloadTooLarge {
+                    len: GROUND2AIR_DATA_PAYLOAD_MAX_SIZE + 1,
+                    max: GROUND2AIR_DATA_PAYLOAD_MAX_SIZE
+                })
+            );
+        }
+
+        #[test]
+        fn test_flush() {
+            let mut handler = InjectHandler::new(2, 6);
+            assert!(handler.flush().is_empty());
+            assert_eq!(handler.flush_deadline(), None);
+
+            // mixed size payloads are padded to the same size
+            assert_eq!(handler.push_data(&[1; 10]).unwrap().len(), 1);
+            assert_eq!(handler.push_data(&[2; 30]).unwrap().len(), 5);
+            assert!(handler.push_data(&[0; GROUND2AIR_DATA_MAX_SIZE + 1]).is_err());
+
+            handler.set_flush_timeout(Some(Duration::from_millis(20)));
+            handler.push_data(&[3; 10]).unwrap();
+            let deadline = handler.flush_deadline().unwrap();
+            assert!(handler.poll(deadline - Duration::from_millis(1)).is_empty());
+            // only the fec packets of the partially filled block are sent
+            let packets = handler.poll(deadline);
+            assert_eq!(packets.len(), 4);
+            for packet in &packets {
+                let vtx_header = &packet[packet.len() - GROUND2AIR_DATA_MAX_SIZE - VTX_PACKET_HEADER_SIZE..];
+                assert_eq!(vtx_header[0], 1); // block index
+                assert!(vtx_header[3] >= 2); // packet index
+            }
+            assert_eq!(handler.flush_deadline(), None);
+
+            // the zero packet could be recovered from the sent packet and the fec packets
+            let fec = Fec::new(2, 6).unwrap();
+            let mut raw = [3u8; 10].to_vec();
+            raw.resize(GROUND2AIR_DATA_MAX_SIZE * 2, 0);
+            let (chunks, _) = fec.encode(&raw).unwrap();
+            assert_eq!(&packets[0][packets[0].len() - GROUND2AIR_DATA_MAX_SIZE..], &chunks[2].data[..]);
+        }
+
         #[test]
         fn test_fec_encode() {
             let a = [
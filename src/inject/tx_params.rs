@@ -0,0 +1,273 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/*
+    Radiotap TX fields of the injected packets, so the uplink could use a robust low rate
+    while the downlink uses a high one. Whether a field is honored depends on the driver.
+
+    format of the rate in cli and config: "6m"(legacy rate in Mbps), "mcs1", "mcs1:40:sgi:stbc"
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TxRate {
+    Legacy(u8), // in 500kbps
+    Mcs {
+        index: u8,
+        bandwidth_40: bool,
+        short_gi: bool,
+        stbc: u8, // number of stbc streams, 0..=3
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TxParams {
+    pub rate: Option<TxRate>,
+    pub tx_power: Option<i8>, // dBm
+    pub retries: u8,
+    pub no_ack: bool,
+}
+
+impl Default for TxParams {
+    fn default() -> Self {
+        TxParams {
+            rate: None,
+            tx_power: None,
+            retries: 0,
+            no_ack: true,
+        }
+    }
+}
+
+// the present bits and fields, refer https://www.radiotap.org/fields/defined
+const RADIOTAP_RATE: u32 = 2;
+const RADIOTAP_DBM_TX_POWER: u32 = 10;
+const RADIOTAP_TX_FLAGS: u32 = 15;
+const RADIOTAP_DATA_RETRIES: u32 = 17;
+const RADIOTAP_MCS: u32 = 19;
+
+const TX_FLAGS_NO_ACK: u16 = 0x08;
+
+const MCS_KNOWN_BW: u8 = 0x01;
+const MCS_KNOWN_MCS: u8 = 0x02;
+const MCS_KNOWN_GI: u8 = 0x04;
+const MCS_KNOWN_STBC: u8 = 0x20;
+const MCS_FLAGS_BW_40: u8 = 0x01;
+const MCS_FLAGS_SHORT_GI: u8 = 0x04;
+const MCS_FLAGS_STBC_SHIFT: u8 = 5;
+
+const RADIOTAP_HEADER_SIZE: usize = 8;
+
+struct RadiotapWriter {
+    present: u32,
+    fields: Vec<u8>,
+}
+
+impl RadiotapWriter {
+    /*
+        the fields must be added in the order of the present bits,
+        each field is aligned to its natural size from the beginning of the header.
+    */
+    fn add(&mut self, bit: u32, align: usize, data: &[u8]) {
+        debug_assert!(self.present >> bit == 0);
+        while !(RADIOTAP_HEADER_SIZE + self.fields.len()).is_multiple_of(align) {
+            self.fields.push(0);
+        }
+        self.present |= 1 << bit;
+        self.fields.extend_from_slice(data);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(RADIOTAP_HEADER_SIZE + self.fields.len());
+        ret.push(0); // version
+        ret.push(0); // pad
+        ret.extend_from_slice(&((RADIOTAP_HEADER_SIZE + self.fields.len()) as u16).to_le_bytes());
+        ret.extend_from_slice(&self.present.to_le_bytes());
+        ret.extend_from_slice(&self.fields);
+        ret
+    }
+}
+
+impl TxParams {
+    pub fn radiotap_header(&self) -> Vec<u8> {
+        let mut writer = RadiotapWriter {
+            present: 0,
+            fields: Vec::new(),
+        };
+        if let Some(TxRate::Legacy(rate)) = self.rate {
+            writer.add(RADIOTAP_RATE, 1, &[rate]);
+        }
+        if let Some(power) = self.tx_power {
+            writer.add(RADIOTAP_DBM_TX_POWER, 1, &[power as u8]);
+        }
+        let tx_flags = if self.no_ack { TX_FLAGS_NO_ACK } else { 0 };
+        writer.add(RADIOTAP_TX_FLAGS, 2, &tx_flags.to_le_bytes());
+        writer.add(RADIOTAP_DATA_RETRIES, 1, &[self.retries]);
+        if let Some(TxRate::Mcs {
+            index,
+            bandwidth_40,
+            short_gi,
+            stbc,
+        }) = self.rate
+        {
+            let mut known = MCS_KNOWN_BW | MCS_KNOWN_MCS | MCS_KNOWN_GI;
+            let mut flags = 0;
+            if bandwidth_40 {
+                flags |= MCS_FLAGS_BW_40;
+            }
+            if short_gi {
+                flags |= MCS_FLAGS_SHORT_GI;
+            }
+            if stbc > 0 {
+                known |= MCS_KNOWN_STBC;
+                flags |= stbc.min(3) << MCS_FLAGS_STBC_SHIFT;
+            }
+            writer.add(RADIOTAP_MCS, 1, &[known, flags, index]);
+        }
+        writer.finish()
+    }
+}
+
+impl FromStr for TxRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid tx rate:{}, should be like 6m, mcs1 or mcs1:40:sgi:stbc", s);
+        let s_lower = s.to_ascii_lowercase();
+        if let Some(mbps) = s_lower.strip_suffix('m') {
+            let mbps: f32 = mbps.parse().map_err(|_| invalid())?;
+            let rate = (mbps * 2.0).round();
+            if !(1.0..=127.0).contains(&rate) {
+                return Err(invalid());
+            }
+            return Ok(TxRate::Legacy(rate as u8));
+        }
+
+        let mut parts = s_lower.split(':');
+        let index = parts
+            .next()
+            .and_then(|x| x.strip_prefix("mcs"))
+            .and_then(|x| x.parse::<u8>().ok())
+            .filter(|x| *x <= 31)
+            .ok_or_else(invalid)?;
+        let mut ret = (index, false, false, 0);
+        for part in parts {
+            match part {
+                "20" => ret.1 = false,
+                "40" => ret.1 = true,
+                "sgi" => ret.2 = true,
+                "stbc" => ret.3 = 1,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(TxRate::Mcs {
+            index: ret.0,
+            bandwidth_40: ret.1,
+            short_gi: ret.2,
+            stbc: ret.3,
+        })
+    }
+}
+
+impl Display for TxRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TxRate::Legacy(rate) => write!(f, "{}m", rate as f32 / 2.0),
+            TxRate::Mcs {
+                index,
+                bandwidth_40,
+                short_gi,
+                stbc,
+            } => {
+                write!(f, "mcs{}", index)?;
+                if bandwidth_40 {
+                    write!(f, ":40")?;
+                }
+                if short_gi {
+                    write!(f, ":sgi")?;
+                }
+                if stbc > 0 {
+                    write!(f, ":stbc")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for TxRate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TxRate> for String {
+    fn from(value: TxRate) -> String {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use radiotap::{field::ext::GuardInterval, Radiotap};
+
+        use crate::inject::tx_params::{TxParams, TxRate};
+
+        #[test]
+        fn test_default_header() {
+            // the same as the header before tx params are configurable
+            assert_eq!(
+                TxParams::default().radiotap_header(),
+                [0, 0, 11, 0, 0x00, 0x80, 0x02, 0x00, 0x08, 0x00, 0x00]
+            );
+        }
+
+        #[test]
+        fn test_legacy_rate() {
+            let params = TxParams {
+                rate: Some("6m".parse().unwrap()),
+                tx_power: Some(10),
+                retries: 2,
+                ..Default::default()
+            };
+            let header = params.radiotap_header();
+            // rate, tx power, then tx flags aligned to 2
+            assert_eq!(&header[8..], &[12, 10, 0x08, 0x00, 2]);
+            let parsed = Radiotap::from_bytes(&header).unwrap();
+            assert_eq!(parsed.rate.unwrap().value, 6.0);
+            assert_eq!(parsed.tx_power.unwrap().value, 10);
+            assert!(parsed.tx_flags.unwrap().no_ack);
+            assert_eq!(parsed.data_retries.unwrap().value, 2);
+
+            // tx flags after a single byte field needs padding
+            let params = TxParams {
+                rate: Some(TxRate::Legacy(2)),
+                ..Default::default()
+            };
+            assert_eq!(&params.radiotap_header()[8..], &[2, 0, 0x08, 0x00, 0]);
+        }
+
+        #[test]
+        fn test_mcs_rate() {
+            let rate: TxRate = "mcs1:40:sgi:stbc".parse().unwrap();
+            assert_eq!(rate.to_string(), "mcs1:40:sgi:stbc");
+            let params = TxParams {
+                rate: Some(rate),
+                ..Default::default()
+            };
+            let parsed = Radiotap::from_bytes(&params.radiotap_header()).unwrap();
+            let mcs = parsed.mcs.unwrap();
+            assert_eq!(mcs.index, Some(1));
+            assert_eq!(mcs.bw.unwrap().bandwidth, 40);
+            assert_eq!(mcs.gi, Some(GuardInterval::Short));
+            assert_eq!(mcs.stbc, Some(1));
+
+            assert!("mcs32".parse::<TxRate>().is_err());
+            assert!("fast".parse::<TxRate>().is_err());
+            assert_eq!("5.5m".parse::<TxRate>(), Ok(TxRate::Legacy(11)));
+        }
+    }
+}
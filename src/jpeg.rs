@@ -0,0 +1,266 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/*
+    Validate the jpeg assembled from the air2ground packets before forwarding it,
+    so a corrupted frame never reaches(and crashes) the decoder of clients.
+
+    It checks the SOI/EOI markers, the segment lengths and the SOF dimensions,
+    and finds out the trailing garbage after EOI.
+    The entropy-coded data is not decoded.
+*/
+
+const MARKER_SOI: u8 = 0xd8;
+const MARKER_EOI: u8 = 0xd9;
+const MARKER_SOS: u8 = 0xda;
+const MARKER_TEM: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JpegError {
+    MissingSoi,
+    MissingEoi,
+    BadMarker { offset: usize, byte: u8 },
+    BadSegmentLength { offset: usize, marker: u8 },
+    MissingSof,
+    DimensionMismatch { expected: (u16, u16), actual: (u16, u16) },
+}
+
+impl Display for JpegError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JpegError::MissingSoi => write!(f, "missing SOI marker"),
+            JpegError::MissingEoi => write!(f, "missing EOI marker"),
+            JpegError::BadMarker { offset, byte } => {
+                write!(f, "expect a marker at {}, found 0x{:02x}", offset, byte)
+            }
+            JpegError::BadSegmentLength { offset, marker } => {
+                write!(f, "bad length of segment 0x{:02x} at {}", marker, offset)
+            }
+            JpegError::MissingSof => write!(f, "missing SOF segment"),
+            JpegError::DimensionMismatch { expected, actual } => write!(
+                f,
+                "SOF dimension {}x{} does not match the resolution {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+        }
+    }
+}
+
+impl Error for JpegError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegInfo {
+    pub width: u16,
+    pub height: u16,
+    pub len: usize, // length of the jpeg, including the EOI marker
+    pub trailing_len: usize, // garbage bytes after EOI
+}
+
+/*
+    What to do with a malformed jpeg.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JpegCheck {
+    Off,  // forward the frames as they are
+    Flag, // forward the malformed frames with an error mark, trim the trailing garbage of valid ones
+    Drop, // drop the malformed frames, trim the trailing garbage of valid ones
+}
+
+impl FromStr for JpegCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(JpegCheck::Off),
+            "flag" => Ok(JpegCheck::Flag),
+            "drop" => Ok(JpegCheck::Drop),
+            _ => Err(format!("invalid jpeg check:{}, should be off, flag or drop", s)),
+        }
+    }
+}
+
+fn is_sof(marker: u8) -> bool {
+    // SOF0 - SOF15, except DHT(C4), JPG(C8) and DAC(CC)
+    (0xc0..=0xcf).contains(&marker) && marker != 0xc4 && marker != 0xc8 && marker != 0xcc
+}
+
+fn is_rst(marker: u8) -> bool {
+    (0xd0..=0xd7).contains(&marker)
+}
+
+/*
+    find the end of the entropy-coded data starting from pos,
+    return the offset of the next marker(0xFF of it).
+*/
+fn skip_entropy_coded_data(data: &[u8], mut pos: usize) -> Option<usize> {
+    while pos + 1 < data.len() {
+        if data[pos] == 0xff {
+            let next = data[pos + 1];
+            if next != 0 && next != 0xff && !is_rst(next) {
+                return Some(pos);
+            }
+        }
+        pos += 1;
+    }
+    None
+}
+
+pub fn validate(data: &[u8], expected_size: Option<(u16, u16)>) -> Result<JpegInfo, JpegError> {
+    if data.len() < 2 || data[0] != 0xff || data[1] != MARKER_SOI {
+        return Err(JpegError::MissingSoi);
+    }
+
+    let mut size = None;
+    let mut pos = 2;
+    loop {
+        if pos >= data.len() {
+            return Err(JpegError::MissingEoi);
+        }
+        if data[pos] != 0xff {
+            return Err(JpegError::BadMarker {
+                offset: pos,
+                byte: data[pos],
+            });
+        }
+        // markers could be padded by any number of 0xFF
+        while pos + 1 < data.len() && data[pos + 1] == 0xff {
+            pos += 1;
+        }
+        if pos + 1 >= data.len() {
+            return Err(JpegError::MissingEoi);
+        }
+
+        let marker_offset = pos;
+        let marker = data[pos + 1];
+        pos += 2;
+        if marker == MARKER_EOI {
+            break;
+        }
+        if is_rst(marker) || marker == MARKER_TEM {
+            continue;
+        }
+        if marker == MARKER_SOI || marker == 0x00 {
+            return Err(JpegError::BadMarker {
+                offset: marker_offset,
+                byte: marker,
+            });
+        }
+
+        if pos + 2 > data.len() {
+            return Err(JpegError::MissingEoi);
+        }
+        let seg_len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
+        if seg_len < 2 || pos + seg_len > data.len() {
+            return Err(JpegError::BadSegmentLength {
+                offset: marker_offset,
+                marker,
+            });
+        }
+
+        if is_sof(marker) {
+            // length(2) | precision(1) | height(2) | width(2) | ...
+            if seg_len < 7 {
+                return Err(JpegError::BadSegmentLength {
+                    offset: marker_offset,
+                    marker,
+                });
+            }
+            let height = u16::from_be_bytes([data[pos + 3], data[pos + 4]]);
+            let width = u16::from_be_bytes([data[pos + 5], data[pos + 6]]);
+            size = Some((width, height));
+        }
+        pos += seg_len;
+
+        if marker == MARKER_SOS {
+            pos = skip_entropy_coded_data(data, pos).ok_or(JpegError::MissingEoi)?;
+        }
+    }
+
+    let (width, height) = size.ok_or(JpegError::MissingSof)?;
+    if let Some(expected) = expected_size {
+        if expected != (width, height) {
+            return Err(JpegError::DimensionMismatch {
+                expected,
+                actual: (width, height),
+            });
+        }
+    }
+
+    Ok(JpegInfo {
+        width,
+        height,
+        len: pos,
+        trailing_len: data.len() - pos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use crate::jpeg::*;
+
+        fn new_jpeg(width: u16, height: u16) -> Vec<u8> {
+            let mut ret = vec![0xff, 0xd8];
+            // APP0
+            ret.extend_from_slice(&[0xff, 0xe0, 0x00, 0x04, 0x4a, 0x46]);
+            // SOF0
+            ret.extend_from_slice(&[0xff, 0xc0, 0x00, 0x0b, 0x08]);
+            ret.extend_from_slice(&height.to_be_bytes());
+            ret.extend_from_slice(&width.to_be_bytes());
+            ret.extend_from_slice(&[0x01, 0x01, 0x11, 0x00]);
+            // SOS with entropy-coded data, including a stuffed 0xFF and a RST marker
+            ret.extend_from_slice(&[0xff, 0xda, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3f, 0x00]);
+            ret.extend_from_slice(&[0x12, 0xff, 0x00, 0x34, 0xff, 0xd0, 0x56]);
+            ret.extend_from_slice(&[0xff, 0xd9]);
+            ret
+        }
+
+        #[test]
+        fn test_validate() {
+            let jpeg = new_jpeg(640, 480);
+            let info = validate(&jpeg, Some((640, 480))).unwrap();
+            assert_eq!((info.width, info.height), (640, 480));
+            assert_eq!(info.len, jpeg.len());
+            assert_eq!(info.trailing_len, 0);
+        }
+
+        #[test]
+        fn test_trailing_garbage() {
+            let mut jpeg = new_jpeg(320, 240);
+            let len = jpeg.len();
+            jpeg.resize(len + 100, 0);
+            let info = validate(&jpeg, None).unwrap();
+            assert_eq!(info.len, len);
+            assert_eq!(info.trailing_len, 100);
+        }
+
+        #[test]
+        fn test_malformed() {
+            let jpeg = new_jpeg(640, 480);
+            assert_eq!(validate(&jpeg[1..], None), Err(JpegError::MissingSoi));
+            assert_eq!(
+                validate(&jpeg[..jpeg.len() - 2], None),
+                Err(JpegError::MissingEoi)
+            );
+            assert_eq!(
+                validate(&jpeg, Some((320, 240))),
+                Err(JpegError::DimensionMismatch {
+                    expected: (320, 240),
+                    actual: (640, 480)
+                })
+            );
+
+            let mut bad_len = jpeg.clone();
+            bad_len[5] = 0xff; // length of APP0
+            assert_eq!(
+                validate(&bad_len, None),
+                Err(JpegError::BadSegmentLength {
+                    offset: 2,
+                    marker: 0xe0
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::Frame;
+
+/*
+    Latency of the stages a frame passes through the ground station:
+        packets:  the first packet of the frame captured -> the last one captured(pcap header timestamps)
+        decode:   the last packet captured -> the last block of the frame decoded
+        assemble: the last block decoded -> the frame completed
+        output:   the frame completed -> sent by an output, including the time in the queue
+        total:    the first packet captured -> sent by an output
+    output and total are per output.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameLatency {
+    pub packets: Option<Duration>,
+    pub decode: Option<Duration>,
+    pub assemble: Option<Duration>,
+    pub output: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+fn between(from: Option<SystemTime>, to: Option<SystemTime>) -> Option<Duration> {
+    // the pcap timestamps are taken by the kernel, a tiny clock skew is taken as 0
+    Some(to?.duration_since(from?).unwrap_or_default())
+}
+
+impl FrameLatency {
+    /*
+        sent is the time the frame is sent by an output, None for the stages before the outputs.
+    */
+    pub fn new(frame: &Frame, sent: Option<SystemTime>) -> Self {
+        let timestamps = &frame.timestamps;
+        FrameLatency {
+            packets: between(timestamps.first_packet, timestamps.last_packet),
+            decode: between(timestamps.last_packet, timestamps.block_decoded),
+            assemble: between(timestamps.block_decoded, frame.complete_time),
+            output: between(frame.complete_time, sent),
+            total: between(timestamps.first_packet, sent),
+        }
+    }
+}
+
+impl std::fmt::Display for FrameLatency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let stages = [
+            ("packets", self.packets),
+            ("decode", self.decode),
+            ("assemble", self.assemble),
+            ("output", self.output),
+            ("total", self.total),
+        ];
+        let mut first = true;
+        for (name, latency) in stages {
+            if let Some(latency) = latency {
+                let sep = if first { "" } else { " " };
+                write!(f, "{}{}:{:.2}ms", sep, name, latency.as_secs_f64() * 1000.0)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+// upper bounds of the buckets, the last bucket is +Inf
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(20),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, latency: Duration) {
+        let index = LATENCY_BUCKETS.partition_point(|x| *x < latency);
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /*
+        the cumulative counts of the buckets with their upper bounds, None for +Inf, as the prometheus histograms.
+    */
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        let bounds = LATENCY_BUCKETS.iter().map(|x| Some(*x)).chain([None]);
+        bounds.zip(self.buckets.iter().scan(0, |acc, x| {
+            *acc += x;
+            Some(*acc)
+        }))
+    }
+
+    /*
+        the upper bound of the bucket the quantile falls in, or the max if it's in the last bucket.
+    */
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64 * q).ceil() as u64).max(1);
+        self.buckets()
+            .find(|(_, count)| *count >= rank)
+            .map(|(bound, _)| bound.unwrap_or(self.max).min(self.max))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/*
+    the histograms of the stages since started, keyed by the stage and the output(empty for the stages before the outputs).
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    histograms: BTreeMap<(&'static str, String), LatencyHistogram>,
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn observe(&mut self, stage: &'static str, output: &str, latency: Option<Duration>) {
+        if let Some(latency) = latency {
+            self.histograms
+                .entry((stage, output.to_string()))
+                .or_default()
+                .observe(latency);
+        }
+    }
+
+    /*
+        the stages before the outputs, once for a frame.
+    */
+    pub fn observe_frame(&mut self, latency: &FrameLatency) {
+        self.observe("packets", "", latency.packets);
+        self.observe("decode", "", latency.decode);
+        self.observe("assemble", "", latency.assemble);
+    }
+
+    pub fn observe_output(&mut self, output: &str, latency: &FrameLatency) {
+        self.observe("output", output, latency.output);
+        self.observe("total", output, latency.total);
+    }
+
+    pub fn histograms(&self) -> impl Iterator<Item = (&str, &str, &LatencyHistogram)> {
+        self.histograms
+            .iter()
+            .map(|((stage, output), histogram)| (*stage, output.as_str(), histogram))
+    }
+
+    /*
+        keyed by the stage, or "stage:output" of the output stages.
+    */
+    pub fn summary(&self) -> BTreeMap<String, LatencySummary> {
+        let to_ms = |x: Duration| x.as_secs_f64() * 1000.0;
+        self.histograms()
+            .map(|(stage, output, histogram)| {
+                let key = if output.is_empty() {
+                    stage.to_string()
+                } else {
+                    format!("{}:{}", stage, output)
+                };
+                let summary = LatencySummary {
+                    count: histogram.count(),
+                    avg_ms: to_ms(histogram.sum()) / histogram.count() as f64,
+                    p50_ms: histogram.quantile(0.5).map(to_ms).unwrap_or_default(),
+                    p99_ms: histogram.quantile(0.99).map(to_ms).unwrap_or_default(),
+                    max_ms: to_ms(histogram.max()),
+                };
+                (key, summary)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::time::{Duration, SystemTime};
+
+        use crate::{
+            latency::{FrameLatency, LatencyHistogram, LatencyStats},
+            Frame,
+        };
+
+        #[test]
+        fn test_histogram() {
+            let mut histogram = LatencyHistogram::default();
+            assert_eq!(histogram.quantile(0.5), None);
+            for ms in [1, 3, 3, 4, 30] {
+                histogram.observe(Duration::from_millis(ms));
+            }
+            assert_eq!(histogram.count(), 5);
+            assert_eq!(histogram.sum(), Duration::from_millis(41));
+            assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(5)));
+            assert_eq!(histogram.quantile(0.99), Some(Duration::from_millis(30)));
+            let buckets: Vec<_> = histogram.buckets().collect();
+            assert_eq!(buckets[1], (Some(Duration::from_millis(1)), 1)); // le is inclusive
+            assert_eq!(buckets[3], (Some(Duration::from_millis(5)), 4));
+            assert_eq!(buckets.last(), Some(&(None, 5)));
+        }
+
+        #[test]
+        fn test_frame_latency() {
+            let start = SystemTime::now();
+            let ms = |x: u64| start + Duration::from_millis(x);
+            let mut frame = Frame::new(1);
+            frame.timestamps.first_packet = Some(ms(0));
+            frame.timestamps.last_packet = Some(ms(2));
+            frame.timestamps.block_decoded = Some(ms(5));
+            frame.complete_time = Some(ms(6));
+
+            let latency = FrameLatency::new(&frame, Some(ms(10)));
+            assert_eq!(latency.packets, Some(Duration::from_millis(2)));
+            assert_eq!(latency.decode, Some(Duration::from_millis(3)));
+            assert_eq!(latency.assemble, Some(Duration::from_millis(1)));
+            assert_eq!(latency.output, Some(Duration::from_millis(4)));
+            assert_eq!(latency.total, Some(Duration::from_millis(10)));
+            assert_eq!(FrameLatency::new(&frame, None).total, None);
+
+            let mut stats = LatencyStats::new();
+            stats.observe_frame(&latency);
+            stats.observe_output("udp", &latency);
+            let summary = stats.summary();
+            assert_eq!(summary["decode"].count, 1);
+            assert_eq!(summary["total:udp"].max_ms, 10.0);
+            assert!(!summary.contains_key("total"));
+        }
+    }
+}
@@ -1,21 +1,47 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::Debug,
-    mem::{size_of, MaybeUninit},
-    time::SystemTime,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use bitfield::bitfield;
+use codec::Codec;
+use events::{Event, EventBus};
+use jpeg::{JpegCheck, JpegError};
 use packet::Air2GroundFramePacket;
-use packet_h_bind::WLAN_IEEE_HEADER_SIZE;
+use packet_h_bind::{
+    Air2Ground_Header_Type_Telemetry, Air2Ground_Header_Type_Video, Resolution,
+    WLAN_IEEE_HEADER_SIZE,
+};
 use pcap::{Packet};
-use radiotap::Radiotap;
+use protocol::{ProtocolDetector, ProtocolVersion};
+use radiotap::{field::Kind, Radiotap, RadiotapIterator};
 use zfec_rs::{Chunk, Fec};
+pub mod air_config;
+pub mod blackbox;
+pub mod codec;
+pub mod config_tracker;
+pub mod control;
 pub mod device;
+pub mod events;
 
 pub mod inject;
+pub mod jpeg;
+pub mod latency;
+pub mod logger;
+pub mod metrics;
+pub mod osd;
+pub mod output;
 mod packet;
 pub mod packet_h_bind;
+pub mod protocol;
+pub mod serial_bridge;
+pub mod settings;
+pub mod stats;
+pub mod stream_header;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod uplink;
 
 pub const VTX_PACKET_HEADER_SIZE:usize = 6;
 bitfield! {
@@ -40,7 +66,7 @@ pub struct VtxPacket {
 
 impl VtxPacket {
     fn from(payload: &[u8], fcs_enable: bool, fec_n: u32) -> Option<Self> {
-        let header = VtxPacketHeader(payload[..VTX_PACKET_HEADER_SIZE].to_vec());
+        let header = VtxPacketHeader::decode(payload).ok()?;
 
         if header.packet_index() >= fec_n {
             return None;
@@ -63,6 +89,7 @@ pub struct Block {
     pub packets: HashMap<u32, VtxPacket>,
     pub fec_packets: Vec<VtxPacket>,
     pub index: u32,
+    pub timestamps: Timestamps,
 }
 
 impl Block {
@@ -71,14 +98,51 @@ impl Block {
             packets: HashMap::new(),
             fec_packets: Vec::new(),
             index,
+            timestamps: Timestamps::default(),
         }
     }
 }
 
+/*
+    When the data of a block or a frame passed the receiver,
+    the packet ones are the capture time in the pcap headers, so the time waiting in the capture buffer is included.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timestamps {
+    pub first_packet: Option<SystemTime>,
+    pub last_packet: Option<SystemTime>,
+    pub block_decoded: Option<SystemTime>, // the last block decoded
+}
+
+impl Timestamps {
+    fn observe_packet(&mut self, time: SystemTime) {
+        self.first_packet = Some(self.first_packet.map_or(time, |x| x.min(time)));
+        self.last_packet = Some(self.last_packet.map_or(time, |x| x.max(time)));
+    }
+
+    /*
+        merge the ones of a block into the ones of its frame.
+    */
+    fn merge(&mut self, other: &Timestamps) {
+        self.first_packet = match (self.first_packet, other.first_packet) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_packet = self.last_packet.max(other.last_packet);
+        self.block_decoded = self.block_decoded.max(other.block_decoded);
+    }
+}
+
 pub struct Frame {
     parts: BTreeMap<u8, Air2GroundFramePacket>,
     frame_index: u32,
     parts_count: u8,
+    jpeg_len: Option<usize>, // valid length of the jpeg data, set by the jpeg check
+    pub jpeg_error: Option<JpegError>,
+    pub recv_time: SystemTime,
+    pub complete_time: Option<SystemTime>,
+    pub timestamps: Timestamps, // of the blocks carrying the frame
+    pub link_stats: ConnectStats, // snapshot of the stats when the frame is completed
 }
 
 impl Frame {
@@ -87,17 +151,61 @@ impl Frame {
             parts: BTreeMap::new(),
             frame_index,
             parts_count: 0,
+            jpeg_len: None,
+            jpeg_error: None,
+            recv_time: SystemTime::now(),
+            complete_time: None,
+            timestamps: Timestamps::default(),
+            link_stats: ConnectStats::new(),
         }
     }
 
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    pub fn parts_count(&self) -> u8 {
+        self.parts_count
+    }
+
+    pub fn resolution(&self) -> Option<Resolution> {
+        self.parts.values().next().map(|x| x.header.resolution)
+    }
+
+    // the ping of the last config packet received by the air unit
+    pub fn pong(&self) -> Option<u8> {
+        self.parts.values().next_back().map(|x| x.header._base.pong)
+    }
+
+    // the pcap timestamp of the first packet of the frame, or the time its first part is received
+    pub fn capture_time(&self) -> SystemTime {
+        self.timestamps.first_packet.unwrap_or(self.recv_time)
+    }
+
+    // from the first part received to the frame completed
+    pub fn latency(&self) -> Option<Duration> {
+        self.complete_time?.duration_since(self.recv_time).ok()
+    }
+
+    // the length of get_jpegdata, without copying the data
+    pub fn jpeg_size(&self) -> usize {
+        let size = self.parts.values().map(|x| x.data.len()).sum();
+        self.jpeg_len.map_or(size, |x| x.min(size))
+    }
+
     pub fn get_jpegdata(&self) -> Vec<u8> {
         let mut ret = Vec::new();
         for (_, air2ground_frame_packet) in &self.parts {
             ret.append(&mut air2ground_frame_packet.data.clone());
         }
+        if let Some(len) = self.jpeg_len {
+            ret.truncate(len);
+        }
         ret
     }
 }
+type TelemetryCallback = Box<dyn FnMut(&[u8])>;
+
 pub struct CapHandler {
     pub blocks: BTreeMap<u32, Block>,
     pub frames: BTreeMap<u32, Frame>,
@@ -107,23 +215,96 @@ pub struct CapHandler {
     pub finish_frame_index: u32,
     pub current_process_block_index: u32,
     callback: Option<Box<dyn FnMut(Frame)>>,
+    telemetry_callback: Option<TelemetryCallback>,
+    jpeg_check: JpegCheck,
+    max_packet_index: Option<u32>, // of all packets, including the ones beyond fec_n
+    protocol: ProtocolVersion,
+    protocol_detector: Option<ProtocolDetector>, // None when the protocol is fixed or detected
+    decoded_timestamps: Option<Timestamps>, // of the last block decoded by process_block, for process_air2ground_packets
+    last_block_index: Option<u32>, // the max index of the blocks decoded, to find the blocks never received
+    events: EventBus,
     pub stats:ConnectStats
 }
 
+#[derive(Clone, Debug)]
 pub struct ConnectStats {
     pub start_time: SystemTime,
-    pub broken_block_count: u32,
+    pub broken_block_count: u32, // lost blocks, never completed
+    pub packet_count: u32,
+    pub filtered_packet_count: u32, // not a vtx packet of the current fec codec
+    pub duplicate_packet_count: u32,
+    pub byte_count: u64, // of all captured packets
+    pub complete_block_count: u32, // all data packets received
+    pub recovered_block_count: u32, // recovered by the fec packets
+    pub frame_count: u32,
+    pub frame_byte_count: u64, // jpeg data of the completed frames
+    pub dropped_frame_count: u32, // incomplete frames dropped when a newer frame is completed
+    pub jpeg_dropped_count: u32, // completed frames dropped by the jpeg check
+    pub crc_error_count: u32, // air2ground packets failed the crc check
+    pub jpeg_error_count: u32,
+    pub jpeg_trimmed_count: u32, // valid jpegs with trailing garbage after EOI
+    pub rssi: Option<i8>, // antenna signal(dBm) of the last captured packet
+    pub antenna_rssi: [Option<i8>; MAX_ANTENNAS], // of each antenna, only reported by the multi-antenna cards
+    pub noise: Option<i8>, // antenna noise(dBm) of the last captured packet, not reported by most cards
 }
 
 impl ConnectStats {
-    fn new() -> Self {
+    pub fn new() -> Self {
         ConnectStats {
             start_time: SystemTime::now(),
             broken_block_count: 0,
+            packet_count: 0,
+            filtered_packet_count: 0,
+            duplicate_packet_count: 0,
+            byte_count: 0,
+            complete_block_count: 0,
+            recovered_block_count: 0,
+            frame_count: 0,
+            frame_byte_count: 0,
+            dropped_frame_count: 0,
+            jpeg_dropped_count: 0,
+            crc_error_count: 0,
+            jpeg_error_count: 0,
+            jpeg_trimmed_count: 0,
+            rssi: None,
+            antenna_rssi: [None; MAX_ANTENNAS],
+            noise: None,
         }
     }
 }
 
+pub const MAX_ANTENNAS: usize = 4;
+
+/*
+    the signal of each antenna, in the extended radiotap namespaces of the multi-antenna cards:
+    the combined signal comes first, then a namespace with the signal and the antenna index for each antenna.
+*/
+fn antenna_signals(data: &[u8]) -> [Option<i8>; MAX_ANTENNAS] {
+    let mut ret = [None; MAX_ANTENNAS];
+    let Ok(iterator) = RadiotapIterator::from_bytes(data) else {
+        return ret;
+    };
+    let mut signal = None;
+    for (kind, value) in iterator.into_iter().map_while(Result::ok) {
+        match kind {
+            Kind::AntennaSignal => signal = value.first().map(|x| *x as i8),
+            Kind::Antenna => {
+                if let (Some(signal), Some(rssi)) = (signal.take(), value.first().and_then(|x| ret.get_mut(*x as usize))) {
+                    *rssi = Some(signal);
+                }
+            }
+            _ => {}
+        }
+    }
+    ret
+}
+
+impl Default for ConnectStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CapHandler {
     pub fn new(fec_k: u32, fec_n: u32) -> Self {
         CapHandler {
@@ -135,10 +316,70 @@ impl CapHandler {
             finish_frame_index: 0,
             current_process_block_index: 0,
             callback: None,
+            telemetry_callback: None,
+            jpeg_check: JpegCheck::Off,
+            max_packet_index: None,
+            protocol: ProtocolVersion::V1,
+            protocol_detector: Some(ProtocolDetector::new()),
+            decoded_timestamps: None,
+            last_block_index: None,
+            events: EventBus::default(),
             stats:ConnectStats::new(),
         }
     }
 
+    /*
+       switch to a new fec codec, the blocks of the old codec are dropped.
+    */
+    pub fn set_fec(&mut self, fec_k: u32, fec_n: u32) {
+        if fec_k == self.fec_k && fec_n == self.fec_n {
+            return;
+        }
+        self.fec_k = fec_k;
+        self.fec_n = fec_n;
+        self.fec = Fec::new(fec_k as usize, fec_n as usize).unwrap();
+        self.blocks.clear();
+    }
+
+    /*
+       the max packet index since the last call, fec_n - 1 of the air side if no packet is lost.
+    */
+    pub fn take_max_packet_index(&mut self) -> Option<u32> {
+        self.max_packet_index.take()
+    }
+
+    /*
+       fix the protocol version of the air unit, or detect it from the packets if None.
+    */
+    pub fn set_protocol(&mut self, protocol: Option<ProtocolVersion>) {
+        match protocol {
+            Some(protocol) => {
+                self.protocol = protocol;
+                self.protocol_detector = None;
+            }
+            None => self.protocol_detector = Some(ProtocolDetector::new()),
+        }
+    }
+
+    /*
+       None if it is still being detected.
+    */
+    pub fn protocol(&self) -> Option<ProtocolVersion> {
+        match self.protocol_detector {
+            Some(_) => None,
+            None => Some(self.protocol),
+        }
+    }
+
+    pub fn set_jpeg_check(&mut self, jpeg_check: JpegCheck) {
+        self.jpeg_check = jpeg_check;
+    }
+
+    // publish the air restarts
+    pub fn set_event_bus(&mut self, events: EventBus) {
+        self.events = events;
+    }
+
     pub fn do_when_recv_new_frame<F>(&mut self, func: F)
     where
         F: FnMut(Frame) + Send + 'static,
@@ -146,11 +387,32 @@ impl CapHandler {
         self.callback = Some(Box::new(func))
     }
 
+    /*
+       the payloads of the air2ground telemetry packets, e.g. the serial data from the flight controller.
+    */
+    pub fn do_when_recv_telemetry<F>(&mut self, func: F)
+    where
+        F: FnMut(&[u8]) + Send + 'static,
+    {
+        self.telemetry_callback = Some(Box::new(func))
+    }
+
     pub fn process_cap_packets(&mut self, packet: Packet) {
         let radiotap = Radiotap::from_bytes(&packet.data).unwrap();
         if radiotap.flags.unwrap().bad_fcs {
             panic!("bad fcs!");
         }
+        self.stats.packet_count += 1;
+        self.stats.byte_count += packet.data.len() as u64;
+        if let Some(signal) = radiotap.antenna_signal {
+            self.stats.rssi = Some(signal.value);
+        }
+        if let Some(noise) = radiotap.antenna_noise {
+            self.stats.noise = Some(noise.value);
+        }
+        if radiotap.antenna.is_some() {
+            self.stats.antenna_rssi = antenna_signals(packet.data);
+        }
         let payload = &packet.data[radiotap.header.length + WLAN_IEEE_HEADER_SIZE..];
         /*
         let payload_valid_len = packet.header.len  // 1540
@@ -159,8 +421,12 @@ impl CapHandler {
         - if radiotap.flags.unwrap().fcs { 4 } else { 0 };
         */
 
+        if let Ok(header) = VtxPacketHeader::decode(payload) {
+            self.max_packet_index = self.max_packet_index.max(Some(header.packet_index()));
+        }
         let vtx_packet = VtxPacket::from(payload, radiotap.flags.unwrap().fcs, self.fec_n);
         if vtx_packet.is_none() {
+            self.stats.filtered_packet_count += 1;
             return;
         }
         let vtx_packet = vtx_packet.unwrap();
@@ -175,6 +441,8 @@ impl CapHandler {
             .blocks
             .get_mut(&vtx_packet.header.block_index())
             .unwrap();
+        let ts = packet.header.ts;
+        block.timestamps.observe_packet(UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_usec as u32 * 1000));
 
         if vtx_packet.header.packet_index() >= self.fec_k {
             if !block
@@ -183,6 +451,8 @@ impl CapHandler {
                 .any(|x| x.header.packet_index() == vtx_packet.header.packet_index())
             {
                 block.fec_packets.push(vtx_packet);
+            } else {
+                self.stats.duplicate_packet_count += 1;
             }
         } else {
             if !block
@@ -192,6 +462,8 @@ impl CapHandler {
                 block
                     .packets
                     .insert(vtx_packet.header.packet_index(), vtx_packet);
+            } else {
+                self.stats.duplicate_packet_count += 1;
             }
         }
     }
@@ -216,6 +488,7 @@ impl CapHandler {
                 entie_out.append(&mut packet.data);
             }
             out = entie_out;
+            self.stats.complete_block_count += 1;
         } else if block.packets.len() + block.fec_packets.len() >= self.fec_k as usize {
             let mut chunks = Vec::<Chunk>::new();
             for i in 0..self.fec_k {
@@ -234,29 +507,114 @@ impl CapHandler {
             }
             let fec_out = self.fec.decode(&chunks, 0).unwrap();
             out = fec_out;
+            self.stats.recovered_block_count += 1;
         } else {
             return None;
         }
         self.current_process_block_index = block_index;
-        self.blocks.remove(&block_index);
+        // the blocks skipped without any packet are lost, the received ones are counted when dropped as stale
+        if let Some(last) = self.last_block_index.filter(|x| block_index > *x) {
+            let received = self.blocks.range(last + 1..block_index).count() as u32;
+            self.stats.broken_block_count += block_index - last - 1 - received;
+        }
+        self.last_block_index = self.last_block_index.max(Some(block_index));
+        let mut timestamps = self.blocks.remove(&block_index).unwrap().timestamps;
+        timestamps.block_decoded = Some(SystemTime::now());
+        self.decoded_timestamps = Some(timestamps);
 
         Some(out)
     }
 
 
+    /*
+       validate the jpeg of a completed frame according to jpeg_check,
+       return false if the frame should be dropped.
+    */
+    fn check_jpeg(&mut self, frame: &mut Frame) -> bool {
+        if self.jpeg_check == JpegCheck::Off {
+            return true;
+        }
+        let expected_size = frame
+            .resolution()
+            .and_then(|x| air_config::Resolution::try_from(x).ok())
+            .map(|x| x.dimensions());
+        match jpeg::validate(&frame.get_jpegdata(), expected_size) {
+            Ok(info) => {
+                if info.trailing_len != 0 {
+                    self.stats.jpeg_trimmed_count += 1;
+                }
+                frame.jpeg_len = Some(info.len);
+                true
+            }
+            Err(e) => {
+                self.stats.jpeg_error_count += 1;
+                frame.jpeg_error = Some(e);
+                if self.jpeg_check == JpegCheck::Drop {
+                    self.stats.jpeg_dropped_count += 1;
+                }
+                self.jpeg_check != JpegCheck::Drop
+            }
+        }
+    }
+
+    /*
+        the data is a block returned by process_block, the frames in it take the timestamps of the block.
+    */
     pub fn process_air2ground_packets(&mut self, data: Vec<u8>) {
         assert_eq!(data.len() % 1470, 0);
+        let timestamps = self.decoded_timestamps.take().unwrap_or_default();
         let mut rest_data = data;
         while rest_data.len() >= 1470 {
             let tmp = rest_data.split_off(1470);
-            let packet = Air2GroundFramePacket::from_bytes(rest_data);
-            rest_data = tmp;
+            let chunk = std::mem::replace(&mut rest_data, tmp);
+            if let Some(detector) = self.protocol_detector.as_mut() {
+                let detected = detector.observe(&chunk);
+                self.protocol = detector.candidate().unwrap_or(self.protocol);
+                if let Some(protocol) = detected {
+                    log::info!("air protocol detected: {}", protocol);
+                    self.protocol_detector = None;
+                    self.events.publish(Event::ProtocolDetected { version: protocol });
+                }
+            }
+            let protocol = self.protocol;
+            let header = match protocol.decode_air2ground_header(&chunk) {
+                Ok(header) => header,
+                Err(e) => {
+                    crate::warn_limited!("invalid air2ground packet: {}", e);
+                    continue;
+                }
+            };
+            if header.type_ == Air2Ground_Header_Type_Telemetry {
+                let header_size = protocol.air2ground_header_size();
+                let end = (header.size as usize).clamp(header_size, chunk.len());
+                if let Some(callback) = self.telemetry_callback.as_mut() {
+                    callback(&chunk[header_size..end]);
+                }
+                continue;
+            }
+            if header.type_ != Air2Ground_Header_Type_Video {
+                continue;
+            }
+            let packet = match Air2GroundFramePacket::decode(protocol, chunk) {
+                Ok(packet) => {
+                    if !packet.crc_ok {
+                        self.stats.crc_error_count += 1;
+                    }
+                    packet
+                }
+                Err(e) => {
+                    crate::warn_limited!("invalid air2ground packet: {}", e);
+                    continue;
+                }
+            };
 
             let frame_index = packet.header.frame_index;
             if frame_index < self.finish_frame_index {
                 // the air side may be restart, so clear blocks and restart
+                self.events.publish(Event::AirRestart { frame_index, last_frame_index: self.finish_frame_index });
                 self.blocks.clear();
                 self.finish_frame_index = 0;
+                self.last_block_index = None;
                 return ;
             }
 
@@ -265,6 +623,7 @@ impl CapHandler {
             }
 
             let frame = self.frames.get_mut(&frame_index).unwrap();
+            frame.timestamps.merge(&timestamps);
             let real_part_index = packet.header.part_index();
             let last_part = packet.header.last_part();
             frame.parts.insert(real_part_index, packet);
@@ -274,9 +633,21 @@ impl CapHandler {
             }
             if frame.parts_count != 0 && frame.parts.len() == frame.parts_count as usize {
                 self.finish_frame_index = frame_index;
+                frame.complete_time = Some(SystemTime::now());
                 if self.callback.is_some() {
-                    (self.callback.as_mut().unwrap())(self.frames.remove(&self.finish_frame_index).unwrap());
+                    let mut frame = self.frames.remove(&self.finish_frame_index).unwrap();
+                    // the frames dropped by the jpeg check are not counted as received
+                    if self.check_jpeg(&mut frame) {
+                        self.stats.frame_count += 1;
+                        self.stats.frame_byte_count += frame.jpeg_size() as u64;
+                        frame.link_stats = self.stats.clone();
+                        (self.callback.as_mut().unwrap())(frame);
+                    }
+                    self.stats.dropped_frame_count += self.frames.len() as u32;
                     self.frames.clear();
+                } else {
+                    self.stats.frame_count += 1;
+                    frame.link_stats = self.stats.clone();
                 }
                 // if self.callback is None, then the frame will not be poped out
                 // let's keep all the frame when no callback apply
@@ -284,6 +655,7 @@ impl CapHandler {
                 while let Some(x) = self.blocks.first_key_value(){
                     if *x.0 <=  self.current_process_block_index {
                         self.blocks.pop_first().unwrap();
+                        self.stats.broken_block_count += 1; // the block is never completed
                     }else{
                         break
                     }
@@ -345,6 +717,7 @@ pub mod tests {
         use std::sync::{Arc, RwLock};
 
         use super::*;
+        use crate::packet_h_bind::Air2Ground_Header;
         #[test]
         fn test_process_air2ground_packet() {
             let mut cap_handler = init_cap_and_recv_packets(40);
@@ -359,6 +732,93 @@ pub mod tests {
             assert_ne!(cap_handler.finish_frame_index, 0);
         }
 
+        #[test]
+        fn test_telemetry_packet() {
+            let mut cap_handler = CapHandler::new(FEC_K as u32, FEC_N as u32);
+            let received = Arc::new(RwLock::new(Vec::new()));
+            let received_copy = received.clone();
+            cap_handler.do_when_recv_telemetry(move |data| {
+                received_copy.write().unwrap().extend_from_slice(data);
+            });
+            let header = Air2Ground_Header {
+                type_: Air2Ground_Header_Type_Telemetry,
+                size: (Air2Ground_Header::SIZE + 3) as u32,
+                pong: 0,
+                crc: 0,
+            };
+            let mut chunk = header.to_bytes();
+            chunk.extend_from_slice(b"fc!");
+            chunk.resize(1470, 0);
+            cap_handler.process_air2ground_packets(chunk);
+            assert_eq!(received.read().unwrap().as_slice(), b"fc!");
+            assert!(cap_handler.frames.is_empty());
+        }
+
+        #[test]
+        fn test_antenna_signals() {
+            // combined signal, then the signal and index of antenna 0 and 1 in the extended namespaces
+            let ext = 1u32 << 29 | 1 << 31;
+            let mut radiotap = vec![0, 0, 21, 0];
+            for present in [1u32 << 5 | ext, 1 << 5 | 1 << 11 | ext, 1 << 5 | 1 << 11] {
+                radiotap.extend_from_slice(&present.to_le_bytes());
+            }
+            radiotap.extend_from_slice(&[-40i8 as u8, -42i8 as u8, 0, -45i8 as u8, 1]);
+            assert_eq!(antenna_signals(&radiotap), [Some(-42), Some(-45), None, None]);
+            assert_eq!(antenna_signals(&radiotap[..4]), [None; MAX_ANTENNAS]);
+        }
+
+        #[test]
+        fn test_detect_protocol() {
+            let mut cap_handler = CapHandler::new(FEC_K as u32, FEC_N as u32);
+            let frames = Arc::new(RwLock::new(Vec::new()));
+            let frames_copy = frames.clone();
+            cap_handler.do_when_recv_new_frame(move |frame| {
+                frames_copy.write().unwrap().push(frame.get_jpegdata());
+            });
+            assert_eq!(cap_handler.protocol(), None);
+            for frame_index in 1..=8u32 {
+                // type size(4) pong crc resolution part_index|last_part frame_index(4)
+                let mut chunk = vec![0, 0, 0, 0, 0, 0, 0, 3, 0x80];
+                chunk[1..5].copy_from_slice(&1470u32.to_le_bytes());
+                chunk.extend_from_slice(&frame_index.to_le_bytes());
+                chunk[6] = crc::Crc::<u8>::new(&crc::CRC_8_SMBUS).checksum(&chunk);
+                chunk.resize(1470, 0xAB);
+                cap_handler.process_air2ground_packets(chunk);
+            }
+            assert_eq!(cap_handler.protocol(), Some(ProtocolVersion::V1));
+            let frames = frames.read().unwrap();
+            assert_eq!(frames.len(), 8);
+            assert!(frames.iter().all(|x| x.len() == 1470 - 13 && x[0] == 0xAB));
+            assert_eq!(cap_handler.stats.frame_byte_count, 8 * (1470 - 13));
+        }
+
+        #[test]
+        fn test_lost_blocks() {
+            let mut cap_handler = CapHandler::new(FEC_K as u32, FEC_N as u32);
+            let packet = |block_index: u32, packet_index: u32| {
+                let mut payload = vec![0u8; VTX_PACKET_HEADER_SIZE + 8];
+                let mut header = VtxPacketHeader(&mut payload[..VTX_PACKET_HEADER_SIZE]);
+                header.set_block_index(block_index);
+                header.set_packet_index(packet_index);
+                VtxPacket::from(&payload, false, FEC_N as u32).unwrap()
+            };
+            for block_index in [0, 3, 5] {
+                let mut block = Block::new(block_index);
+                // the block 3 is received but never completed
+                let packets = if block_index == 3 { 1 } else { FEC_K as u32 };
+                for packet_index in 0..packets {
+                    block.packets.insert(packet_index, packet(block_index, packet_index));
+                }
+                cap_handler.blocks.insert(block_index, block);
+            }
+            assert!(cap_handler.process_block(0).is_some());
+            assert!(cap_handler.process_block(3).is_none());
+            assert!(cap_handler.process_block(5).is_some());
+            // 1, 2 and 4 are never received
+            assert_eq!(cap_handler.stats.broken_block_count, 3);
+            assert_eq!(cap_handler.stats.complete_block_count, 2);
+        }
+
         #[test]
         fn test_call_back() {
             let mut cap_handler = init_cap_and_recv_packets(40);
@@ -401,7 +861,7 @@ pub mod tests {
                 .unwrap();
 
             let packet = block.packets.get(&0).unwrap().clone();
-            let d = Air2GroundFramePacket::from_bytes(packet.data);
+            let d = Air2GroundFramePacket::decode(ProtocolVersion::V1, packet.data).unwrap();
             println!("{:?}", d.header);
         }
 
@@ -0,0 +1,400 @@
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Write as _},
+    io::Write,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    LevelFilter, Log, Metadata, Record, SetLoggerError,
+};
+use serde::{Deserialize, Serialize};
+
+/*
+    The logger of the ground station, the records of the `log` macros are written to stderr,
+    as a text line or a json line:
+        12.345 WARN  esp_vtx_gs_rs::packet: crc check failed suppressed=41
+        {"ts":1760000000.123,"level":"WARN","target":"esp_vtx_gs_rs::packet","msg":"crc check failed","suppressed":41}
+
+    The key-values of a record(e.g. `log::info!(fps = 29.8; "stats")`) are appended, or become fields of the json line.
+    The filter is a default level and per target levels, e.g. "info,esp_vtx_gs_rs::uplink=debug",
+    the longest matching target wins.
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LogFilter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> Self {
+        LogFilter {
+            default,
+            targets: Vec::new(),
+        }
+    }
+
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|x| x.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+
+    /*
+        more verbose by the count of -v, e.g. info -> debug -> trace.
+    */
+    pub fn raise(&mut self, count: u8) {
+        for _ in 0..count {
+            self.default = match self.default {
+                LevelFilter::Off => LevelFilter::Error,
+                LevelFilter::Error => LevelFilter::Warn,
+                LevelFilter::Warn => LevelFilter::Info,
+                LevelFilter::Info => LevelFilter::Debug,
+                _ => LevelFilter::Trace,
+            };
+        }
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter::new(LevelFilter::Info)
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_level = |x: &str| {
+            LevelFilter::from_str(x.trim())
+                .map_err(|_| format!("invalid log level:{}, should be off, error, warn, info, debug or trace", x))
+        };
+        let mut ret = LogFilter::default();
+        for directive in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => ret.targets.push((target.trim().to_string(), parse_level(level)?)),
+                None => ret.default = parse_level(directive)?,
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Display for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.default.as_str().to_ascii_lowercase())?;
+        for (target, level) in &self.targets {
+            write!(f, ",{}={}", target, level.as_str().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for LogFilter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LogFilter> for String {
+    fn from(value: LogFilter) -> String {
+        value.to_string()
+    }
+}
+
+/*
+    The latest log lines kept in memory, e.g. shown by the terminal dashboard.
+    Clones share the same lines.
+*/
+#[derive(Clone)]
+pub struct RecentLogs {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl RecentLogs {
+    pub fn new(capacity: usize) -> Self {
+        RecentLogs {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    // the oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+pub struct Logger {
+    filter: LogFilter,
+    json: bool,
+    start: Instant,
+    memory: Option<RecentLogs>,
+}
+
+impl Logger {
+    pub fn new(filter: LogFilter, json: bool) -> Self {
+        Logger {
+            filter,
+            json,
+            start: Instant::now(),
+            memory: None,
+        }
+    }
+
+    /*
+        keep the lines in memory instead of writing them to stderr, e.g. when the terminal is used by the dashboard.
+    */
+    pub fn with_memory(mut self, logs: RecentLogs) -> Self {
+        self.memory = Some(logs);
+        self
+    }
+
+    /*
+        install as the global logger, fails if one is installed already.
+    */
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        log::set_max_level(self.filter.max_level());
+        log::set_boxed_logger(Box::new(self))
+    }
+
+    pub fn format(&self, record: &Record) -> String {
+        let mut line = String::new();
+        if self.json {
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let mut fields = serde_json::Map::new();
+            fields.insert("ts".to_string(), ((ts * 1000.0).round() / 1000.0).into());
+            fields.insert("level".to_string(), record.level().as_str().into());
+            fields.insert("target".to_string(), record.target().into());
+            fields.insert("msg".to_string(), record.args().to_string().into());
+            let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+            line = serde_json::Value::Object(fields).to_string();
+        } else {
+            let _ = write!(
+                line,
+                "{:.3} {:<5} {}: {}",
+                self.start.elapsed().as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+            let _ = record.key_values().visit(&mut TextFields(&mut line));
+        }
+        line
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut line = self.format(record);
+        if let Some(memory) = &self.memory {
+            memory.push(line);
+            return;
+        }
+        line.push('\n');
+        // one write for a line, so the lines of the threads are not mixed
+        let _ = std::io::stderr().lock().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+struct TextFields<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for TextFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let _ = write!(self.0, " {}={}", key, value);
+        Ok(())
+    }
+}
+
+struct JsonFields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(x) = value.to_u64() {
+            x.into()
+        } else if let Some(x) = value.to_i64() {
+            x.into()
+        } else if let Some(x) = value.to_f64() {
+            x.into()
+        } else if let Some(x) = value.to_bool() {
+            x.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/*
+    Let a repeated record through at most once per interval, and count the suppressed ones in between.
+    Used by the warn_limited macro, one for every call site.
+*/
+pub struct RateLimit {
+    interval: Duration,
+    state: Mutex<(Option<Instant>, u64)>, // last passed, suppressed since
+}
+
+impl RateLimit {
+    pub const fn new(interval: Duration) -> Self {
+        RateLimit {
+            interval,
+            state: Mutex::new((None, 0)),
+        }
+    }
+
+    /*
+        Some(suppressed count since the last passed one) if this one passes.
+    */
+    pub fn check(&self, now: Instant) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        let (last, suppressed) = &mut *state;
+        match last {
+            Some(last) if now.saturating_duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            _ => {
+                *last = Some(now);
+                Some(std::mem::take(suppressed))
+            }
+        }
+    }
+}
+
+/*
+    log::warn!, but at most once a second for the call site, e.g. for the errors of every packet:
+        warn_limited!("crc check failed");
+    the count of the suppressed ones is attached as the `suppressed` field.
+*/
+#[macro_export]
+macro_rules! warn_limited {
+    ($($arg:tt)+) => {{
+        static LIMIT: $crate::logger::RateLimit =
+            $crate::logger::RateLimit::new(::std::time::Duration::from_secs(1));
+        if ::log::log_enabled!(::log::Level::Warn) {
+            match LIMIT.check(::std::time::Instant::now()) {
+                Some(0) => ::log::warn!($($arg)+),
+                Some(suppressed) => ::log::warn!(suppressed; $($arg)+),
+                None => {}
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::time::{Duration, Instant};
+
+        use log::{Level, LevelFilter, Log, Record};
+
+        use crate::logger::{LogFilter, Logger, RateLimit, RecentLogs};
+
+        #[test]
+        fn test_filter() {
+            let filter: LogFilter = "warn, esp_vtx_gs_rs::uplink=debug,esp_vtx_gs_rs::uplink::input=off"
+                .parse()
+                .unwrap();
+            assert_eq!(filter.level("esp_vtx_gs_rs::packet"), LevelFilter::Warn);
+            assert_eq!(filter.level("esp_vtx_gs_rs::uplink"), LevelFilter::Debug);
+            assert_eq!(filter.level("esp_vtx_gs_rs::uplink::queue"), LevelFilter::Debug);
+            assert_eq!(filter.level("esp_vtx_gs_rs::uplink::input"), LevelFilter::Off);
+            assert_eq!(filter.level("esp_vtx_gs_rs::uplinkx"), LevelFilter::Warn);
+            assert_eq!(filter.max_level(), LevelFilter::Debug);
+            assert_eq!(filter.to_string().parse(), Ok(filter));
+            assert!("loud".parse::<LogFilter>().is_err());
+
+            let mut filter = LogFilter::default();
+            filter.raise(1);
+            assert_eq!(filter.level("x"), LevelFilter::Debug);
+            filter.raise(3);
+            assert_eq!(filter.level("x"), LevelFilter::Trace);
+        }
+
+        #[test]
+        fn test_format() {
+            let logger = Logger::new(LogFilter::default(), true);
+            let kvs = [("suppressed", 41u64)];
+            let record = Record::builder()
+                .level(Level::Warn)
+                .target("esp_vtx_gs_rs::packet")
+                .args(format_args!("crc check failed"))
+                .key_values(&kvs)
+                .build();
+            let line: serde_json::Value = serde_json::from_str(&logger.format(&record)).unwrap();
+            assert_eq!(line["level"], "WARN");
+            assert_eq!(line["target"], "esp_vtx_gs_rs::packet");
+            assert_eq!(line["msg"], "crc check failed");
+            assert_eq!(line["suppressed"], 41);
+
+            let logger = Logger::new(LogFilter::default(), false);
+            let line = logger.format(&record);
+            assert!(line.ends_with(" WARN  esp_vtx_gs_rs::packet: crc check failed suppressed=41"));
+
+            let logs = RecentLogs::new(2);
+            let logger = logger.with_memory(logs.clone());
+            for _ in 0..3 {
+                logger.log(&record);
+            }
+            assert_eq!(logs.lines().len(), 2);
+            assert!(logs.lines()[1].ends_with("crc check failed suppressed=41"));
+        }
+
+        #[test]
+        fn test_rate_limit() {
+            let now = Instant::now();
+            let limit = RateLimit::new(Duration::from_secs(1));
+            assert_eq!(limit.check(now), Some(0));
+            assert_eq!(limit.check(now + Duration::from_millis(10)), None);
+            assert_eq!(limit.check(now + Duration::from_millis(500)), None);
+            assert_eq!(limit.check(now + Duration::from_millis(1000)), Some(2));
+            assert_eq!(limit.check(now + Duration::from_millis(2500)), Some(0));
+        }
+    }
+}
@@ -1,32 +1,215 @@
 use std::{
     net::{Ipv4Addr, SocketAddr, UdpSocket},
-    sync::{Arc, RwLock, Condvar, Mutex},
-    time::SystemTime, str::FromStr, collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH}, str::FromStr,
 };
 
 use clap::Parser;
-use esp_vtx_gs_rs::{device::Device, packet_h_bind::Ground2Air_Config_Packet, inject::InjectHandler, Frame};
+use esp_vtx_gs_rs::{device::Device, inject::InjectHandler, inject::tx_params::TxRate};
+use esp_vtx_gs_rs::settings::{load_air_state, save_air_state, Settings};
 use esp_vtx_gs_rs::CapHandler;
+use esp_vtx_gs_rs::config_tracker::ConfigTracker;
+use esp_vtx_gs_rs::blackbox::{Blackbox, BlackboxDumper, ErrorTrigger};
+use esp_vtx_gs_rs::control::ControlServer;
+use esp_vtx_gs_rs::events::{Event, EventBus, EventWriter, LinkMonitor};
+use esp_vtx_gs_rs::serial_bridge::SerialBridge;
+use esp_vtx_gs_rs::jpeg::JpegCheck;
+use esp_vtx_gs_rs::latency::{FrameLatency, LatencyStats};
+use esp_vtx_gs_rs::logger::{LogFilter, Logger, RecentLogs};
+use esp_vtx_gs_rs::metrics::{Metrics, MetricsServer};
+use esp_vtx_gs_rs::osd::{OsdFormat, OsdSender};
+use esp_vtx_gs_rs::protocol::ProtocolVersion;
+use esp_vtx_gs_rs::stats::StatsEngine;
+use esp_vtx_gs_rs::stream_header::StreamHeader;
+use esp_vtx_gs_rs::uplink::{UplinkInput, UplinkPriority, UplinkQueue, UplinkScheduler};
+use esp_vtx_gs_rs::output::{jpeg_dump::JpegDumper, mjpeg_pipe::MjpegPipe, queue::{FrameQueue, QueuePolicy}, spawn_output};
+#[cfg(feature = "tui")]
+use esp_vtx_gs_rs::tui::{Dashboard, REFRESH_INTERVAL};
 use pcap::Linktype;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, arg_required_else_help(true))]
 struct Cli {
+    // settings file(toml), the flags below override the values in it
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
     #[arg(short, long)]
     dev: Option<String>,
 
+    // protocol version of the air firmware: v1, detected from the packets if not set
+    #[arg(long)]
+    protocol: Option<ProtocolVersion>,
+
     // output port
-    #[arg(long, default_value_t = 12345)]
-    port: u32,
+    #[arg(long)]
+    port: Option<u16>,
 
     // control port
     #[arg(long)]
-    control_port: Option<u32>,
+    control_port: Option<u16>,
+
+    // udp port to receive uplink payloads(mavlink, custom messages) which are injected to the air unit
+    #[arg(long)]
+    uplink_port: Option<u16>,
+
+    // same as --uplink-port, but the payloads are sent before the others, e.g. rc commands
+    #[arg(long)]
+    uplink_high_port: Option<u16>,
+
+    // tx rate of the uplink: legacy rate like 6m, or mcs1, mcs1:40:sgi:stbc
+    #[arg(long)]
+    uplink_rate: Option<TxRate>,
+
+    // bridge a serial device, or a pty linked at this path, over the uplink data and telemetry packets
+    #[arg(long)]
+    serial: Option<PathBuf>,
+
+    // baud rate of the serial device, default: 115200
+    #[arg(long)]
+    serial_baud: Option<u32>,
 
     // target ip
-    #[arg(long,default_value = "127.0.0.1")]
-    target_ip: String,
+    #[arg(long)]
+    target_ip: Option<String>,
+
+    // dump every frame as a jpeg file into this directory
+    #[arg(long)]
+    dump_dir: Option<String>,
+
+    // session name of the dumped files, default is the unix time of start
+    #[arg(long)]
+    dump_session: Option<String>,
+
+    // write a json-lines sidecar with frame metadata and link stats next to the dumped frames
+    #[arg(long)]
+    dump_sidecar: bool,
+
+    // disk usage cap(MB) of the dumped frames, the oldest ones are removed when exceeded
+    #[arg(long)]
+    dump_max_mb: Option<u64>,
+
+    // prepend a stream header(frame index, timestamps, link quality) to each udp datagram
+    #[arg(long)]
+    udp_header: bool,
+
+    // write the mjpeg stream to stdout("-") or a named pipe
+    #[arg(long)]
+    mjpeg_out: Option<String>,
+
+    // what to do with malformed jpegs: off, flag or drop(default)
+    #[arg(long)]
+    jpeg_check: Option<JpegCheck>,
+
+    // queue policy of udp and mjpeg outputs: latest(default), drop-oldest[:N] or fifo[:N]
+    #[arg(long)]
+    queue: Option<QueuePolicy>,
+
+    // queue policy of the frame dump, default: fifo:64
+    #[arg(long)]
+    dump_queue: Option<QueuePolicy>,
+
+    // interval(ms) of the status line, default: 1000
+    #[arg(long)]
+    stats_interval: Option<u64>,
 
+    // print the link report as json lines instead of the status line
+    #[arg(long)]
+    stats_json: bool,
+
+    // http port of the prometheus /metrics endpoint
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    // udp port to send the OSD telemetry(rssi, loss, fps, bitrate, rtt, air config) to, on the target ip
+    #[arg(long)]
+    osd_port: Option<u16>,
+
+    // format of the OSD telemetry: json(default) or binary
+    #[arg(long)]
+    osd_format: Option<OsdFormat>,
+
+    // interval(ms) of the OSD telemetry, default: 200
+    #[arg(long)]
+    osd_interval: Option<u64>,
+
+    // log level, and per module levels: e.g. "warn" or "info,esp_vtx_gs_rs::uplink=debug", default: info
+    #[arg(long)]
+    log_level: Option<LogFilter>,
+
+    // more verbose logs, -v for debug, -vv for trace
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    // write the logs as json lines
+    #[arg(long)]
+    log_json: bool,
+
+    // append the events of the session(config changes, link lost, air restarts, stats snapshots...) to this json-lines file
+    #[arg(long)]
+    event_log: Option<PathBuf>,
+
+    // keep the raw captured packets in memory, and dump them as pcap files into this directory when the link is lost,
+    // on the decode errors, or by the dump_blackbox command
+    #[arg(long)]
+    blackbox_dir: Option<PathBuf>,
+
+    // window(ms) of the blackbox, default: 5000
+    #[arg(long)]
+    blackbox_window: Option<u64>,
+
+    // show the terminal dashboard instead of the status line, the logs are shown in it
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+}
+
+impl Cli {
+    fn apply_to(self, settings: &mut Settings) {
+        fn set<T>(target: &mut T, value: Option<T>) {
+            if let Some(v) = value {
+                *target = v;
+            }
+        }
+        fn set_option<T>(target: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *target = value;
+            }
+        }
+
+        set_option(&mut settings.device, self.dev);
+        set_option(&mut settings.protocol, self.protocol);
+        set(&mut settings.output.port, self.port);
+        set_option(&mut settings.control.port, self.control_port);
+        set_option(&mut settings.uplink.port, self.uplink_port);
+        set_option(&mut settings.uplink.high_port, self.uplink_high_port);
+        set_option(&mut settings.uplink.tx_rate, self.uplink_rate);
+        set_option(&mut settings.serial.path, self.serial);
+        set(&mut settings.serial.baud, self.serial_baud);
+        set(&mut settings.output.target_ip, self.target_ip);
+        set_option(&mut settings.dump.dir, self.dump_dir);
+        set_option(&mut settings.dump.session, self.dump_session);
+        settings.dump.sidecar |= self.dump_sidecar;
+        set_option(&mut settings.dump.max_mb, self.dump_max_mb);
+        settings.output.udp_header |= self.udp_header;
+        set_option(&mut settings.output.mjpeg_out, self.mjpeg_out);
+        set(&mut settings.output.jpeg_check, self.jpeg_check);
+        set(&mut settings.output.queue, self.queue);
+        set(&mut settings.dump.queue, self.dump_queue);
+        set(&mut settings.stats.interval_ms, self.stats_interval);
+        settings.stats.json |= self.stats_json;
+        set_option(&mut settings.metrics.port, self.metrics_port);
+        set_option(&mut settings.osd.port, self.osd_port);
+        set(&mut settings.osd.format, self.osd_format);
+        set(&mut settings.osd.interval_ms, self.osd_interval);
+        set(&mut settings.log.level, self.log_level);
+        settings.log.level.raise(self.verbose);
+        settings.log.json |= self.log_json;
+        set_option(&mut settings.events.path, self.event_log);
+        set_option(&mut settings.blackbox.dir, self.blackbox_dir);
+        set(&mut settings.blackbox.window_ms, self.blackbox_window);
+    }
 }
 
 /*
@@ -61,64 +244,308 @@ struct Cli {
 */
 fn main() {
     let args = Cli::parse();
-    
-    if let Some(dev) = args.dev {
-        let wlan_dev = Arc::new(RwLock::new(Device::new(dev)));
-        let mut cap_hander = CapHandler::new(2, 3);
+    let config_path = args.config.clone();
+    let mut settings = match &config_path {
+        Some(path) => Settings::load(path).unwrap_or_else(|e| panic!("could not load the settings: {}", e)),
+        None => Settings::default(),
+    };
+    #[cfg(feature = "tui")]
+    let tui = args.tui;
+    #[cfg(not(feature = "tui"))]
+    let tui = false;
+    args.apply_to(&mut settings);
+    // the dashboard owns the terminal, so the logs are kept for it instead of written to stderr
+    let recent_logs = RecentLogs::new(100);
+    let mut logger = Logger::new(settings.log.level.clone(), settings.log.json);
+    if tui {
+        logger = logger.with_memory(recent_logs.clone());
+    }
+    logger.init().expect("could not init the logger!");
+
+    let air_state_path = settings.air_state_path(config_path.as_deref());
+    if let Some(path) = &air_state_path {
+        match load_air_state(path) {
+            Ok(Some(config)) => settings.air = config,
+            Ok(None) => {}
+            Err(e) => log::warn!("could not load the air state, use [air] in settings: {}", e),
+        }
+    }
+
+    let events = EventBus::new();
+    if let Some(path) = &settings.events.path {
+        EventWriter::create(path).expect("could not open the event log!").subscribe_to(&events);
+    }
+
+    if let Some(dev) = settings.device {
+        events.publish(Event::SessionStart { device: dev.clone(), version: env!("CARGO_PKG_VERSION"), air: settings.air });
+        let shared_metrics = Arc::new(RwLock::new(Metrics {
+            card: dev.clone(),
+            ..Default::default()
+        }));
+        let wlan_dev = Arc::new(RwLock::new(Device::new(dev.clone())));
+        let mut cap_hander = CapHandler::new(settings.air.fec_codec_k as u32, settings.air.fec_codec_n as u32);
+        cap_hander.set_jpeg_check(settings.output.jpeg_check);
+        cap_hander.set_protocol(settings.protocol);
+        cap_hander.set_event_bus(events.clone());
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         
-        let target_ip = Ipv4Addr::from_str(&args.target_ip.as_str()).unwrap();
-        let target = SocketAddr::new(target_ip.into(), args.port as u16);
+        let target_ip = Ipv4Addr::from_str(settings.output.target_ip.as_str()).unwrap();
+        let target = SocketAddr::new(target_ip.into(), settings.output.port);
 
-        let count: Arc<RwLock<u32>> = Arc::new(RwLock::new(0));
-        let count2 = count.clone();
+        let stats_engine = Arc::new(Mutex::new(StatsEngine::new(Duration::from_millis(settings.stats.window_ms))));
+        let stats_engine_rx = stats_engine.clone();
 
-        let send_frames :Arc<Mutex<VecDeque<Frame>>> = Arc::new(Mutex::new(VecDeque::new()));
-        let send_frames_push = send_frames.clone();
-        let cond= Arc::new(Condvar::new());
-        let cond_push = cond.clone();
-        
-        cap_hander.do_when_recv_new_frame(move |frame| {
-            let mut vec = send_frames_push.lock().unwrap();
-            vec.push_back(frame);
-            cond_push.notify_all();
-            (*count2.write().unwrap()) += 1;
+        let latency_stats = Arc::new(Mutex::new(LatencyStats::new()));
+        let mut outputs: Vec<(&str, FrameQueue)> = Vec::new();
+
+        let udp_queue = FrameQueue::new(settings.output.queue);
+        let udp_header = settings.output.udp_header;
+        spawn_output("udp", udp_queue.clone(), latency_stats.clone(), move |frame| {
+            let mut datagram = Vec::new();
+            if udp_header{
+                StreamHeader::from_frame(frame).encode(&mut datagram);
+            }
+            datagram.append(&mut frame.get_jpegdata());
+            socket.send_to(&datagram,target).map(|_| ())
         });
+        outputs.push(("udp", udp_queue));
+
+        if let Some(target) = settings.output.mjpeg_out {
+            let mut mjpeg_pipe = MjpegPipe::new(&target).expect("could not open the mjpeg output!");
+            let queue = FrameQueue::new(settings.output.queue);
+            spawn_output("mjpeg", queue.clone(), latency_stats.clone(), move |frame| mjpeg_pipe.write_frame(frame));
+            outputs.push(("mjpeg", queue));
+        }
+
+        if let Some(dir) = settings.dump.dir {
+            let session = settings.dump.session.unwrap_or_else(|| {
+                SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs().to_string()
+            });
+            let mut jpeg_dumper = JpegDumper::new(dir, session, settings.dump.sidecar, settings.dump.max_mb.map(|x| x * 1024 * 1024))
+                .expect("could not create the dump directory!");
+            let queue = FrameQueue::new(settings.dump.queue);
+            spawn_output("dump", queue.clone(), latency_stats.clone(), move |frame| {
+                if let Err(e) = jpeg_dumper.dump_frame(frame){
+                    esp_vtx_gs_rs::warn_limited!("dump frame {} failed:{}", frame.frame_index(), e);
+                }
+                Ok(())
+            });
+            outputs.push(("dump", queue));
+            events.publish(Event::RecordingStart { recorder: "dump" });
+        }
 
-        std::thread::spawn(move ||{
-            loop{
-                let mut vec = send_frames.lock().unwrap();
-                if let Some(frame) = vec.pop_front(){
-                    socket.send_to(&frame.get_jpegdata(),target).unwrap();
+        let mut config_tracker = ConfigTracker::new(settings.air, Duration::from_millis(settings.control.confirm_timeout_ms));
+        config_tracker.set_event_bus(events.clone());
+        let config_tracker = Arc::new(Mutex::new(config_tracker));
+        if let Some(path) = air_state_path {
+            // only the config applied by the air unit is restored at the next start
+            let config_tracker_rx = config_tracker.clone();
+            events.subscribe(move |record| {
+                if let Event::ConfigConfirmed { .. } = record.event {
+                    let Some(config) = config_tracker_rx.lock().unwrap().confirmed() else { return; };
+                    if let Err(e) = save_air_state(&path, &config) {
+                        log::warn!("could not save the air state: {}", e);
+                    }
                 }
-                drop(cond.wait(vec));
+            });
+        }
+        let config_tracker_rx = config_tracker.clone();
+        let output_queues: Vec<FrameQueue> = outputs.iter().map(|(_, queue)| queue.clone()).collect();
+        let latency_stats_rx = latency_stats.clone();
+        cap_hander.do_when_recv_new_frame(move |frame| {
+            config_tracker_rx.lock().unwrap().observe_frame(frame.resolution(), frame.pong());
+            latency_stats_rx.lock().unwrap().observe_frame(&FrameLatency::new(&frame, None));
+            let frame = Arc::new(frame);
+            for queue in &output_queues{
+                queue.push(frame.clone());
+            }
+            if let Some(latency) = frame.latency() {
+                stats_engine_rx.lock().unwrap().observe_latency(Instant::now(), latency);
             }
         });
 
+        let shared_stats = Arc::new(RwLock::new(cap_hander.stats.clone()));
 
-        if args.control_port.is_some(){
-            let wlan_dev_tx = wlan_dev.clone();
+        let blackbox = Arc::new(Mutex::new(Blackbox::new(
+            Duration::from_millis(settings.blackbox.window_ms),
+            settings.blackbox.max_mb as usize * 1024 * 1024,
+        )));
+        let blackbox_dumper = settings.blackbox.dir.as_ref().map(|dir| {
+            let session = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs().to_string();
+            let dumper = BlackboxDumper::new(blackbox.clone(), dir.clone(), session, Duration::from_millis(settings.blackbox.cooldown_ms))
+                .expect("could not create the blackbox directory!");
+            dumper.spawn() // the dumps are written on its worker thread
+        });
+        if let Some(dumper) = &blackbox_dumper {
+            let dumper_rx = dumper.clone();
+            events.subscribe(move |record| {
+                if let Event::LinkLost { .. } = record.event {
+                    dumper_rx.trigger("link_lost");
+                }
+            });
+            if settings.blackbox.error_threshold > 0 {
+                let error_trigger = ErrorTrigger::new(settings.blackbox.error_threshold);
+                let shared_stats = shared_stats.clone();
+                let dumper = dumper.clone();
+                std::thread::spawn(move ||{
+                    error_trigger.run(shared_stats, dumper);
+                });
+            }
+        }
+        let uplink_queue = UplinkQueue::new(settings.uplink.queue_len);
+        if let Some(control_port) = settings.control.port{
+            let mut control_server = ControlServer::new(control_port, config_tracker.clone(), shared_stats.clone())
+                .expect("could not bind the control port!");
+            let uplink_queue = uplink_queue.clone();
+            control_server.do_when_config_changed(move |_| {
+                uplink_queue.wake(); // send the changed config at once, it's already requested to the tracker
+            });
+            if let Some(dumper) = &blackbox_dumper {
+                control_server.set_blackbox(dumper.clone());
+            }
             std::thread::spawn(move ||{
-                let config = Ground2Air_Config_Packet::default();
-                let mut inject_handler = InjectHandler::new(2,6);
-                loop{
-                    let mut wlan_dev = wlan_dev_tx.write().unwrap();
-                    let push_ret = inject_handler.push_ground2air_config_packet(&config);
-                    for i in push_ret{
-                        wlan_dev.cap.sendpacket(i).unwrap();
-                    }
-                    drop(wlan_dev);
-                    std::thread::sleep(std::time::Duration::from_millis(500));
+                control_server.run();
+            });
+        }
+
+        for (port, priority) in [(settings.uplink.port, UplinkPriority::Normal), (settings.uplink.high_port, UplinkPriority::High)] {
+            if let Some(port) = port {
+                let uplink_input = UplinkInput::new(port, uplink_queue.clone(), priority)
+                    .expect("could not bind the uplink port!");
+                std::thread::spawn(move ||{
+                    uplink_input.run();
+                });
+            }
+        }
+
+        if let Some(path) = &settings.serial.path {
+            let serial_bridge = Arc::new(SerialBridge::open(path, settings.serial.baud).expect("could not open the serial port!"));
+            serial_bridge.remove_link_on_exit();
+            let serial_writer = serial_bridge.spawn_downstream().expect("could not open the serial port!");
+            cap_hander.do_when_recv_telemetry(move |data| {
+                serial_writer.write(data);
+            });
+            let uplink_queue = uplink_queue.clone();
+            std::thread::spawn(move ||{
+                serial_bridge.run_upstream(uplink_queue);
+            });
+        }
+
+        let mut scheduler = UplinkScheduler::new(settings.uplink.scheduler_config());
+        scheduler.set_event_bus(events.clone());
+        let scheduler_stats = scheduler.stats_handle();
+        if settings.protocol.is_none() {
+            // the uplink follows the protocol detected from the video
+            let protocol = scheduler.protocol_handle();
+            events.subscribe(move |record| {
+                if let Event::ProtocolDetected { version } = record.event {
+                    *protocol.lock().unwrap() = Some(version);
+                }
+            });
+        }
+        if settings.control.port.is_some() || settings.uplink.port.is_some() || settings.uplink.high_port.is_some() || settings.serial.path.is_some() || tui {
+            let mut inject_cap = wlan_dev.read().unwrap().open_inject().expect("could not open the device for injection!");
+            let config_tracker_tx = config_tracker.clone();
+            let uplink_queue = uplink_queue.clone();
+            std::thread::spawn(move ||{
+                let mut inject_handler = InjectHandler::new(settings.uplink.fec_k, settings.uplink.fec_n);
+                inject_handler.set_tx_params(settings.uplink.tx_params());
+                if let Some(protocol) = settings.protocol {
+                    inject_handler.set_protocol(protocol);
                 }
+                inject_handler.set_flush_timeout(settings.uplink.flush_timeout());
+                scheduler.run(inject_handler, config_tracker_tx, uplink_queue, |packet| inject_cap.sendpacket(packet));
+            });
+        }
+
+        if let Some(port) = settings.osd.port {
+            let target_ip = settings.osd.target_ip.as_deref().unwrap_or(settings.output.target_ip.as_str());
+            let target_ip = Ipv4Addr::from_str(target_ip).expect("invalid osd target ip!");
+            let osd_sender = OsdSender::new(SocketAddr::new(target_ip.into(), port), settings.osd.format, Duration::from_millis(settings.osd.interval_ms))
+                .expect("could not open the osd socket!");
+            let shared_stats = shared_stats.clone();
+            let config_tracker = config_tracker.clone();
+            std::thread::spawn(move ||{
+                osd_sender.run(shared_stats, config_tracker);
             });
         }
+        // the OSD reads the shared stats, so they are refreshed as often as it's sent
+        let mut share_interval = match settings.osd.port {
+            Some(_) => settings.osd.interval_ms.min(settings.stats.interval_ms),
+            None => settings.stats.interval_ms,
+        };
 
+        if settings.events.path.is_some() || blackbox_dumper.is_some() {
+            // the link monitor reads the shared stats too
+            share_interval = share_interval.min(settings.events.link_timeout_ms / 2);
+            let link_monitor = LinkMonitor::new(Duration::from_millis(settings.events.link_timeout_ms), Instant::now());
+            let shared_stats = shared_stats.clone();
+            let events = events.clone();
+            std::thread::spawn(move ||{
+                link_monitor.run(shared_stats, events);
+            });
+        }
+
+        if let Some(port) = settings.metrics.port {
+            let metrics_server = MetricsServer::new(port, shared_metrics.clone()).expect("could not bind the metrics port!");
+            std::thread::spawn(move ||{
+                metrics_server.run();
+            });
+        }
+
+        // and as often as the dashboard is drawn
+        #[cfg(feature = "tui")]
+        let share_interval = if tui { share_interval.min(REFRESH_INTERVAL.as_millis() as u64) } else { share_interval };
+        #[cfg(feature = "tui")]
+        if tui {
+            let mut dashboard = Dashboard::new(shared_stats.clone(), shared_metrics.clone(), config_tracker.clone(), recent_logs.clone());
+            let config_tracker = config_tracker.clone();
+            let uplink_queue = uplink_queue.clone();
+            dashboard.do_when_command(move |command| {
+                let mut tracker = config_tracker.lock().unwrap();
+                let old_config = tracker.requested();
+                let mut new_config = old_config;
+                command.apply(&mut new_config)?;
+                tracker.request(new_config, Instant::now());
+                drop(tracker);
+                if new_config != old_config {
+                    uplink_queue.wake(); // send the changed config at once
+                }
+                Ok(new_config)
+            });
+            std::thread::spawn(move ||{
+                if let Err(e) = dashboard.run() {
+                    log::error!("the dashboard failed: {}", e);
+                    // the logs were kept for the dashboard, write them out now the terminal is restored
+                    for line in recent_logs.lines() {
+                        eprintln!("{}", line);
+                    }
+                }
+                std::process::exit(0);
+            });
+        }
 
         let mut last_time = std::time::SystemTime::now();
+        let mut last_share_time = Instant::now();
+        let mut last_snapshot_time = Instant::now();
         loop {
             let mut wlan_dev_unwrap = wlan_dev.write().unwrap();
-            let packet = wlan_dev_unwrap.cap.next_packet().unwrap();   // TODO: change this block action to epoll 
+            let packet = match wlan_dev_unwrap.cap.next_packet() {   // TODO: change this block action to epoll 
+                Ok(packet) => packet,
+                Err(e) => {
+                    events.publish(Event::DeviceError { device: dev, error: e.to_string() });
+                    events.flush();
+                    panic!("capture failed: {}", e);
+                }
+            };
 
+            if blackbox_dumper.is_some() {
+                let ts = Duration::new(packet.header.ts.tv_sec as u64, packet.header.ts.tv_usec as u32 * 1000);
+                blackbox.lock().unwrap().push(ts, packet.header.len, packet.data);
+            }
+            // the codec of the confirmed config, refer ConfigTracker::decoder_fec
+            let (fec_k, fec_n) = config_tracker.lock().unwrap().decoder_fec(Instant::now());
+            cap_hander.set_fec(fec_k as u32, fec_n as u32);
             cap_hander.process_cap_packets(packet);
             drop(wlan_dev_unwrap);
             let block_indexs: Vec<u32> = cap_hander.blocks.keys().cloned().collect();
@@ -128,10 +555,52 @@ fn main() {
                 }
             }
 
-            if last_time.elapsed().unwrap().as_secs() >= 1 {
-                println!("fps:{}", count.read().unwrap());
-                *(count.write().unwrap()) = 0;
+            if last_share_time.elapsed() >= Duration::from_millis(share_interval) {
+                *shared_stats.write().unwrap() = cap_hander.stats.clone();
+                last_share_time = Instant::now();
+            }
+
+            if last_time.elapsed().unwrap() >= Duration::from_millis(settings.stats.interval_ms) {
+                let report = {
+                    let mut stats_engine = stats_engine.lock().unwrap();
+                    stats_engine.record(Instant::now(), &cap_hander.stats);
+                    stats_engine.report()
+                };
+                let output_stats: Vec<(String, _)> = outputs.iter().map(|(name, queue)| (name.to_string(), queue.stats())).collect();
+                let uplink_stats = uplink_queue.stats();
+                let inject_stats = *scheduler_stats.lock().unwrap();
+                if settings.events.snapshot_interval_ms > 0 && last_snapshot_time.elapsed() >= Duration::from_millis(settings.events.snapshot_interval_ms) {
+                    if let Some(report) = &report {
+                        events.publish(Event::Stats(report.clone()));
+                    }
+                    last_snapshot_time = Instant::now();
+                }
+                if let Some(report) = report.as_ref().filter(|_| !tui) {
+                    if settings.stats.json {
+                        let mut json = serde_json::to_value(report).unwrap();
+                        json["latency_stages"] = serde_json::to_value(latency_stats.lock().unwrap().summary()).unwrap();
+                        log::info!(target: "esp_vtx_gs_rs::stats", "{}", json);
+                    } else {
+                        let mut drops: Vec<String> = output_stats.iter().map(|(name, stats)| format!("{}:{}", name, stats.dropped)).collect();
+                        drops.push(format!("uplink:{}", uplink_stats.dropped));
+                        log::info!(target: "esp_vtx_gs_rs::stats", "{} jpeg_error:{} queue_dropped {} injected:{} inject_error:{}", report.summary(), report.jpeg_errors,
+                            drops.join(" "), inject_stats.injected_frames, inject_stats.send_errors);
+                    }
+                }
+                {
+                    let mut metrics = shared_metrics.write().unwrap();
+                    metrics.link = cap_hander.stats.clone();
+                    metrics.report = report;
+                    metrics.outputs = output_stats;
+                    metrics.uplink = uplink_stats;
+                    metrics.scheduler = inject_stats;
+                    metrics.latency = latency_stats.lock().unwrap().clone();
+                }
                 last_time = SystemTime::now();
+
+                if let Some(index) = cap_hander.take_max_packet_index() {
+                    config_tracker.lock().unwrap().observe_max_packet_index(index);
+                }
             }
             
         }
@@ -0,0 +1,434 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::{
+    latency::LatencyStats,
+    output::queue::QueueStats,
+    stats::LinkReport,
+    uplink::{SchedulerStats, UplinkStats},
+    ConnectStats,
+};
+
+/*
+    Expose the counters and gauges in the prometheus text format over http:
+        curl http://127.0.0.1:9100/metrics
+
+    The main loop updates the shared Metrics every stats interval, the server only renders the latest one,
+    so scraping never touches the receiver.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    pub card: String, // the capture device, label of the per card metrics
+    pub link: ConnectStats,
+    pub report: Option<LinkReport>, // of the stats window
+    pub outputs: Vec<(String, QueueStats)>,
+    pub uplink: UplinkStats,
+    pub scheduler: SchedulerStats,
+    pub latency: LatencyStats,
+}
+
+struct MetricWriter {
+    out: String,
+}
+
+impl MetricWriter {
+    fn metric(&mut self, name: &str, kind: &str, help: &str, samples: &[(String, f64)]) {
+        if samples.is_empty() {
+            return;
+        }
+        let _ = writeln!(self.out, "# HELP esp_vtx_{} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE esp_vtx_{} {}", name, kind);
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                let _ = writeln!(self.out, "esp_vtx_{} {}", name, value);
+            } else {
+                let _ = writeln!(self.out, "esp_vtx_{}{{{}}} {}", name, labels, value);
+            }
+        }
+    }
+}
+
+impl MetricWriter {
+    fn latency_histograms(&mut self, latency: &LatencyStats) {
+        let name = "frame_stage_latency_seconds";
+        if latency.histograms().next().is_none() {
+            return;
+        }
+        let _ = writeln!(
+            self.out,
+            "# HELP esp_vtx_{} Latency of the stages of the frames, refer src/latency.rs.",
+            name
+        );
+        let _ = writeln!(self.out, "# TYPE esp_vtx_{} histogram", name);
+        for (stage, output, histogram) in latency.histograms() {
+            let mut labels = label("stage", stage);
+            if !output.is_empty() {
+                labels = format!("{},{}", labels, label("output", output));
+            }
+            for (bound, count) in histogram.buckets() {
+                let le = bound.map_or("+Inf".to_string(), |x| x.as_secs_f64().to_string());
+                let _ = writeln!(
+                    self.out,
+                    "esp_vtx_{}_bucket{{{},{}}} {}",
+                    name,
+                    labels,
+                    label("le", &le),
+                    count
+                );
+            }
+            let sum = histogram.sum().as_secs_f64();
+            let _ = writeln!(self.out, "esp_vtx_{}_sum{{{}}} {}", name, labels, sum);
+            let count = histogram.count();
+            let _ = writeln!(self.out, "esp_vtx_{}_count{{{}}} {}", name, labels, count);
+        }
+    }
+}
+
+fn label(name: &str, value: &str) -> String {
+    let value = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("{}=\"{}\"", name, value)
+}
+
+impl Metrics {
+    pub fn render(&self) -> String {
+        let mut w = MetricWriter { out: String::new() };
+        let link = &self.link;
+        let card = label("card", &self.card);
+        let kind = |k: &str, v: f64| (label("kind", k), v);
+
+        w.metric(
+            "uptime_seconds",
+            "gauge",
+            "Seconds since the receiver started.",
+            &[(
+                String::new(),
+                link.start_time
+                    .elapsed()
+                    .map(|x| x.as_secs_f64())
+                    .unwrap_or(0.0),
+            )],
+        );
+        w.metric(
+            "packets_total",
+            "counter",
+            "Captured wifi packets by kind.",
+            &[
+                kind("received", link.packet_count as f64),
+                kind("filtered", link.filtered_packet_count as f64),
+                kind("duplicate", link.duplicate_packet_count as f64),
+            ],
+        );
+        w.metric(
+            "received_bytes_total",
+            "counter",
+            "Bytes of the captured packets.",
+            &[(String::new(), link.byte_count as f64)],
+        );
+        w.metric(
+            "blocks_total",
+            "counter",
+            "FEC blocks by result.",
+            &[
+                kind("complete", link.complete_block_count as f64),
+                kind("recovered", link.recovered_block_count as f64),
+                kind("lost", link.broken_block_count as f64),
+            ],
+        );
+        w.metric(
+            "frames_total",
+            "counter",
+            "Frames by result.",
+            &[
+                kind("complete", link.frame_count as f64),
+                kind("partial", link.dropped_frame_count as f64),
+                kind("dropped", link.jpeg_dropped_count as f64),
+            ],
+        );
+        w.metric(
+            "frame_bytes_total",
+            "counter",
+            "Jpeg bytes of the completed frames.",
+            &[(String::new(), link.frame_byte_count as f64)],
+        );
+        w.metric(
+            "crc_errors_total",
+            "counter",
+            "Air2ground packets failed the crc check.",
+            &[(String::new(), link.crc_error_count as f64)],
+        );
+        w.metric(
+            "jpeg_errors_total",
+            "counter",
+            "Malformed jpegs.",
+            &[(String::new(), link.jpeg_error_count as f64)],
+        );
+        if let Some(rssi) = link.rssi {
+            w.metric(
+                "rssi_dbm",
+                "gauge",
+                "Antenna signal of the last captured packet.",
+                &[(card.clone(), rssi as f64)],
+            );
+        }
+
+        if let Some(report) = &self.report {
+            let window = |v: f64| vec![(String::new(), v)];
+            w.metric(
+                "fps",
+                "gauge",
+                "Completed frames per second in the stats window.",
+                &window(report.fps),
+            );
+            w.metric(
+                "block_loss_ratio",
+                "gauge",
+                "Lost blocks of all blocks in the stats window.",
+                &window(report.block_loss),
+            );
+            w.metric(
+                "receive_bytes_per_second",
+                "gauge",
+                "Captured bytes per second in the stats window.",
+                &window(report.byte_rate),
+            );
+            if let (Some(avg), Some(max)) = (report.latency_avg_ms, report.latency_max_ms) {
+                w.metric(
+                    "frame_latency_seconds",
+                    "gauge",
+                    "Frame latency in the stats window, from the first part received to completed.",
+                    &[
+                        (label("stat", "avg"), avg / 1000.0),
+                        (label("stat", "max"), max / 1000.0),
+                    ],
+                );
+            }
+        }
+
+        let output = |name: &str, v: u64| (label("output", name), v as f64);
+        w.metric(
+            "output_frames_total",
+            "counter",
+            "Frames delivered to the outputs.",
+            &self
+                .outputs
+                .iter()
+                .map(|(name, x)| output(name, x.delivered))
+                .collect::<Vec<_>>(),
+        );
+        w.metric(
+            "output_dropped_frames_total",
+            "counter",
+            "Frames dropped by the output queues.",
+            &self
+                .outputs
+                .iter()
+                .map(|(name, x)| output(name, x.dropped))
+                .collect::<Vec<_>>(),
+        );
+        w.metric(
+            "output_queue_len",
+            "gauge",
+            "Frames waiting in the output queues.",
+            &self
+                .outputs
+                .iter()
+                .map(|(name, x)| output(name, x.len as u64))
+                .collect::<Vec<_>>(),
+        );
+
+        let uplink = &self.uplink;
+        w.metric(
+            "uplink_payloads_total",
+            "counter",
+            "Uplink payloads by result.",
+            &[
+                kind("pushed", uplink.pushed as f64),
+                kind("sent", uplink.sent as f64),
+                kind("dropped", uplink.dropped as f64),
+            ],
+        );
+        w.metric(
+            "uplink_queue_len",
+            "gauge",
+            "Payloads waiting in the uplink queue.",
+            &[(String::new(), uplink.len as f64)],
+        );
+        let scheduler = &self.scheduler;
+        w.metric(
+            "uplink_packets_total",
+            "counter",
+            "Ground2air packets by kind.",
+            &[
+                kind("config", scheduler.config_packets as f64),
+                kind("data", scheduler.data_packets as f64),
+            ],
+        );
+        w.metric(
+            "injected_frames_total",
+            "counter",
+            "Injected wifi frames, including the fec ones.",
+            &[(card.clone(), scheduler.injected_frames as f64)],
+        );
+        w.metric(
+            "inject_errors_total",
+            "counter",
+            "Failed injections.",
+            &[(card, scheduler.send_errors as f64)],
+        );
+        w.latency_histograms(&self.latency);
+        w.out
+    }
+}
+
+pub struct MetricsServer {
+    listener: TcpListener,
+    metrics: Arc<RwLock<Metrics>>,
+}
+
+// the requests are tiny, bigger ones are not for us
+const MAX_REQUEST_SIZE: usize = 4096;
+
+impl MetricsServer {
+    pub fn new(port: u16, metrics: Arc<RwLock<Metrics>>) -> io::Result<Self> {
+        let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+        Ok(MetricsServer { listener, metrics })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /*
+        serve the scrapes one by one, it never returns.
+    */
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            let result = stream.and_then(|x| self.handle(x));
+            if let Err(e) = result {
+                log::warn!("metrics request failed:{}", e);
+            }
+        }
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|x| x == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+            let len = stream.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..len]);
+        }
+        let request = String::from_utf8_lossy(&request);
+        let mut parts = request
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let (method, path) = (parts.next(), parts.next());
+
+        let (status, body) = match (method, path) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", self.metrics.read().unwrap().render()),
+            (Some("GET"), _) => ("404 Not Found", "not found\n".to_string()),
+            _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            io::{Read, Write},
+            net::TcpStream,
+            sync::{Arc, RwLock},
+            time::Duration,
+        };
+
+        use crate::{
+            latency::FrameLatency,
+            metrics::{Metrics, MetricsServer},
+            output::queue::QueueStats,
+        };
+
+        #[test]
+        fn test_render() {
+            let mut metrics = Metrics {
+                card: "wlan0".to_string(),
+                ..Default::default()
+            };
+            metrics.link.packet_count = 12;
+            metrics.link.recovered_block_count = 3;
+            metrics.link.rssi = Some(-42);
+            metrics.outputs.push((
+                "udp".to_string(),
+                QueueStats {
+                    len: 2,
+                    ..Default::default()
+                },
+            ));
+            let text = metrics.render();
+            assert!(text.contains("# TYPE esp_vtx_packets_total counter\n"));
+            assert!(text.contains("esp_vtx_packets_total{kind=\"received\"} 12\n"));
+            assert!(text.contains("esp_vtx_blocks_total{kind=\"recovered\"} 3\n"));
+            assert!(text.contains("esp_vtx_rssi_dbm{card=\"wlan0\"} -42\n"));
+            assert!(text.contains("esp_vtx_output_queue_len{output=\"udp\"} 2\n"));
+            // no window report yet
+            assert!(!text.contains("esp_vtx_fps"));
+            assert!(!text.contains("esp_vtx_frame_stage_latency_seconds"));
+
+            let mut frame_latency = FrameLatency::default();
+            frame_latency.output = Some(Duration::from_millis(3));
+            metrics.latency.observe_output("udp", &frame_latency);
+            let text = metrics.render();
+            assert!(text.contains("# TYPE esp_vtx_frame_stage_latency_seconds histogram\n"));
+            assert!(text.contains(
+                "esp_vtx_frame_stage_latency_seconds_bucket{stage=\"output\",output=\"udp\",le=\"0.002\"} 0\n"
+            ));
+            assert!(text.contains(
+                "esp_vtx_frame_stage_latency_seconds_bucket{stage=\"output\",output=\"udp\",le=\"0.005\"} 1\n"
+            ));
+            assert!(text.contains(
+                "esp_vtx_frame_stage_latency_seconds_count{stage=\"output\",output=\"udp\"} 1\n"
+            ));
+        }
+
+        #[test]
+        fn test_server() {
+            let metrics = Arc::new(RwLock::new(Metrics::default()));
+            let server = MetricsServer::new(0, metrics).unwrap();
+            let port = server.local_addr().unwrap().port();
+            std::thread::spawn(move || server.run());
+
+            let get = |path: &str| {
+                let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+                write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            };
+            let response = get("/metrics");
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.contains("esp_vtx_uptime_seconds"));
+            assert!(get("/").starts_with("HTTP/1.1 404"));
+        }
+    }
+}
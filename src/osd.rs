@@ -0,0 +1,294 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    air_config::AirConfig,
+    config_tracker::{ConfigState, ConfigTracker},
+    output::unix_millis,
+    stats::{LinkReport, StatsEngine},
+    ConnectStats, MAX_ANTENNAS,
+};
+
+/*
+    Link health for the OSD of the clients, sent to a separate udp port several times per second,
+    so a client could draw it over the video without parsing the video stream.
+
+    json format, one datagram for each:
+        {"seq":12,"time_ms":1760000000123,"rssi":-42,"antenna_rssi":[-42,-45,null,null],"block_loss":0.01,
+         "fec_recovery":0.05,"fps":29.8,"bitrate_kbps":6512.3,"link_kbps":9120.0,"rtt_ms":35.2,
+         "config_state":"confirmed","air":{...the AirConfig...}}
+
+    binary format (little-endian):
+    offset  size  field
+    0       4     magic "EOSD"
+    4       1     version
+    5       1     len, new fields of newer versions are only appended
+    6       4     seq
+    10      8     time_ms, unix time
+    18      1     rssi(dBm), i8::MIN if unknown
+    19      4     rssi(dBm) of antenna 0..3, i8::MIN if unknown
+    23      2     block_loss, in 1/10000
+    25      2     fec_recovery, in 1/10000
+    27      2     fps, in 1/100
+    29      4     bitrate_kbps
+    33      4     link_kbps
+    37      2     rtt_ms, 0xFFFF if unknown
+    39      1     config_state: 0 pending, 1 confirmed, 2 timed out
+    40      1     resolution (Resolution in packets.h)
+    41      1     fps_limit
+    42      1     quality
+    43      1     wifi_rate (WIFI_Rate in packets.h)
+    44      1     wifi_power(dBm)
+    45      1     fec_k
+    46      1     fec_n
+*/
+pub const OSD_MAGIC: [u8; 4] = *b"EOSD";
+pub const OSD_VERSION: u8 = 1;
+pub const OSD_SIZE: usize = 47;
+
+// the rates of the OSD are over this window, shorter than the status line for a quick response
+const OSD_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsdFormat {
+    Json,
+    Binary,
+}
+
+impl FromStr for OsdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OsdFormat::Json),
+            "binary" => Ok(OsdFormat::Binary),
+            _ => Err(format!(
+                "invalid osd format:{}, should be json or binary",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OsdTelemetry {
+    pub seq: u32,
+    pub time_ms: u64,
+    pub rssi: Option<i8>,
+    pub antenna_rssi: [Option<i8>; MAX_ANTENNAS],
+    pub block_loss: f64,   // lost / all blocks, 0..=1
+    pub fec_recovery: f64, // recovered by fec / all blocks, 0..=1
+    pub fps: f64,
+    pub bitrate_kbps: f64, // of the jpeg data
+    pub link_kbps: f64,    // of all captured packets
+    pub rtt_ms: Option<f64>,
+    pub config_state: ConfigState,
+    pub air: AirConfig, // the confirmed config, or the requested one before confirmed
+}
+
+impl OsdTelemetry {
+    pub fn new(
+        seq: u32,
+        report: &LinkReport,
+        stats: &ConnectStats,
+        tracker: &mut ConfigTracker,
+    ) -> Self {
+        let now = Instant::now();
+        let all_blocks = report.complete_blocks + report.recovered_blocks + report.lost_blocks;
+        let ratio = |x: u32| {
+            if all_blocks == 0 {
+                0.0
+            } else {
+                x as f64 / all_blocks as f64
+            }
+        };
+        OsdTelemetry {
+            seq,
+            time_ms: unix_millis(SystemTime::now()),
+            rssi: stats.rssi,
+            antenna_rssi: stats.antenna_rssi,
+            block_loss: report.block_loss,
+            fec_recovery: ratio(report.recovered_blocks),
+            fps: report.fps,
+            bitrate_kbps: report.frame_byte_rate * 8.0 / 1000.0,
+            link_kbps: report.byte_rate * 8.0 / 1000.0,
+            rtt_ms: tracker.rtt(now).map(|x| x.as_secs_f64() * 1000.0),
+            config_state: tracker.state(now),
+            air: tracker.confirmed().unwrap_or_else(|| tracker.requested()),
+        }
+    }
+
+    pub fn encode(&self, format: OsdFormat) -> Vec<u8> {
+        match format {
+            OsdFormat::Json => serde_json::to_vec(self).unwrap(),
+            OsdFormat::Binary => self.encode_binary(),
+        }
+    }
+
+    fn encode_binary(&self) -> Vec<u8> {
+        let scaled = |x: f64, scale: f64| (x * scale).round().clamp(0.0, u16::MAX as f64) as u16;
+        let rssi = |x: Option<i8>| x.unwrap_or(i8::MIN) as u8;
+        let mut out = Vec::with_capacity(OSD_SIZE);
+        out.extend_from_slice(&OSD_MAGIC);
+        out.push(OSD_VERSION);
+        out.push(OSD_SIZE as u8);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&self.time_ms.to_le_bytes());
+        out.push(rssi(self.rssi));
+        out.extend(self.antenna_rssi.iter().map(|x| rssi(*x)));
+        out.extend_from_slice(&scaled(self.block_loss, 10000.0).to_le_bytes());
+        out.extend_from_slice(&scaled(self.fec_recovery, 10000.0).to_le_bytes());
+        out.extend_from_slice(&scaled(self.fps, 100.0).to_le_bytes());
+        out.extend_from_slice(&(self.bitrate_kbps.round() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.link_kbps.round() as u32).to_le_bytes());
+        let rtt = self
+            .rtt_ms
+            .map(|x| scaled(x, 1.0).min(u16::MAX - 1))
+            .unwrap_or(u16::MAX);
+        out.extend_from_slice(&rtt.to_le_bytes());
+        out.push(match self.config_state {
+            ConfigState::Pending => 0,
+            ConfigState::Confirmed => 1,
+            ConfigState::TimedOut => 2,
+        });
+        let packet = self.air.to_packet();
+        out.extend_from_slice(&[
+            packet.camera.resolution,
+            packet.camera.fps_limit,
+            packet.camera.quality,
+            packet.wifi_rate,
+            packet.wifi_power as u8,
+            packet.fec_codec_k,
+            packet.fec_codec_n,
+        ]);
+        out
+    }
+}
+
+pub struct OsdSender {
+    socket: UdpSocket,
+    target: SocketAddr,
+    format: OsdFormat,
+    interval: Duration,
+    engine: StatsEngine,
+    seq: u32,
+}
+
+impl OsdSender {
+    pub fn new(target: SocketAddr, format: OsdFormat, interval: Duration) -> io::Result<Self> {
+        Ok(OsdSender {
+            socket: UdpSocket::bind("0.0.0.0:0")?,
+            target,
+            format,
+            interval,
+            engine: StatsEngine::new(OSD_WINDOW),
+            seq: 0,
+        })
+    }
+
+    /*
+        the telemetry of the latest stats, the rates are over the records of the last second.
+    */
+    pub fn next(
+        &mut self,
+        now: Instant,
+        stats: &ConnectStats,
+        tracker: &mut ConfigTracker,
+    ) -> OsdTelemetry {
+        self.engine.record(now, stats);
+        let report = self.engine.report().unwrap_or_default();
+        self.seq = self.seq.wrapping_add(1);
+        OsdTelemetry::new(self.seq, &report, stats, tracker)
+    }
+
+    /*
+        send the telemetry every interval, it never returns.
+        The stats are shared by the capture loop, so the rates fall to 0 when no packet is captured.
+    */
+    pub fn run(mut self, stats: Arc<RwLock<ConnectStats>>, tracker: Arc<Mutex<ConfigTracker>>) {
+        loop {
+            let stats = stats.read().unwrap().clone();
+            let telemetry = self.next(Instant::now(), &stats, &mut tracker.lock().unwrap());
+            if let Err(e) = self
+                .socket
+                .send_to(&telemetry.encode(self.format), self.target)
+            {
+                crate::warn_limited!("osd send failed:{}", e);
+            }
+            std::thread::sleep(self.interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            net::UdpSocket,
+            time::{Duration, Instant},
+        };
+
+        use crate::{
+            air_config::AirConfig,
+            config_tracker::ConfigTracker,
+            osd::{OsdFormat, OsdSender, OSD_SIZE},
+            ConnectStats,
+        };
+
+        #[test]
+        fn test_telemetry() {
+            let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let mut sender = OsdSender::new(
+                receiver.local_addr().unwrap(),
+                OsdFormat::Json,
+                Duration::from_millis(100),
+            )
+            .unwrap();
+            let mut tracker = ConfigTracker::new(AirConfig::default(), Duration::from_secs(3));
+            let mut stats = ConnectStats::new();
+            stats.rssi = Some(-42);
+            stats.antenna_rssi[1] = Some(-45);
+            let now = Instant::now();
+            let first = sender.next(now, &stats, &mut tracker);
+            assert_eq!((first.seq, first.fps), (1, 0.0));
+
+            stats.frame_count += 30;
+            stats.frame_byte_count += 30 * 10000;
+            stats.complete_block_count += 90;
+            stats.recovered_block_count += 10;
+            let telemetry = sender.next(now + Duration::from_secs(1), &stats, &mut tracker);
+            assert_eq!(telemetry.fps, 30.0);
+            assert_eq!(telemetry.bitrate_kbps, 2400.0);
+            assert_eq!(telemetry.fec_recovery, 0.1);
+            assert_eq!(telemetry.rtt_ms, None);
+
+            let json: serde_json::Value =
+                serde_json::from_slice(&telemetry.encode(OsdFormat::Json)).unwrap();
+            assert_eq!(json["seq"], 2);
+            assert_eq!(
+                json["antenna_rssi"],
+                serde_json::json!([null, -45, null, null])
+            );
+            assert_eq!(json["config_state"], "pending");
+            assert_eq!(json["air"]["fec_codec_n"], AirConfig::default().fec_codec_n);
+
+            let binary = telemetry.encode(OsdFormat::Binary);
+            assert_eq!(binary.len(), OSD_SIZE);
+            assert_eq!(&binary[..6], b"EOSD\x01\x2f");
+            assert_eq!(binary[18] as i8, -42);
+            assert_eq!(&binary[19..23], &[0x80, -45i8 as u8, 0x80, 0x80]);
+            assert_eq!(u16::from_le_bytes([binary[25], binary[26]]), 1000);
+            assert_eq!(u16::from_le_bytes([binary[27], binary[28]]), 3000);
+            assert_eq!(u16::from_le_bytes([binary[37], binary[38]]), u16::MAX);
+            assert_eq!(binary[46], AirConfig::default().fec_codec_n);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+/*
+    Outputs of the completed frames: the udp sending, the jpeg dump and the mjpeg pipe,
+    each run by spawn_output in its own thread with its own queue, wired up in main.rs.
+*/
+pub mod jpeg_dump;
+pub mod mjpeg_pipe;
+pub mod queue;
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    latency::{FrameLatency, LatencyStats},
+    Frame,
+};
+use queue::FrameQueue;
+
+/*
+    run an output in its own thread, popping frames from the queue until it is closed.
+    the output is disabled(the queue is closed) when it returns a fatal error, e.g. the reader of the mjpeg pipe exits,
+    the other errors(e.g. the udp client is unreachable for a while) only drop the frame.
+    the latency of the frames sent is observed into latency, and logged at debug level with the target esp_vtx_gs_rs::latency.
+*/
+pub fn spawn_output<F>(
+    name: &str,
+    queue: FrameQueue,
+    latency: Arc<Mutex<LatencyStats>>,
+    mut output: F,
+) -> JoinHandle<()>
+where
+    F: FnMut(&Frame) -> io::Result<()> + Send + 'static,
+{
+    let name = name.to_string();
+    std::thread::Builder::new()
+        .name(format!("output-{}", name))
+        .spawn(move || {
+            while let Some(frame) = queue.pop() {
+                if let Err(e) = output(&frame) {
+                    if is_fatal(&e) {
+                        log::warn!("{} output failed:{}, disable it.", name, e);
+                        queue.close();
+                        break;
+                    }
+                    crate::warn_limited!("{} output failed:{}", name, e);
+                    continue;
+                }
+                let frame_latency = FrameLatency::new(&frame, Some(SystemTime::now()));
+                latency.lock().unwrap().observe_output(&name, &frame_latency);
+                log::debug!(target: "esp_vtx_gs_rs::latency", "frame {} {}: {}", frame.frame_index(), name, frame_latency);
+            }
+        })
+        .unwrap()
+}
+
+// the output could never succeed again
+fn is_fatal(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::BrokenPipe
+}
+
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub(crate) fn unix_micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|x| x.as_micros() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            io,
+            sync::{Arc, Mutex},
+        };
+
+        use crate::{
+            latency::LatencyStats,
+            output::{
+                queue::{FrameQueue, QueuePolicy},
+                spawn_output,
+            },
+            Frame,
+        };
+
+        #[test]
+        fn test_output_errors() {
+            let queue = FrameQueue::new(QueuePolicy::BoundedFifo(8));
+            let latency = Arc::new(Mutex::new(LatencyStats::new()));
+            let mut errors = vec![
+                io::ErrorKind::BrokenPipe,
+                io::ErrorKind::ConnectionRefused,
+                io::ErrorKind::HostUnreachable,
+            ];
+            for i in 0..4 {
+                queue.push(Arc::new(Frame::new(i)));
+            }
+            let output = spawn_output("test", queue.clone(), latency, move |_| match errors.pop() {
+                Some(kind) => Err(io::Error::from(kind)),
+                None => Ok(()),
+            });
+            output.join().unwrap();
+            // the transient errors are skipped, and the broken pipe disables the output
+            assert_eq!(queue.stats().delivered, 3);
+            queue.push(Arc::new(Frame::new(4))); // closed
+            assert_eq!(queue.stats().pushed, 4);
+        }
+    }
+}
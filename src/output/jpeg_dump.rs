@@ -0,0 +1,269 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use super::unix_millis;
+use crate::Frame;
+
+/*
+    Dump every completed frame to a single jpeg file:
+        DIR/SESSION_FRAMEINDEX_RECVMS.jpg
+    with an optional json-lines sidecar DIR/SESSION.jsonl, one line per dumped frame.
+
+    When max_bytes is set, the oldest jpeg files of this session are removed
+    to keep the disk usage under the cap, so the last frames before a crash are always kept.
+    The cap counts the files of the session already in DIR(e.g. before a restart with the same session)
+    and the sidecar when it is enabled, which is never removed.
+    RECVMS and recv_time_ms are the capture time of the first packet of the frame.
+*/
+pub struct JpegDumper {
+    dir: PathBuf,
+    session: String,
+    sidecar: Option<BufWriter<File>>,
+    max_bytes: Option<u64>,
+    used_bytes: u64,
+    dumped_files: VecDeque<(PathBuf, u64)>,
+}
+
+#[derive(Serialize)]
+struct SidecarRecord<'a> {
+    file: &'a str,
+    frame_index: u32,
+    recv_time_ms: u64,
+    complete_time_ms: Option<u64>,
+    size: usize,
+    parts: u8,
+    resolution: Option<u8>,
+    jpeg_error: Option<String>,
+    stats: SidecarStats,
+}
+
+#[derive(Serialize)]
+struct SidecarStats {
+    uptime_ms: u64,
+    packet_count: u32,
+    frame_count: u32,
+    broken_block_count: u32,
+    jpeg_error_count: u32,
+    rssi: Option<i8>,
+}
+
+impl JpegDumper {
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        session: String,
+        sidecar: bool,
+        max_bytes: Option<u64>,
+    ) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let sidecar_path = dir.join(format!("{}.jsonl", session));
+        let mut used_bytes = 0;
+        let sidecar = if sidecar {
+            used_bytes += fs::metadata(&sidecar_path).map_or(0, |x| x.len());
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(sidecar_path)?;
+            Some(BufWriter::new(file))
+        } else {
+            None
+        };
+
+        let dumped_files = Self::session_files(&dir, &session)?;
+        used_bytes += dumped_files.iter().map(|(_, size)| size).sum::<u64>();
+
+        let mut dumper = JpegDumper {
+            dir,
+            session,
+            sidecar,
+            max_bytes,
+            used_bytes,
+            dumped_files,
+        };
+        dumper.remove_oldest_over_cap()?;
+        Ok(dumper)
+    }
+
+    /*
+        the jpeg files of the session in the dir, oldest first.
+        The names sort by the frame index, which restarts with the air unit, so they are sorted by the modified time first.
+    */
+    fn session_files(dir: &Path, session: &str) -> io::Result<VecDeque<(PathBuf, u64)>> {
+        let prefix = format!("{}_", session);
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !name.starts_with(&prefix) || !name.ends_with(".jpg") {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            files.push((metadata.modified()?, entry.path(), metadata.len()));
+        }
+        files.sort();
+        Ok(files.into_iter().map(|(_, path, size)| (path, size)).collect())
+    }
+
+    pub fn dump_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let data = frame.get_jpegdata();
+        let recv_time_ms = unix_millis(frame.capture_time());
+        let file_name = format!(
+            "{}_{:08}_{}.jpg",
+            self.session,
+            frame.frame_index(),
+            recv_time_ms
+        );
+        let path = self.dir.join(&file_name);
+        fs::write(&path, &data)?;
+
+        self.used_bytes += data.len() as u64;
+        self.dumped_files.push_back((path, data.len() as u64));
+
+        if let Some(sidecar) = self.sidecar.as_mut() {
+            let stats = &frame.link_stats;
+            let record = SidecarRecord {
+                file: &file_name,
+                frame_index: frame.frame_index(),
+                recv_time_ms,
+                complete_time_ms: frame.complete_time.map(unix_millis),
+                size: data.len(),
+                parts: frame.parts_count(),
+                resolution: frame.resolution(),
+                jpeg_error: frame.jpeg_error.as_ref().map(|x| x.to_string()),
+                stats: SidecarStats {
+                    uptime_ms: stats
+                        .start_time
+                        .elapsed()
+                        .map(|x| x.as_millis() as u64)
+                        .unwrap_or(0),
+                    packet_count: stats.packet_count,
+                    frame_count: stats.frame_count,
+                    broken_block_count: stats.broken_block_count,
+                    jpeg_error_count: stats.jpeg_error_count,
+                    rssi: stats.rssi,
+                },
+            };
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            sidecar.write_all(&line)?;
+            sidecar.flush()?;
+            self.used_bytes += line.len() as u64;
+        }
+        self.remove_oldest_over_cap()
+
+    }
+
+    fn remove_oldest_over_cap(&mut self) -> io::Result<()> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Ok(());
+        };
+        // always keep the newest frame, even if it is bigger than the cap itself
+        while self.used_bytes > max_bytes && self.dumped_files.len() > 1 {
+            let (path, size) = self.dumped_files.pop_front().unwrap();
+            self.used_bytes -= size;
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::fs;
+
+        use crate::{
+            output::jpeg_dump::JpegDumper, packet::Air2GroundFramePacket, protocol::ProtocolVersion,
+            Frame,
+        };
+
+        fn new_frame(frame_index: u32, jpeg_size: usize) -> Frame {
+            let mut frame = Frame::new(frame_index);
+            let mut data = vec![0u8; 13];
+            data.resize(13 + jpeg_size, 0xff);
+            frame.parts.insert(0, Air2GroundFramePacket::decode(ProtocolVersion::V1, data).unwrap());
+            frame.parts_count = 1;
+            frame
+        }
+
+        #[test]
+        fn test_dump_with_cap() {
+            let dir = std::env::temp_dir().join("esp_vtx_gs_rs_test_dump_with_cap");
+            let _ = fs::remove_dir_all(&dir);
+            // the sidecar is counted by the cap too, refer test_cap_counts_existing_files
+            let mut dumper = JpegDumper::new(&dir, "s".to_string(), false, Some(250)).unwrap();
+            for i in 0..4 {
+                dumper.dump_frame(&new_frame(i, 100)).unwrap();
+            }
+
+            let mut jpegs: Vec<String> = fs::read_dir(&dir)
+                .unwrap()
+                .map(|x| x.unwrap().file_name().into_string().unwrap())
+                .filter(|x| x.ends_with(".jpg"))
+                .collect();
+            jpegs.sort();
+            assert_eq!(jpegs.len(), 2);
+            assert!(jpegs[0].starts_with("s_00000002_"));
+            assert!(jpegs[1].starts_with("s_00000003_"));
+
+            assert!(!dir.join("s.jsonl").exists());
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_cap_counts_existing_files() {
+            let dir = std::env::temp_dir().join("esp_vtx_gs_rs_test_cap_counts_existing_files");
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("s_00000001_1.jpg"), [0xff; 100]).unwrap();
+            fs::write(dir.join("other_00000001_1.jpg"), [0xff; 100]).unwrap();
+
+            let mut dumper = JpegDumper::new(&dir, "s".to_string(), true, Some(1000)).unwrap();
+            assert_eq!(dumper.used_bytes, 100);
+            let mut frame = new_frame(2, 100);
+            let capture_time = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1234);
+            frame.timestamps.first_packet = Some(capture_time);
+            dumper.dump_frame(&frame).unwrap();
+            let sidecar_len = fs::metadata(dir.join("s.jsonl")).unwrap().len();
+            assert_eq!(dumper.used_bytes, 200 + sidecar_len);
+            assert!(dir.join("s_00000002_1234.jpg").exists());
+            let sidecar = fs::read_to_string(dir.join("s.jsonl")).unwrap();
+            assert!(sidecar.contains("\"recv_time_ms\":1234"));
+
+            // the old file of the session is removed first, the one of the other session is kept
+            dumper.max_bytes = Some(dumper.used_bytes + 100);
+            dumper.dump_frame(&new_frame(3, 100)).unwrap();
+            assert!(!dir.join("s_00000001_1.jpg").exists());
+            assert!(dir.join("other_00000001_1.jpg").exists());
+            assert_eq!(dumper.dumped_files.back().unwrap().1, 100);
+            assert!(dumper.dumped_files.back().unwrap().0.exists());
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_cap_skips_disabled_sidecar() {
+            let dir = std::env::temp_dir().join("esp_vtx_gs_rs_test_cap_skips_disabled_sidecar");
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            // left by an earlier run with the sidecar
+            fs::write(dir.join("s.jsonl"), [b'{'; 1000]).unwrap();
+
+            let dumper = JpegDumper::new(&dir, "s".to_string(), false, Some(250)).unwrap();
+            assert_eq!(dumper.used_bytes, 0);
+            let dumper = JpegDumper::new(&dir, "s".to_string(), true, Some(2000)).unwrap();
+            assert_eq!(dumper.used_bytes, 1000);
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::unix::{ffi::OsStrExt, fs::OpenOptionsExt, io::AsRawFd},
+    path::PathBuf,
+};
+
+use crate::Frame;
+
+/*
+    Write the concatenated jpeg stream of completed frames to stdout or a named pipe(FIFO),
+    so it could be directly piped into other tools:
+        esp-vtx-gs-rs -d wlan0 --mjpeg-out - | ffmpeg -f mjpeg -i - ...
+
+    When writing to stdout, nothing else should be printed to stdout.
+*/
+pub enum MjpegPipe {
+    Stdout,
+    Fifo { path: PathBuf, file: Option<File> },
+}
+
+impl MjpegPipe {
+    /*
+        "-" means stdout, otherwise a FIFO path.
+        The FIFO is created if the path does not exist.
+    */
+    pub fn new(target: &str) -> io::Result<Self> {
+        if target == "-" {
+            return Ok(MjpegPipe::Stdout);
+        }
+
+        let path = PathBuf::from(target);
+        if !path.exists() {
+            let c_path = CString::new(path.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(MjpegPipe::Fifo { path, file: None })
+    }
+
+    /*
+        Frames are dropped silently when nobody is reading the FIFO,
+        and the FIFO is reopened when the reader comes back.
+    */
+    pub fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let data = frame.get_jpegdata();
+        match self {
+            MjpegPipe::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&data)?;
+                stdout.flush()
+            }
+            MjpegPipe::Fifo { path, file } => {
+                if file.is_none() {
+                    *file = Self::open_fifo_writer(path)?;
+                }
+                let Some(writer) = file.as_mut() else {
+                    return Ok(()); // no reader yet
+                };
+
+                match writer.write_all(&data) {
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                        *file = None; // the reader is gone
+                        Ok(())
+                    }
+                    ret => ret,
+                }
+            }
+        }
+    }
+
+    fn open_fifo_writer(path: &PathBuf) -> io::Result<Option<File>> {
+        // open with O_NONBLOCK fails with ENXIO instead of blocking when there is no reader
+        let file = match OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) if e.raw_os_error() == Some(libc::ENXIO) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // switch back to blocking writes, so a frame will never be partially written
+        unsafe {
+            let fd = file.as_raw_fd();
+            let flags = libc::fcntl(fd, libc::F_GETFL);
+            if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(Some(file))
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    str::FromStr,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Frame;
+
+/*
+    Policy of a frame queue when the output could not keep up with the receiver.
+    format in cli: "latest", "drop-oldest:N" or "fifo:N"
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum QueuePolicy {
+    LatestOnly,         // only keep the newest frame, the lowest latency
+    DropOldest(usize),  // keep at most N frames, drop the oldest one when full
+    BoundedFifo(usize), // keep at most N frames, drop the incoming one when full
+}
+
+impl QueuePolicy {
+    fn capacity(&self) -> usize {
+        match *self {
+            QueuePolicy::LatestOnly => 1,
+            QueuePolicy::DropOldest(n) | QueuePolicy::BoundedFifo(n) => n.max(1),
+        }
+    }
+}
+
+impl FromStr for QueuePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, size) = match s.split_once(':') {
+            Some((name, size)) => (
+                name,
+                Some(size.parse::<usize>().map_err(|e| format!("invalid queue size {}: {}", size, e))?),
+            ),
+            None => (s, None),
+        };
+        match (name, size) {
+            ("latest", None) => Ok(QueuePolicy::LatestOnly),
+            ("drop-oldest", size) => Ok(QueuePolicy::DropOldest(size.unwrap_or(4))),
+            ("fifo", size) => Ok(QueuePolicy::BoundedFifo(size.unwrap_or(32))),
+            _ => Err(format!(
+                "invalid queue policy:{}, should be latest, drop-oldest[:N] or fifo[:N]",
+                s
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for QueuePolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<QueuePolicy> for String {
+    fn from(value: QueuePolicy) -> String {
+        value.to_string()
+    }
+}
+
+impl Display for QueuePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueuePolicy::LatestOnly => write!(f, "latest"),
+            QueuePolicy::DropOldest(n) => write!(f, "drop-oldest:{}", n),
+            QueuePolicy::BoundedFifo(n) => write!(f, "fifo:{}", n),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub pushed: u64,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub len: usize,
+}
+
+struct QueueState {
+    frames: VecDeque<Arc<Frame>>,
+    stats: QueueStats,
+    closed: bool,
+}
+
+/*
+    A frame delivery channel between the receiver and one output.
+    Clone it to share the same queue between the pushing and the popping threads.
+*/
+#[derive(Clone)]
+pub struct FrameQueue {
+    policy: QueuePolicy,
+    inner: Arc<(Mutex<QueueState>, Condvar)>,
+}
+
+impl FrameQueue {
+    pub fn new(policy: QueuePolicy) -> Self {
+        FrameQueue {
+            policy,
+            inner: Arc::new((
+                Mutex::new(QueueState {
+                    frames: VecDeque::with_capacity(policy.capacity()),
+                    stats: QueueStats::default(),
+                    closed: false,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    pub fn policy(&self) -> QueuePolicy {
+        self.policy
+    }
+
+    pub fn push(&self, frame: Arc<Frame>) {
+        let (lock, cond) = &*self.inner;
+        let mut state = lock.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        state.stats.pushed += 1;
+        if state.frames.len() >= self.policy.capacity() {
+            state.stats.dropped += 1;
+            match self.policy {
+                QueuePolicy::LatestOnly | QueuePolicy::DropOldest(_) => {
+                    state.frames.pop_front();
+                }
+                QueuePolicy::BoundedFifo(_) => return,
+            }
+        }
+        state.frames.push_back(frame);
+        cond.notify_one();
+    }
+
+    /*
+        block until a frame is available, return None after the queue is closed and drained.
+    */
+    pub fn pop(&self) -> Option<Arc<Frame>> {
+        let (lock, cond) = &*self.inner;
+        let mut state = lock.lock().unwrap();
+        loop {
+            if let Some(frame) = state.frames.pop_front() {
+                state.stats.delivered += 1;
+                return Some(frame);
+            }
+            if state.closed {
+                return None;
+            }
+            state = cond.wait(state).unwrap();
+        }
+    }
+
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<Arc<Frame>> {
+        let (lock, cond) = &*self.inner;
+        let state = lock.lock().unwrap();
+        let (mut state, _) = cond
+            .wait_timeout_while(state, timeout, |x| x.frames.is_empty() && !x.closed)
+            .unwrap();
+        let frame = state.frames.pop_front();
+        if frame.is_some() {
+            state.stats.delivered += 1;
+        }
+        frame
+    }
+
+    pub fn close(&self) {
+        let (lock, cond) = &*self.inner;
+        lock.lock().unwrap().closed = true;
+        cond.notify_all();
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.inner.0.lock().unwrap();
+        QueueStats {
+            len: state.frames.len(),
+            ..state.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{sync::Arc, time::Duration};
+
+        use crate::{
+            output::queue::{FrameQueue, QueuePolicy},
+            Frame,
+        };
+
+        fn push_frames(queue: &FrameQueue, indexs: std::ops::Range<u32>) {
+            for i in indexs {
+                queue.push(Arc::new(Frame::new(i)));
+            }
+        }
+
+        fn pop_all(queue: &FrameQueue) -> Vec<u32> {
+            let mut ret = Vec::new();
+            while let Some(frame) = queue.pop_timeout(Duration::ZERO) {
+                ret.push(frame.frame_index());
+            }
+            ret
+        }
+
+        #[test]
+        fn test_policy() {
+            let queue = FrameQueue::new(QueuePolicy::LatestOnly);
+            push_frames(&queue, 0..3);
+            assert_eq!(pop_all(&queue), vec![2]);
+            assert_eq!(queue.stats().dropped, 2);
+
+            let queue = FrameQueue::new(QueuePolicy::DropOldest(2));
+            push_frames(&queue, 0..4);
+            assert_eq!(pop_all(&queue), vec![2, 3]);
+
+            let queue = FrameQueue::new(QueuePolicy::BoundedFifo(2));
+            push_frames(&queue, 0..4);
+            assert_eq!(pop_all(&queue), vec![0, 1]);
+            let stats = queue.stats();
+            assert_eq!((stats.pushed, stats.delivered, stats.dropped), (4, 2, 2));
+        }
+
+        #[test]
+        fn test_pop_all_pushed_frames() {
+            // frames pushed between two wakeups should all be delivered without waiting for a new frame
+            let queue = FrameQueue::new(QueuePolicy::BoundedFifo(8));
+            push_frames(&queue, 0..2);
+            let queue_pop = queue.clone();
+            let handle = std::thread::spawn(move || {
+                let mut ret = Vec::new();
+                while let Some(frame) = queue_pop.pop() {
+                    ret.push(frame.frame_index());
+                }
+                ret
+            });
+            queue.close();
+            assert_eq!(handle.join().unwrap(), vec![0, 1]);
+        }
+
+        #[test]
+        fn test_parse_policy() {
+            assert_eq!("latest".parse(), Ok(QueuePolicy::LatestOnly));
+            assert_eq!("drop-oldest:3".parse(), Ok(QueuePolicy::DropOldest(3)));
+            assert_eq!("fifo".parse(), Ok(QueuePolicy::BoundedFifo(32)));
+            assert!("fifo:x".parse::<QueuePolicy>().is_err());
+            assert!("lifo".parse::<QueuePolicy>().is_err());
+        }
+    }
+}
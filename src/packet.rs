@@ -1,13 +1,14 @@
-use std::{mem::MaybeUninit, slice};
-
 use crc::{Crc, CRC_8_SMBUS};
 
+use crate::codec::{Codec, CodecError};
 use crate::packet_h_bind::*;
+use crate::protocol::ProtocolVersion;
 
 
 pub struct Air2GroundFramePacket {
     pub header: Air2Ground_Video_Packet,
     pub data: Vec<u8>,
+    pub crc_ok: bool,
 }
 
 static SMBUS_CRC:Crc<u8> = Crc::<u8>::new(&CRC_8_SMBUS);
@@ -22,37 +23,30 @@ impl Air2Ground_Video_Packet{
     #[inline(always)]
     pub fn crc_cal(&mut self) -> u8{
         self._base.crc = 0;
-        unsafe{
-            let data = slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>());
-            self._base.crc =  SMBUS_CRC.checksum(data);
-            self._base.crc
-        } 
+        self._base.crc = SMBUS_CRC.checksum(&self.to_bytes());
+        self._base.crc
     }
 }
 
 impl Air2GroundFramePacket {
-    pub fn from_bytes(mut origin_data: Vec<u8>) -> Self {
-        let payload = origin_data.split_off(std::mem::size_of::<Air2Ground_Video_Packet>());
-        let mut header = MaybeUninit::<Air2Ground_Video_Packet>::zeroed();
-        let mut header_inited;
-        unsafe {
-            std::ptr::copy_nonoverlapping(
-                origin_data.as_ptr() as *const Air2Ground_Video_Packet,
-                header.as_mut_ptr(),
-                1,
-            );
-            header_inited = header.assume_init();
-        }
+    /*
+        decode a video packet of the protocol version, the header is converted to the V1 struct.
+    */
+    pub fn decode(version: ProtocolVersion, mut origin_data: Vec<u8>) -> Result<Self, CodecError> {
+        let header = version.decode_video_header(&origin_data)?;
+        let crc_ok = version.video_crc_valid(&origin_data);
+        let payload = origin_data.split_off(version.video_header_size());
 
-        if !header_inited.crc_check(){
+        if !crc_ok{
             //panic!(" crc failed!");
-            println!("[warning]crc check failed."); // just add a warning, as a crc failed frame is not a big issue on ground station.
+            crate::warn_limited!("crc check failed, frame_index:{}", { header.frame_index }); // just add a warning, as a crc failed frame is not a big issue on ground station.
         }
 
-        Air2GroundFramePacket {
-            header: header_inited,
+        Ok(Air2GroundFramePacket {
+            header,
             data: payload,
-        }
+            crc_ok,
+        })
     }
 }
 
@@ -100,12 +94,9 @@ impl Default for Ground2Air_Header {
 }
 
 impl Ground2Air_Config_Packet{
-    fn update_crc(&mut self){
+    pub fn update_crc(&mut self){
         self._base.crc = 0;
-        unsafe{
-            let data = slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>());
-            self._base.crc =  SMBUS_CRC.checksum(data);
-        } 
+        self._base.crc = SMBUS_CRC.checksum(&self.to_bytes());
     }
 }
 
@@ -122,8 +113,26 @@ impl Default for Ground2Air_Config_Packet {
             dvr_record: false,
             camera: Default::default(),
         };
-        ret._base.size = std::mem::size_of::<Self>() as u32;
+        ret._base.size = Self::SIZE as u32;
         ret.update_crc();
         ret
     }
 }
+
+impl Ground2Air_Data_Packet{
+    /*
+        header followed by the payload, the size and crc cover both of them.
+    */
+    pub fn with_payload(payload: &[u8]) -> Vec<u8>{
+        let mut header = Ground2Air_Header{
+            type_: Ground2Air_Header_Type_Data,
+            size: (Self::SIZE + payload.len()) as u32,
+            crc: 0,
+        };
+        let mut ret = header.to_bytes();
+        ret.extend_from_slice(payload);
+        header.crc = SMBUS_CRC.checksum(&ret);
+        ret[..Self::SIZE].copy_from_slice(&header.to_bytes());
+        ret
+    }
+}
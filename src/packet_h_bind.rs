@@ -1,1200 +1,32 @@
-#![allow(warnings)] 
-/* automatically generated by rust-bindgen 0.69.4 */
+#![allow(non_camel_case_types, non_upper_case_globals)]
+/*
+    The protocol types shared with the air unit, mirroring bind_src/packets.h and bind_src/structures.h.
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct __BindgenBitfieldUnit<Storage> {
-    storage: Storage,
-}
-impl<Storage> __BindgenBitfieldUnit<Storage> {
-    #[inline]
-    pub const fn new(storage: Storage) -> Self {
-        Self { storage }
-    }
-}
-impl<Storage> __BindgenBitfieldUnit<Storage>
-where
-    Storage: AsRef<[u8]> + AsMut<[u8]>,
-{
-    #[inline]
-    pub fn get_bit(&self, index: usize) -> bool {
-        debug_assert!(index / 8 < self.storage.as_ref().len());
-        let byte_index = index / 8;
-        let byte = self.storage.as_ref()[byte_index];
-        let bit_index = if cfg!(target_endian = "big") {
-            7 - (index % 8)
-        } else {
-            index % 8
-        };
-        let mask = 1 << bit_index;
-        byte & mask == mask
-    }
-    #[inline]
-    pub fn set_bit(&mut self, index: usize, val: bool) {
-        debug_assert!(index / 8 < self.storage.as_ref().len());
-        let byte_index = index / 8;
-        let byte = &mut self.storage.as_mut()[byte_index];
-        let bit_index = if cfg!(target_endian = "big") {
-            7 - (index % 8)
-        } else {
-            index % 8
-        };
-        let mask = 1 << bit_index;
-        if val {
-            *byte |= mask;
-        } else {
-            *byte &= !mask;
-        }
-    }
-    #[inline]
-    pub fn get(&self, bit_offset: usize, bit_width: u8) -> u64 {
-        debug_assert!(bit_width <= 64);
-        debug_assert!(bit_offset / 8 < self.storage.as_ref().len());
-        debug_assert!((bit_offset + (bit_width as usize)) / 8 <= self.storage.as_ref().len());
-        let mut val = 0;
-        for i in 0..(bit_width as usize) {
-            if self.get_bit(i + bit_offset) {
-                let index = if cfg!(target_endian = "big") {
-                    bit_width as usize - 1 - i
-                } else {
-                    i
-                };
-                val |= 1 << index;
-            }
-        }
-        val
-    }
-    #[inline]
-    pub fn set(&mut self, bit_offset: usize, bit_width: u8, val: u64) {
-        debug_assert!(bit_width <= 64);
-        debug_assert!(bit_offset / 8 < self.storage.as_ref().len());
-        debug_assert!((bit_offset + (bit_width as usize)) / 8 <= self.storage.as_ref().len());
-        for i in 0..(bit_width as usize) {
-            let mask = 1 << i;
-            let val_bit_is_set = val & mask == mask;
-            let index = if cfg!(target_endian = "big") {
-                bit_width as usize - 1 - i
-            } else {
-                i
-            };
-            self.set_bit(index + bit_offset, val_bit_is_set);
-        }
-    }
-}
-pub const _GLIBCXX_CXX_CONFIG_H: u32 = 1;
-pub const _GLIBCXX_RELEASE: u32 = 11;
-pub const __GLIBCXX__: u32 = 20230528;
-pub const _GLIBCXX_HAVE_ATTRIBUTE_VISIBILITY: u32 = 1;
-pub const _GLIBCXX_USE_DEPRECATED: u32 = 1;
-pub const _GLIBCXX_EXTERN_TEMPLATE: u32 = 1;
-pub const _GLIBCXX_USE_DUAL_ABI: u32 = 1;
-pub const _GLIBCXX_USE_CXX11_ABI: u32 = 1;
-pub const _GLIBCXX_INLINE_VERSION: u32 = 0;
-pub const _GLIBCXX_USE_ALLOCATOR_NEW: u32 = 1;
-pub const _GLIBCXX_OS_DEFINES: u32 = 1;
-pub const __NO_CTYPE: u32 = 1;
-pub const _FEATURES_H: u32 = 1;
-pub const _ISOC95_SOURCE: u32 = 1;
-pub const _ISOC99_SOURCE: u32 = 1;
-pub const _ISOC11_SOURCE: u32 = 1;
-pub const _ISOC2X_SOURCE: u32 = 1;
-pub const _POSIX_SOURCE: u32 = 1;
-pub const _POSIX_C_SOURCE: u32 = 200809;
-pub const _XOPEN_SOURCE: u32 = 700;
-pub const _XOPEN_SOURCE_EXTENDED: u32 = 1;
-pub const _LARGEFILE64_SOURCE: u32 = 1;
-pub const _DEFAULT_SOURCE: u32 = 1;
-pub const _ATFILE_SOURCE: u32 = 1;
-pub const _DYNAMIC_STACK_SIZE_SOURCE: u32 = 1;
-pub const __GLIBC_USE_ISOC2X: u32 = 1;
-pub const __USE_ISOC11: u32 = 1;
-pub const __USE_ISOC99: u32 = 1;
-pub const __USE_ISOC95: u32 = 1;
-pub const __USE_ISOCXX11: u32 = 1;
-pub const __USE_POSIX: u32 = 1;
-pub const __USE_POSIX2: u32 = 1;
-pub const __USE_POSIX199309: u32 = 1;
-pub const __USE_POSIX199506: u32 = 1;
-pub const __USE_XOPEN2K: u32 = 1;
-pub const __USE_XOPEN2K8: u32 = 1;
-pub const __USE_XOPEN: u32 = 1;
-pub const __USE_XOPEN_EXTENDED: u32 = 1;
-pub const __USE_UNIX98: u32 = 1;
-pub const _LARGEFILE_SOURCE: u32 = 1;
-pub const __USE_XOPEN2K8XSI: u32 = 1;
-pub const __USE_XOPEN2KXSI: u32 = 1;
-pub const __USE_LARGEFILE: u32 = 1;
-pub const __USE_LARGEFILE64: u32 = 1;
-pub const __WORDSIZE: u32 = 64;
-pub const __WORDSIZE_TIME64_COMPAT32: u32 = 1;
-pub const __SYSCALL_WORDSIZE: u32 = 64;
-pub const __TIMESIZE: u32 = 64;
-pub const __USE_MISC: u32 = 1;
-pub const __USE_ATFILE: u32 = 1;
-pub const __USE_DYNAMIC_STACK_SIZE: u32 = 1;
-pub const __USE_GNU: u32 = 1;
-pub const __USE_FORTIFY_LEVEL: u32 = 0;
-pub const __GLIBC_USE_DEPRECATED_GETS: u32 = 0;
-pub const __GLIBC_USE_DEPRECATED_SCANF: u32 = 0;
-pub const _STDC_PREDEF_H: u32 = 1;
-pub const __STDC_IEC_559__: u32 = 1;
-pub const __STDC_IEC_60559_BFP__: u32 = 201404;
-pub const __STDC_IEC_559_COMPLEX__: u32 = 1;
-pub const __STDC_IEC_60559_COMPLEX__: u32 = 201404;
-pub const __STDC_ISO_10646__: u32 = 201706;
-pub const __GNU_LIBRARY__: u32 = 6;
-pub const __GLIBC__: u32 = 2;
-pub const __GLIBC_MINOR__: u32 = 35;
-pub const _SYS_CDEFS_H: u32 = 1;
-pub const __glibc_c99_flexarr_available: u32 = 1;
-pub const __LDOUBLE_REDIRECTS_TO_FLOAT128_ABI: u32 = 0;
-pub const __HAVE_GENERIC_SELECTION: u32 = 0;
-pub const _GLIBCXX_GTHREAD_USE_WEAK: u32 = 0;
-pub const _GLIBCXX_CPU_DEFINES: u32 = 1;
-pub const _GLIBCXX_FAST_MATH: u32 = 0;
-pub const _GLIBCXX_USE_FLOAT128: u32 = 1;
-pub const _GLIBCXX_FLOAT_IS_IEEE_BINARY32: u32 = 1;
-pub const _GLIBCXX_DOUBLE_IS_IEEE_BINARY64: u32 = 1;
-pub const _GLIBCXX_HAVE_BUILTIN_HAS_UNIQ_OBJ_REP: u32 = 1;
-pub const _GLIBCXX_HAVE_BUILTIN_IS_AGGREGATE: u32 = 1;
-pub const _GLIBCXX_HAVE_BUILTIN_IS_CONSTANT_EVALUATED: u32 = 1;
-pub const _GLIBCXX_HAVE_BUILTIN_IS_SAME: u32 = 1;
-pub const _GLIBCXX_HAVE_BUILTIN_LAUNDER: u32 = 1;
-pub const _GLIBCXX_HAVE_ACOSF: u32 = 1;
-pub const _GLIBCXX_HAVE_ACOSL: u32 = 1;
-pub const _GLIBCXX_HAVE_ALIGNED_ALLOC: u32 = 1;
-pub const _GLIBCXX_HAVE_ARPA_INET_H: u32 = 1;
-pub const _GLIBCXX_HAVE_ASINF: u32 = 1;
-pub const _GLIBCXX_HAVE_ASINL: u32 = 1;
-pub const _GLIBCXX_HAVE_AS_SYMVER_DIRECTIVE: u32 = 1;
-pub const _GLIBCXX_HAVE_ATAN2F: u32 = 1;
-pub const _GLIBCXX_HAVE_ATAN2L: u32 = 1;
-pub const _GLIBCXX_HAVE_ATANF: u32 = 1;
-pub const _GLIBCXX_HAVE_ATANL: u32 = 1;
-pub const _GLIBCXX_HAVE_ATOMIC_LOCK_POLICY: u32 = 1;
-pub const _GLIBCXX_HAVE_AT_QUICK_EXIT: u32 = 1;
-pub const _GLIBCXX_HAVE_CEILF: u32 = 1;
-pub const _GLIBCXX_HAVE_CEILL: u32 = 1;
-pub const _GLIBCXX_HAVE_COMPLEX_H: u32 = 1;
-pub const _GLIBCXX_HAVE_COSF: u32 = 1;
-pub const _GLIBCXX_HAVE_COSHF: u32 = 1;
-pub const _GLIBCXX_HAVE_COSHL: u32 = 1;
-pub const _GLIBCXX_HAVE_COSL: u32 = 1;
-pub const _GLIBCXX_HAVE_DIRENT_H: u32 = 1;
-pub const _GLIBCXX_HAVE_DLFCN_H: u32 = 1;
-pub const _GLIBCXX_HAVE_ENDIAN_H: u32 = 1;
-pub const _GLIBCXX_HAVE_EXCEPTION_PTR_SINCE_GCC46: u32 = 1;
-pub const _GLIBCXX_HAVE_EXECINFO_H: u32 = 1;
-pub const _GLIBCXX_HAVE_EXPF: u32 = 1;
-pub const _GLIBCXX_HAVE_EXPL: u32 = 1;
-pub const _GLIBCXX_HAVE_FABSF: u32 = 1;
-pub const _GLIBCXX_HAVE_FABSL: u32 = 1;
-pub const _GLIBCXX_HAVE_FCNTL_H: u32 = 1;
-pub const _GLIBCXX_HAVE_FENV_H: u32 = 1;
-pub const _GLIBCXX_HAVE_FINITE: u32 = 1;
-pub const _GLIBCXX_HAVE_FINITEF: u32 = 1;
-pub const _GLIBCXX_HAVE_FINITEL: u32 = 1;
-pub const _GLIBCXX_HAVE_FLOAT_H: u32 = 1;
-pub const _GLIBCXX_HAVE_FLOORF: u32 = 1;
-pub const _GLIBCXX_HAVE_FLOORL: u32 = 1;
-pub const _GLIBCXX_HAVE_FMODF: u32 = 1;
-pub const _GLIBCXX_HAVE_FMODL: u32 = 1;
-pub const _GLIBCXX_HAVE_FREXPF: u32 = 1;
-pub const _GLIBCXX_HAVE_FREXPL: u32 = 1;
-pub const _GLIBCXX_HAVE_GETIPINFO: u32 = 1;
-pub const _GLIBCXX_HAVE_GETS: u32 = 1;
-pub const _GLIBCXX_HAVE_HYPOT: u32 = 1;
-pub const _GLIBCXX_HAVE_HYPOTF: u32 = 1;
-pub const _GLIBCXX_HAVE_HYPOTL: u32 = 1;
-pub const _GLIBCXX_HAVE_ICONV: u32 = 1;
-pub const _GLIBCXX_HAVE_INT64_T: u32 = 1;
-pub const _GLIBCXX_HAVE_INT64_T_LONG: u32 = 1;
-pub const _GLIBCXX_HAVE_INTTYPES_H: u32 = 1;
-pub const _GLIBCXX_HAVE_ISINFF: u32 = 1;
-pub const _GLIBCXX_HAVE_ISINFL: u32 = 1;
-pub const _GLIBCXX_HAVE_ISNANF: u32 = 1;
-pub const _GLIBCXX_HAVE_ISNANL: u32 = 1;
-pub const _GLIBCXX_HAVE_ISWBLANK: u32 = 1;
-pub const _GLIBCXX_HAVE_LC_MESSAGES: u32 = 1;
-pub const _GLIBCXX_HAVE_LDEXPF: u32 = 1;
-pub const _GLIBCXX_HAVE_LDEXPL: u32 = 1;
-pub const _GLIBCXX_HAVE_LIBINTL_H: u32 = 1;
-pub const _GLIBCXX_HAVE_LIMIT_AS: u32 = 1;
-pub const _GLIBCXX_HAVE_LIMIT_DATA: u32 = 1;
-pub const _GLIBCXX_HAVE_LIMIT_FSIZE: u32 = 1;
-pub const _GLIBCXX_HAVE_LIMIT_RSS: u32 = 1;
-pub const _GLIBCXX_HAVE_LIMIT_VMEM: u32 = 0;
-pub const _GLIBCXX_HAVE_LINK: u32 = 1;
-pub const _GLIBCXX_HAVE_LINUX_FUTEX: u32 = 1;
-pub const _GLIBCXX_HAVE_LINUX_RANDOM_H: u32 = 1;
-pub const _GLIBCXX_HAVE_LINUX_TYPES_H: u32 = 1;
-pub const _GLIBCXX_HAVE_LOCALE_H: u32 = 1;
-pub const _GLIBCXX_HAVE_LOG10F: u32 = 1;
-pub const _GLIBCXX_HAVE_LOG10L: u32 = 1;
-pub const _GLIBCXX_HAVE_LOGF: u32 = 1;
-pub const _GLIBCXX_HAVE_LOGL: u32 = 1;
-pub const _GLIBCXX_HAVE_MBSTATE_T: u32 = 1;
-pub const _GLIBCXX_HAVE_MEMALIGN: u32 = 1;
-pub const _GLIBCXX_HAVE_MEMORY_H: u32 = 1;
-pub const _GLIBCXX_HAVE_MODF: u32 = 1;
-pub const _GLIBCXX_HAVE_MODFF: u32 = 1;
-pub const _GLIBCXX_HAVE_MODFL: u32 = 1;
-pub const _GLIBCXX_HAVE_NETDB_H: u32 = 1;
-pub const _GLIBCXX_HAVE_NETINET_IN_H: u32 = 1;
-pub const _GLIBCXX_HAVE_NETINET_TCP_H: u32 = 1;
-pub const _GLIBCXX_HAVE_POLL: u32 = 1;
-pub const _GLIBCXX_HAVE_POLL_H: u32 = 1;
-pub const _GLIBCXX_HAVE_POSIX_MEMALIGN: u32 = 1;
-pub const _GLIBCXX_HAVE_POSIX_SEMAPHORE: u32 = 1;
-pub const _GLIBCXX_HAVE_POWF: u32 = 1;
-pub const _GLIBCXX_HAVE_POWL: u32 = 1;
-pub const _GLIBCXX_HAVE_QUICK_EXIT: u32 = 1;
-pub const _GLIBCXX_HAVE_READLINK: u32 = 1;
-pub const _GLIBCXX_HAVE_SECURE_GETENV: u32 = 1;
-pub const _GLIBCXX_HAVE_SETENV: u32 = 1;
-pub const _GLIBCXX_HAVE_SINCOS: u32 = 1;
-pub const _GLIBCXX_HAVE_SINCOSF: u32 = 1;
-pub const _GLIBCXX_HAVE_SINCOSL: u32 = 1;
-pub const _GLIBCXX_HAVE_SINF: u32 = 1;
-pub const _GLIBCXX_HAVE_SINHF: u32 = 1;
-pub const _GLIBCXX_HAVE_SINHL: u32 = 1;
-pub const _GLIBCXX_HAVE_SINL: u32 = 1;
-pub const _GLIBCXX_HAVE_SOCKATMARK: u32 = 1;
-pub const _GLIBCXX_HAVE_SQRTF: u32 = 1;
-pub const _GLIBCXX_HAVE_SQRTL: u32 = 1;
-pub const _GLIBCXX_HAVE_STDALIGN_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STDBOOL_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STDINT_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STDLIB_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STRERROR_L: u32 = 1;
-pub const _GLIBCXX_HAVE_STRERROR_R: u32 = 1;
-pub const _GLIBCXX_HAVE_STRINGS_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STRING_H: u32 = 1;
-pub const _GLIBCXX_HAVE_STRTOF: u32 = 1;
-pub const _GLIBCXX_HAVE_STRTOLD: u32 = 1;
-pub const _GLIBCXX_HAVE_STRUCT_DIRENT_D_TYPE: u32 = 1;
-pub const _GLIBCXX_HAVE_STRXFRM_L: u32 = 1;
-pub const _GLIBCXX_HAVE_SYMLINK: u32 = 1;
-pub const _GLIBCXX_HAVE_SYMVER_SYMBOL_RENAMING_RUNTIME_SUPPORT: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_IOCTL_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_IPC_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_PARAM_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_RESOURCE_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_SDT_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_SEM_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_SOCKET_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_STATVFS_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_STAT_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_SYSINFO_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_TIME_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_TYPES_H: u32 = 1;
-pub const _GLIBCXX_HAVE_SYS_UIO_H: u32 = 1;
-pub const _GLIBCXX_HAVE_S_ISREG: u32 = 1;
-pub const _GLIBCXX_HAVE_TANF: u32 = 1;
-pub const _GLIBCXX_HAVE_TANHF: u32 = 1;
-pub const _GLIBCXX_HAVE_TANHL: u32 = 1;
-pub const _GLIBCXX_HAVE_TANL: u32 = 1;
-pub const _GLIBCXX_HAVE_TGMATH_H: u32 = 1;
-pub const _GLIBCXX_HAVE_TIMESPEC_GET: u32 = 1;
-pub const _GLIBCXX_HAVE_TLS: u32 = 1;
-pub const _GLIBCXX_HAVE_TRUNCATE: u32 = 1;
-pub const _GLIBCXX_HAVE_UCHAR_H: u32 = 1;
-pub const _GLIBCXX_HAVE_UNISTD_H: u32 = 1;
-pub const _GLIBCXX_HAVE_USELOCALE: u32 = 1;
-pub const _GLIBCXX_HAVE_UTIME_H: u32 = 1;
-pub const _GLIBCXX_HAVE_VFWSCANF: u32 = 1;
-pub const _GLIBCXX_HAVE_VSWSCANF: u32 = 1;
-pub const _GLIBCXX_HAVE_VWSCANF: u32 = 1;
-pub const _GLIBCXX_HAVE_WCHAR_H: u32 = 1;
-pub const _GLIBCXX_HAVE_WCSTOF: u32 = 1;
-pub const _GLIBCXX_HAVE_WCTYPE_H: u32 = 1;
-pub const _GLIBCXX_HAVE_WRITEV: u32 = 1;
-pub const _GLIBCXX_HAVE___CXA_THREAD_ATEXIT_IMPL: u32 = 1;
-pub const _GLIBCXX_LT_OBJDIR: &[u8; 7] = b".libs/\0";
-pub const _GLIBCXX_PACKAGE_BUGREPORT: &[u8; 1] = b"\0";
-pub const _GLIBCXX_PACKAGE_NAME: &[u8; 15] = b"package-unused\0";
-pub const _GLIBCXX_PACKAGE_STRING: &[u8; 30] = b"package-unused version-unused\0";
-pub const _GLIBCXX_PACKAGE_TARNAME: &[u8; 10] = b"libstdc++\0";
-pub const _GLIBCXX_PACKAGE_URL: &[u8; 1] = b"\0";
-pub const _GLIBCXX_PACKAGE__GLIBCXX_VERSION: &[u8; 15] = b"version-unused\0";
-pub const _GLIBCXX_STDC_HEADERS: u32 = 1;
-pub const _GLIBCXX_DARWIN_USE_64_BIT_INODE: u32 = 1;
-pub const _GLIBCXX11_USE_C99_COMPLEX: u32 = 1;
-pub const _GLIBCXX11_USE_C99_MATH: u32 = 1;
-pub const _GLIBCXX11_USE_C99_STDIO: u32 = 1;
-pub const _GLIBCXX11_USE_C99_STDLIB: u32 = 1;
-pub const _GLIBCXX11_USE_C99_WCHAR: u32 = 1;
-pub const _GLIBCXX98_USE_C99_COMPLEX: u32 = 1;
-pub const _GLIBCXX98_USE_C99_MATH: u32 = 1;
-pub const _GLIBCXX98_USE_C99_STDIO: u32 = 1;
-pub const _GLIBCXX98_USE_C99_STDLIB: u32 = 1;
-pub const _GLIBCXX98_USE_C99_WCHAR: u32 = 1;
-pub const _GLIBCXX_ATOMIC_BUILTINS: u32 = 1;
-pub const _GLIBCXX_FULLY_DYNAMIC_STRING: u32 = 0;
-pub const _GLIBCXX_HAS_GTHREADS: u32 = 1;
-pub const _GLIBCXX_HOSTED: u32 = 1;
-pub const _GLIBCXX_RES_LIMITS: u32 = 1;
-pub const _GLIBCXX_STDIO_EOF: i32 = -1;
-pub const _GLIBCXX_STDIO_SEEK_CUR: u32 = 1;
-pub const _GLIBCXX_STDIO_SEEK_END: u32 = 2;
-pub const _GLIBCXX_SYMVER: u32 = 1;
-pub const _GLIBCXX_SYMVER_GNU: u32 = 1;
-pub const _GLIBCXX_USE_C11_UCHAR_CXX11: u32 = 1;
-pub const _GLIBCXX_USE_C99: u32 = 1;
-pub const _GLIBCXX_USE_C99_COMPLEX_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_CTYPE_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_FENV_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_INTTYPES_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_INTTYPES_WCHAR_T_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_MATH_TR1: u32 = 1;
-pub const _GLIBCXX_USE_C99_STDINT_TR1: u32 = 1;
-pub const _GLIBCXX_USE_CLOCK_MONOTONIC: u32 = 1;
-pub const _GLIBCXX_USE_CLOCK_REALTIME: u32 = 1;
-pub const _GLIBCXX_USE_DECIMAL_FLOAT: u32 = 1;
-pub const _GLIBCXX_USE_DEV_RANDOM: u32 = 1;
-pub const _GLIBCXX_USE_FCHMOD: u32 = 1;
-pub const _GLIBCXX_USE_FCHMODAT: u32 = 1;
-pub const _GLIBCXX_USE_GETTIMEOFDAY: u32 = 1;
-pub const _GLIBCXX_USE_GET_NPROCS: u32 = 1;
-pub const _GLIBCXX_USE_INT128: u32 = 1;
-pub const _GLIBCXX_USE_LFS: u32 = 1;
-pub const _GLIBCXX_USE_LONG_LONG: u32 = 1;
-pub const _GLIBCXX_USE_LSTAT: u32 = 1;
-pub const _GLIBCXX_USE_NANOSLEEP: u32 = 1;
-pub const _GLIBCXX_USE_NLS: u32 = 1;
-pub const _GLIBCXX_USE_PTHREAD_COND_CLOCKWAIT: u32 = 1;
-pub const _GLIBCXX_USE_PTHREAD_MUTEX_CLOCKLOCK: u32 = 1;
-pub const _GLIBCXX_USE_PTHREAD_RWLOCK_CLOCKLOCK: u32 = 1;
-pub const _GLIBCXX_USE_PTHREAD_RWLOCK_T: u32 = 1;
-pub const _GLIBCXX_USE_RANDOM_TR1: u32 = 1;
-pub const _GLIBCXX_USE_REALPATH: u32 = 1;
-pub const _GLIBCXX_USE_SCHED_YIELD: u32 = 1;
-pub const _GLIBCXX_USE_SC_NPROCESSORS_ONLN: u32 = 1;
-pub const _GLIBCXX_USE_SENDFILE: u32 = 1;
-pub const _GLIBCXX_USE_ST_MTIM: u32 = 1;
-pub const _GLIBCXX_USE_TMPNAM: u32 = 1;
-pub const _GLIBCXX_USE_UTIME: u32 = 1;
-pub const _GLIBCXX_USE_UTIMENSAT: u32 = 1;
-pub const _GLIBCXX_USE_WCHAR_T: u32 = 1;
-pub const _GLIBCXX_VERBOSE: u32 = 1;
-pub const _GLIBCXX_X86_RDRAND: u32 = 1;
-pub const _GLIBCXX_X86_RDSEED: u32 = 1;
-pub const _GTHREAD_USE_MUTEX_TIMEDLOCK: u32 = 1;
-pub const _ASSERT_H: u32 = 1;
-pub const _STRING_H: u32 = 1;
-pub const __GLIBC_USE_LIB_EXT2: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_BFP_EXT: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_BFP_EXT_C2X: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_EXT: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_FUNCS_EXT: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_FUNCS_EXT_C2X: u32 = 1;
-pub const __GLIBC_USE_IEC_60559_TYPES_EXT: u32 = 1;
-pub const _BITS_TYPES_LOCALE_T_H: u32 = 1;
-pub const _BITS_TYPES___LOCALE_T_H: u32 = 1;
-pub const _STRINGS_H: u32 = 1;
-pub const _GLIBCXX_CSTRING: u32 = 1;
-pub const _GLIBCXX_CSTDINT: u32 = 1;
-pub const _STDINT_H: u32 = 1;
-pub const _BITS_TYPES_H: u32 = 1;
-pub const _BITS_TYPESIZES_H: u32 = 1;
-pub const __OFF_T_MATCHES_OFF64_T: u32 = 1;
-pub const __INO_T_MATCHES_INO64_T: u32 = 1;
-pub const __RLIM_T_MATCHES_RLIM64_T: u32 = 1;
-pub const __STATFS_MATCHES_STATFS64: u32 = 1;
-pub const __KERNEL_OLD_TIMEVAL_MATCHES_TIMEVAL64: u32 = 1;
-pub const __FD_SETSIZE: u32 = 1024;
-pub const _BITS_TIME64_H: u32 = 1;
-pub const _BITS_WCHAR_H: u32 = 1;
-pub const _BITS_STDINT_INTN_H: u32 = 1;
-pub const _BITS_STDINT_UINTN_H: u32 = 1;
-pub const INT8_MIN: i32 = -128;
-pub const INT16_MIN: i32 = -32768;
-pub const INT32_MIN: i32 = -2147483648;
-pub const INT8_MAX: u32 = 127;
-pub const INT16_MAX: u32 = 32767;
-pub const INT32_MAX: u32 = 2147483647;
-pub const UINT8_MAX: u32 = 255;
-pub const UINT16_MAX: u32 = 65535;
-pub const UINT32_MAX: u32 = 4294967295;
-pub const INT_LEAST8_MIN: i32 = -128;
-pub const INT_LEAST16_MIN: i32 = -32768;
-pub const INT_LEAST32_MIN: i32 = -2147483648;
-pub const INT_LEAST8_MAX: u32 = 127;
-pub const INT_LEAST16_MAX: u32 = 32767;
-pub const INT_LEAST32_MAX: u32 = 2147483647;
-pub const UINT_LEAST8_MAX: u32 = 255;
-pub const UINT_LEAST16_MAX: u32 = 65535;
-pub const UINT_LEAST32_MAX: u32 = 4294967295;
-pub const INT_FAST8_MIN: i32 = -128;
-pub const INT_FAST16_MIN: i64 = -9223372036854775808;
-pub const INT_FAST32_MIN: i64 = -9223372036854775808;
-pub const INT_FAST8_MAX: u32 = 127;
-pub const INT_FAST16_MAX: u64 = 9223372036854775807;
-pub const INT_FAST32_MAX: u64 = 9223372036854775807;
-pub const UINT_FAST8_MAX: u32 = 255;
-pub const UINT_FAST16_MAX: i32 = -1;
-pub const UINT_FAST32_MAX: i32 = -1;
-pub const INTPTR_MIN: i64 = -9223372036854775808;
-pub const INTPTR_MAX: u64 = 9223372036854775807;
-pub const UINTPTR_MAX: i32 = -1;
-pub const PTRDIFF_MIN: i64 = -9223372036854775808;
-pub const PTRDIFF_MAX: u64 = 9223372036854775807;
-pub const SIG_ATOMIC_MIN: i32 = -2147483648;
-pub const SIG_ATOMIC_MAX: u32 = 2147483647;
-pub const SIZE_MAX: i32 = -1;
-pub const WINT_MIN: u32 = 0;
-pub const WINT_MAX: u32 = 4294967295;
-pub const INT8_WIDTH: u32 = 8;
-pub const UINT8_WIDTH: u32 = 8;
-pub const INT16_WIDTH: u32 = 16;
-pub const UINT16_WIDTH: u32 = 16;
-pub const INT32_WIDTH: u32 = 32;
-pub const UINT32_WIDTH: u32 = 32;
-pub const INT64_WIDTH: u32 = 64;
-pub const UINT64_WIDTH: u32 = 64;
-pub const INT_LEAST8_WIDTH: u32 = 8;
-pub const UINT_LEAST8_WIDTH: u32 = 8;
-pub const INT_LEAST16_WIDTH: u32 = 16;
-pub const UINT_LEAST16_WIDTH: u32 = 16;
-pub const INT_LEAST32_WIDTH: u32 = 32;
-pub const UINT_LEAST32_WIDTH: u32 = 32;
-pub const INT_LEAST64_WIDTH: u32 = 64;
-pub const UINT_LEAST64_WIDTH: u32 = 64;
-pub const INT_FAST8_WIDTH: u32 = 8;
-pub const UINT_FAST8_WIDTH: u32 = 8;
-pub const INT_FAST16_WIDTH: u32 = 64;
-pub const UINT_FAST16_WIDTH: u32 = 64;
-pub const INT_FAST32_WIDTH: u32 = 64;
-pub const UINT_FAST32_WIDTH: u32 = 64;
-pub const INT_FAST64_WIDTH: u32 = 64;
-pub const UINT_FAST64_WIDTH: u32 = 64;
-pub const INTPTR_WIDTH: u32 = 64;
-pub const UINTPTR_WIDTH: u32 = 64;
-pub const INTMAX_WIDTH: u32 = 64;
-pub const UINTMAX_WIDTH: u32 = 64;
-pub const PTRDIFF_WIDTH: u32 = 64;
-pub const SIG_ATOMIC_WIDTH: u32 = 32;
-pub const SIZE_WIDTH: u32 = 64;
-pub const WCHAR_WIDTH: u32 = 32;
-pub const WINT_WIDTH: u32 = 32;
-pub type std_nullptr_t = *const ::std::os::raw::c_void;
-extern "C" {
-    pub fn __assert_fail(
-        __assertion: *const ::std::os::raw::c_char,
-        __file: *const ::std::os::raw::c_char,
-        __line: ::std::os::raw::c_uint,
-        __function: *const ::std::os::raw::c_char,
-    ) -> !;
-}
-extern "C" {
-    pub fn __assert_perror_fail(
-        __errnum: ::std::os::raw::c_int,
-        __file: *const ::std::os::raw::c_char,
-        __line: ::std::os::raw::c_uint,
-        __function: *const ::std::os::raw::c_char,
-    ) -> !;
-}
-extern "C" {
-    pub fn __assert(
-        __assertion: *const ::std::os::raw::c_char,
-        __file: *const ::std::os::raw::c_char,
-        __line: ::std::os::raw::c_int,
-    ) -> !;
-}
-extern "C" {
-    pub fn memcpy(
-        __dest: *mut ::std::os::raw::c_void,
-        __src: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn memmove(
-        __dest: *mut ::std::os::raw::c_void,
-        __src: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn memccpy(
-        __dest: *mut ::std::os::raw::c_void,
-        __src: *const ::std::os::raw::c_void,
-        __c: ::std::os::raw::c_int,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn memset(
-        __s: *mut ::std::os::raw::c_void,
-        __c: ::std::os::raw::c_int,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn memcmp(
-        __s1: *const ::std::os::raw::c_void,
-        __s2: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn __memcmpeq(
-        __s1: *const ::std::os::raw::c_void,
-        __s2: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn memchr(
-        __s: *mut ::std::os::raw::c_void,
-        __c: ::std::os::raw::c_int,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn rawmemchr(
-        __s: *mut ::std::os::raw::c_void,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn memrchr(
-        __s: *mut ::std::os::raw::c_void,
-        __c: ::std::os::raw::c_int,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn strcpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strncpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strcat(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strncat(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strcmp(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strncmp(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strcoll(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strxfrm(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> usize;
-}
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct __locale_struct {
-    pub __locales: [*mut __locale_data; 13usize],
-    pub __ctype_b: *const ::std::os::raw::c_ushort,
-    pub __ctype_tolower: *const ::std::os::raw::c_int,
-    pub __ctype_toupper: *const ::std::os::raw::c_int,
-    pub __names: [*const ::std::os::raw::c_char; 13usize],
-}
-#[test]
-fn bindgen_test_layout___locale_struct() {
-    const UNINIT: ::std::mem::MaybeUninit<__locale_struct> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<__locale_struct>(),
-        232usize,
-        concat!("Size of: ", stringify!(__locale_struct))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<__locale_struct>(),
-        8usize,
-        concat!("Alignment of ", stringify!(__locale_struct))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__locales) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__locale_struct),
-            "::",
-            stringify!(__locales)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__ctype_b) as usize - ptr as usize },
-        104usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__locale_struct),
-            "::",
-            stringify!(__ctype_b)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__ctype_tolower) as usize - ptr as usize },
-        112usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__locale_struct),
-            "::",
-            stringify!(__ctype_tolower)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__ctype_toupper) as usize - ptr as usize },
-        120usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__locale_struct),
-            "::",
-            stringify!(__ctype_toupper)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__names) as usize - ptr as usize },
-        128usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__locale_struct),
-            "::",
-            stringify!(__names)
-        )
-    );
-}
-pub type __locale_t = *mut __locale_struct;
-pub type locale_t = __locale_t;
-extern "C" {
-    pub fn strcoll_l(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-        __l: locale_t,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strxfrm_l(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-        __l: locale_t,
-    ) -> usize;
-}
-extern "C" {
-    pub fn strdup(__s: *const ::std::os::raw::c_char) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strndup(
-        __string: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strchr(
-        __s: *mut ::std::os::raw::c_char,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strrchr(
-        __s: *mut ::std::os::raw::c_char,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strchrnul(
-        __s: *mut ::std::os::raw::c_char,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strcspn(
-        __s: *const ::std::os::raw::c_char,
-        __reject: *const ::std::os::raw::c_char,
-    ) -> usize;
-}
-extern "C" {
-    pub fn strspn(
-        __s: *const ::std::os::raw::c_char,
-        __accept: *const ::std::os::raw::c_char,
-    ) -> usize;
-}
-extern "C" {
-    pub fn strpbrk(
-        __s: *mut ::std::os::raw::c_char,
-        __accept: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strstr(
-        __haystack: *mut ::std::os::raw::c_char,
-        __needle: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strtok(
-        __s: *mut ::std::os::raw::c_char,
-        __delim: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn __strtok_r(
-        __s: *mut ::std::os::raw::c_char,
-        __delim: *const ::std::os::raw::c_char,
-        __save_ptr: *mut *mut ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strtok_r(
-        __s: *mut ::std::os::raw::c_char,
-        __delim: *const ::std::os::raw::c_char,
-        __save_ptr: *mut *mut ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strcasestr(
-        __haystack: *mut ::std::os::raw::c_char,
-        __needle: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn memmem(
-        __haystack: *const ::std::os::raw::c_void,
-        __haystacklen: usize,
-        __needle: *const ::std::os::raw::c_void,
-        __needlelen: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn __mempcpy(
-        __dest: *mut ::std::os::raw::c_void,
-        __src: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn mempcpy(
-        __dest: *mut ::std::os::raw::c_void,
-        __src: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn strlen(__s: *const ::std::os::raw::c_char) -> usize;
-}
-extern "C" {
-    pub fn strnlen(__string: *const ::std::os::raw::c_char, __maxlen: usize) -> usize;
-}
-extern "C" {
-    pub fn strerror(__errnum: ::std::os::raw::c_int) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strerror_r(
-        __errnum: ::std::os::raw::c_int,
-        __buf: *mut ::std::os::raw::c_char,
-        __buflen: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strerrordesc_np(__err: ::std::os::raw::c_int) -> *const ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strerrorname_np(__err: ::std::os::raw::c_int) -> *const ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strerror_l(
-        __errnum: ::std::os::raw::c_int,
-        __l: locale_t,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn bcmp(
-        __s1: *const ::std::os::raw::c_void,
-        __s2: *const ::std::os::raw::c_void,
-        __n: usize,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn bcopy(
-        __src: *const ::std::os::raw::c_void,
-        __dest: *mut ::std::os::raw::c_void,
-        __n: usize,
-    );
-}
-extern "C" {
-    pub fn bzero(__s: *mut ::std::os::raw::c_void, __n: usize);
-}
-extern "C" {
-    pub fn index(
-        __s: *const ::std::os::raw::c_char,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn rindex(
-        __s: *const ::std::os::raw::c_char,
-        __c: ::std::os::raw::c_int,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn ffs(__i: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn ffsl(__l: ::std::os::raw::c_long) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn ffsll(__ll: ::std::os::raw::c_longlong) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strcasecmp(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strncasecmp(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strcasecmp_l(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-        __loc: locale_t,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strncasecmp_l(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-        __n: usize,
-        __loc: locale_t,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn explicit_bzero(__s: *mut ::std::os::raw::c_void, __n: usize);
-}
-extern "C" {
-    pub fn strsep(
-        __stringp: *mut *mut ::std::os::raw::c_char,
-        __delim: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strsignal(__sig: ::std::os::raw::c_int) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn sigabbrev_np(__sig: ::std::os::raw::c_int) -> *const ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn sigdescr_np(__sig: ::std::os::raw::c_int) -> *const ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn __stpcpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn stpcpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn __stpncpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn stpncpy(
-        __dest: *mut ::std::os::raw::c_char,
-        __src: *const ::std::os::raw::c_char,
-        __n: usize,
-    ) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn strverscmp(
-        __s1: *const ::std::os::raw::c_char,
-        __s2: *const ::std::os::raw::c_char,
-    ) -> ::std::os::raw::c_int;
-}
-extern "C" {
-    pub fn strfry(__string: *mut ::std::os::raw::c_char) -> *mut ::std::os::raw::c_char;
-}
-extern "C" {
-    pub fn memfrob(__s: *mut ::std::os::raw::c_void, __n: usize) -> *mut ::std::os::raw::c_void;
-}
-extern "C" {
-    pub fn basename(__filename: *mut ::std::os::raw::c_char) -> *mut ::std::os::raw::c_char;
-}
-pub type __u_char = ::std::os::raw::c_uchar;
-pub type __u_short = ::std::os::raw::c_ushort;
-pub type __u_int = ::std::os::raw::c_uint;
-pub type __u_long = ::std::os::raw::c_ulong;
-pub type __int8_t = ::std::os::raw::c_schar;
-pub type __uint8_t = ::std::os::raw::c_uchar;
-pub type __int16_t = ::std::os::raw::c_short;
-pub type __uint16_t = ::std::os::raw::c_ushort;
-pub type __int32_t = ::std::os::raw::c_int;
-pub type __uint32_t = ::std::os::raw::c_uint;
-pub type __int64_t = ::std::os::raw::c_long;
-pub type __uint64_t = ::std::os::raw::c_ulong;
-pub type __int_least8_t = __int8_t;
-pub type __uint_least8_t = __uint8_t;
-pub type __int_least16_t = __int16_t;
-pub type __uint_least16_t = __uint16_t;
-pub type __int_least32_t = __int32_t;
-pub type __uint_least32_t = __uint32_t;
-pub type __int_least64_t = __int64_t;
-pub type __uint_least64_t = __uint64_t;
-pub type __quad_t = ::std::os::raw::c_long;
-pub type __u_quad_t = ::std::os::raw::c_ulong;
-pub type __intmax_t = ::std::os::raw::c_long;
-pub type __uintmax_t = ::std::os::raw::c_ulong;
-pub type __dev_t = ::std::os::raw::c_ulong;
-pub type __uid_t = ::std::os::raw::c_uint;
-pub type __gid_t = ::std::os::raw::c_uint;
-pub type __ino_t = ::std::os::raw::c_ulong;
-pub type __ino64_t = ::std::os::raw::c_ulong;
-pub type __mode_t = ::std::os::raw::c_uint;
-pub type __nlink_t = ::std::os::raw::c_ulong;
-pub type __off_t = ::std::os::raw::c_long;
-pub type __off64_t = ::std::os::raw::c_long;
-pub type __pid_t = ::std::os::raw::c_int;
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct __fsid_t {
-    pub __val: [::std::os::raw::c_int; 2usize],
-}
-#[test]
-fn bindgen_test_layout___fsid_t() {
-    const UNINIT: ::std::mem::MaybeUninit<__fsid_t> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<__fsid_t>(),
-        8usize,
-        concat!("Size of: ", stringify!(__fsid_t))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<__fsid_t>(),
-        4usize,
-        concat!("Alignment of ", stringify!(__fsid_t))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).__val) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(__fsid_t),
-            "::",
-            stringify!(__val)
-        )
-    );
-}
-pub type __clock_t = ::std::os::raw::c_long;
-pub type __rlim_t = ::std::os::raw::c_ulong;
-pub type __rlim64_t = ::std::os::raw::c_ulong;
-pub type __id_t = ::std::os::raw::c_uint;
-pub type __time_t = ::std::os::raw::c_long;
-pub type __useconds_t = ::std::os::raw::c_uint;
-pub type __suseconds_t = ::std::os::raw::c_long;
-pub type __suseconds64_t = ::std::os::raw::c_long;
-pub type __daddr_t = ::std::os::raw::c_int;
-pub type __key_t = ::std::os::raw::c_int;
-pub type __clockid_t = ::std::os::raw::c_int;
-pub type __timer_t = *mut ::std::os::raw::c_void;
-pub type __blksize_t = ::std::os::raw::c_long;
-pub type __blkcnt_t = ::std::os::raw::c_long;
-pub type __blkcnt64_t = ::std::os::raw::c_long;
-pub type __fsblkcnt_t = ::std::os::raw::c_ulong;
-pub type __fsblkcnt64_t = ::std::os::raw::c_ulong;
-pub type __fsfilcnt_t = ::std::os::raw::c_ulong;
-pub type __fsfilcnt64_t = ::std::os::raw::c_ulong;
-pub type __fsword_t = ::std::os::raw::c_long;
-pub type __ssize_t = ::std::os::raw::c_long;
-pub type __syscall_slong_t = ::std::os::raw::c_long;
-pub type __syscall_ulong_t = ::std::os::raw::c_ulong;
-pub type __loff_t = __off64_t;
-pub type __caddr_t = *mut ::std::os::raw::c_char;
-pub type __intptr_t = ::std::os::raw::c_long;
-pub type __socklen_t = ::std::os::raw::c_uint;
-pub type __sig_atomic_t = ::std::os::raw::c_int;
-pub type int_least8_t = __int_least8_t;
-pub type int_least16_t = __int_least16_t;
-pub type int_least32_t = __int_least32_t;
-pub type int_least64_t = __int_least64_t;
-pub type uint_least8_t = __uint_least8_t;
-pub type uint_least16_t = __uint_least16_t;
-pub type uint_least32_t = __uint_least32_t;
-pub type uint_least64_t = __uint_least64_t;
-pub type int_fast8_t = ::std::os::raw::c_schar;
-pub type int_fast16_t = ::std::os::raw::c_long;
-pub type int_fast32_t = ::std::os::raw::c_long;
-pub type int_fast64_t = ::std::os::raw::c_long;
-pub type uint_fast8_t = ::std::os::raw::c_uchar;
-pub type uint_fast16_t = ::std::os::raw::c_ulong;
-pub type uint_fast32_t = ::std::os::raw::c_ulong;
-pub type uint_fast64_t = ::std::os::raw::c_ulong;
-pub type intmax_t = __intmax_t;
-pub type uintmax_t = __uintmax_t;
-extern "C" {
-    #[link_name = "\u{1}_ZL27WLAN_IEEE_HEADER_AIR2GROUND"]
-    pub static WLAN_IEEE_HEADER_AIR2GROUND: [u8; 24usize];
-}
-extern "C" {
-    #[link_name = "\u{1}_ZL27WLAN_IEEE_HEADER_GROUND2AIR"]
-    pub static WLAN_IEEE_HEADER_GROUND2AIR: [u8; 24usize];
-}
-pub const WLAN_IEEE_HEADER_SIZE: usize = 24;
+    It used to be generated by bindgen, now only the protocol types are kept and maintained by hand,
+    with the names bindgen gives them. When the air side edits the headers, update this file:
+    the layout is asserted at compile time, the same as the static_asserts in the headers,
+    and the sizes and offsets computed from packets.h by build.rs are asserted too, so the build fails on drift.
+
+    The structs are packed as the headers(#pragma pack(push, 1)),
+    encode/decode them by crate::codec::Codec instead of casting pointers.
+*/
+use std::mem::{offset_of, size_of};
+
+// structures.h
+pub const WLAN_IEEE_HEADER_AIR2GROUND: [u8; 24] = [
+    0x08, 0x01, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+    0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x10, 0x86,
+];
+pub const WLAN_IEEE_HEADER_GROUND2AIR: [u8; 24] = [
+    0x08, 0x01, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11,
+    0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x10, 0x86,
+];
+pub const WLAN_IEEE_HEADER_SIZE: usize = WLAN_IEEE_HEADER_AIR2GROUND.len();
 pub const WLAN_MAX_PACKET_SIZE: usize = 1500;
-pub const WLAN_MAX_PAYLOAD_SIZE: usize = 1476;
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct Wlan_Outgoing_Packet {
-    pub ptr: *mut u8,
-    pub payload_ptr: *mut u8,
-    pub size: u16,
-    pub offset: u16,
-}
-#[test]
-fn bindgen_test_layout_Wlan_Outgoing_Packet() {
-    const UNINIT: ::std::mem::MaybeUninit<Wlan_Outgoing_Packet> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Wlan_Outgoing_Packet>(),
-        24usize,
-        concat!("Size of: ", stringify!(Wlan_Outgoing_Packet))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Wlan_Outgoing_Packet>(),
-        8usize,
-        concat!("Alignment of ", stringify!(Wlan_Outgoing_Packet))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).ptr) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Outgoing_Packet),
-            "::",
-            stringify!(ptr)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).payload_ptr) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Outgoing_Packet),
-            "::",
-            stringify!(payload_ptr)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).size) as usize - ptr as usize },
-        16usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Outgoing_Packet),
-            "::",
-            stringify!(size)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).offset) as usize - ptr as usize },
-        18usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Outgoing_Packet),
-            "::",
-            stringify!(offset)
-        )
-    );
-}
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct Wlan_Incoming_Packet {
-    pub ptr: *mut u8,
-    pub size: u16,
-    pub offset: u16,
-}
-#[test]
-fn bindgen_test_layout_Wlan_Incoming_Packet() {
-    const UNINIT: ::std::mem::MaybeUninit<Wlan_Incoming_Packet> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Wlan_Incoming_Packet>(),
-        16usize,
-        concat!("Size of: ", stringify!(Wlan_Incoming_Packet))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Wlan_Incoming_Packet>(),
-        8usize,
-        concat!("Alignment of ", stringify!(Wlan_Incoming_Packet))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).ptr) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Incoming_Packet),
-            "::",
-            stringify!(ptr)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).size) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Incoming_Packet),
-            "::",
-            stringify!(size)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).offset) as usize - ptr as usize },
-        10usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Wlan_Incoming_Packet),
-            "::",
-            stringify!(offset)
-        )
-    );
-}
+pub const WLAN_MAX_PAYLOAD_SIZE: usize = WLAN_MAX_PACKET_SIZE - WLAN_IEEE_HEADER_SIZE;
+
+// packets.h
+pub type WIFI_Rate = u8;
 pub const WIFI_Rate_RATE_B_2M_CCK: WIFI_Rate = 0;
 pub const WIFI_Rate_RATE_B_2M_CCK_S: WIFI_Rate = 1;
 pub const WIFI_Rate_RATE_B_5_5M_CCK: WIFI_Rate = 2;
@@ -1225,9 +57,15 @@ pub const WIFI_Rate_RATE_N_58M_MCS6: WIFI_Rate = 26;
 pub const WIFI_Rate_RATE_N_65M_MCS6_S: WIFI_Rate = 27;
 pub const WIFI_Rate_RATE_N_65M_MCS7: WIFI_Rate = 28;
 pub const WIFI_Rate_RATE_N_72M_MCS7_S: WIFI_Rate = 29;
-pub type WIFI_Rate = u8;
-pub const AIR2GROUND_MTU: usize = 1470;
+
+pub const AIR2GROUND_MTU: usize = WLAN_MAX_PAYLOAD_SIZE - 6; // 6 is the fec header size
+
 pub const GROUND2AIR_DATA_MAX_SIZE: usize = 64;
+
+pub type Ground2Air_Header_Type = u8;
+pub const Ground2Air_Header_Type_Data: Ground2Air_Header_Type = 0;
+pub const Ground2Air_Header_Type_Config: Ground2Air_Header_Type = 1;
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Ground2Air_Header {
@@ -1235,72 +73,14 @@ pub struct Ground2Air_Header {
     pub size: u32,
     pub crc: u8,
 }
-pub const Ground2Air_Header_Type_Data: Ground2Air_Header_Type = 0;
-pub const Ground2Air_Header_Type_Config: Ground2Air_Header_Type = 1;
-pub type Ground2Air_Header_Type = u8;
-#[test]
-fn bindgen_test_layout_Ground2Air_Header() {
-    const UNINIT: ::std::mem::MaybeUninit<Ground2Air_Header> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Ground2Air_Header>(),
-        6usize,
-        concat!("Size of: ", stringify!(Ground2Air_Header))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Ground2Air_Header>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Ground2Air_Header))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).type_) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Header),
-            "::",
-            stringify!(type_)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).size) as usize - ptr as usize },
-        1usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Header),
-            "::",
-            stringify!(size)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).crc) as usize - ptr as usize },
-        5usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Header),
-            "::",
-            stringify!(crc)
-        )
-    );
-}
-#[repr(C)]
+
+#[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Ground2Air_Data_Packet {
     pub _base: Ground2Air_Header,
 }
-#[test]
-fn bindgen_test_layout_Ground2Air_Data_Packet() {
-    assert_eq!(
-        ::std::mem::size_of::<Ground2Air_Data_Packet>(),
-        6usize,
-        concat!("Size of: ", stringify!(Ground2Air_Data_Packet))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Ground2Air_Data_Packet>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Ground2Air_Data_Packet))
-    );
-}
+
+pub type Resolution = u8;
 pub const Resolution_QVGA: Resolution = 0;
 pub const Resolution_CIF: Resolution = 1;
 pub const Resolution_HVGA: Resolution = 2;
@@ -1309,7 +89,7 @@ pub const Resolution_SVGA: Resolution = 4;
 pub const Resolution_XGA: Resolution = 5;
 pub const Resolution_SXGA: Resolution = 6;
 pub const Resolution_UXGA: Resolution = 7;
-pub type Resolution = u8;
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Ground2Air_Config_Packet {
@@ -1323,6 +103,7 @@ pub struct Ground2Air_Config_Packet {
     pub dvr_record: bool,
     pub camera: Ground2Air_Config_Packet_Camera,
 }
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Ground2Air_Config_Packet_Camera {
@@ -1353,378 +134,11 @@ pub struct Ground2Air_Config_Packet_Camera {
     pub vflip: bool,
     pub dcw: bool,
 }
-#[test]
-fn bindgen_test_layout_Ground2Air_Config_Packet_Camera() {
-    const UNINIT: ::std::mem::MaybeUninit<Ground2Air_Config_Packet_Camera> =
-        ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Ground2Air_Config_Packet_Camera>(),
-        27usize,
-        concat!("Size of: ", stringify!(Ground2Air_Config_Packet_Camera))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Ground2Air_Config_Packet_Camera>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Ground2Air_Config_Packet_Camera))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).resolution) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(resolution)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).fps_limit) as usize - ptr as usize },
-        1usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(fps_limit)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).quality) as usize - ptr as usize },
-        2usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(quality)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).brightness) as usize - ptr as usize },
-        3usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(brightness)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).contrast) as usize - ptr as usize },
-        4usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(contrast)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).saturation) as usize - ptr as usize },
-        5usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(saturation)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).sharpness) as usize - ptr as usize },
-        6usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(sharpness)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).denoise) as usize - ptr as usize },
-        7usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(denoise)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).special_effect) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(special_effect)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).awb) as usize - ptr as usize },
-        9usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(awb)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).awb_gain) as usize - ptr as usize },
-        10usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(awb_gain)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).wb_mode) as usize - ptr as usize },
-        11usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(wb_mode)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).aec) as usize - ptr as usize },
-        12usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(aec)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).aec2) as usize - ptr as usize },
-        13usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(aec2)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).ae_level) as usize - ptr as usize },
-        14usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(ae_level)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).aec_value) as usize - ptr as usize },
-        15usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(aec_value)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).agc) as usize - ptr as usize },
-        17usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(agc)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).agc_gain) as usize - ptr as usize },
-        18usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(agc_gain)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).gainceiling) as usize - ptr as usize },
-        19usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(gainceiling)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).bpc) as usize - ptr as usize },
-        20usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(bpc)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).wpc) as usize - ptr as usize },
-        21usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(wpc)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).raw_gma) as usize - ptr as usize },
-        22usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(raw_gma)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).lenc) as usize - ptr as usize },
-        23usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(lenc)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).hmirror) as usize - ptr as usize },
-        24usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(hmirror)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).vflip) as usize - ptr as usize },
-        25usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(vflip)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).dcw) as usize - ptr as usize },
-        26usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet_Camera),
-            "::",
-            stringify!(dcw)
-        )
-    );
-}
-#[test]
-fn bindgen_test_layout_Ground2Air_Config_Packet() {
-    const UNINIT: ::std::mem::MaybeUninit<Ground2Air_Config_Packet> =
-        ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Ground2Air_Config_Packet>(),
-        41usize,
-        concat!("Size of: ", stringify!(Ground2Air_Config_Packet))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Ground2Air_Config_Packet>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Ground2Air_Config_Packet))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).ping) as usize - ptr as usize },
-        6usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(ping)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).wifi_power) as usize - ptr as usize },
-        7usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(wifi_power)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).wifi_rate) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(wifi_rate)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).fec_codec_k) as usize - ptr as usize },
-        9usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(fec_codec_k)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).fec_codec_n) as usize - ptr as usize },
-        10usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(fec_codec_n)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).fec_codec_mtu) as usize - ptr as usize },
-        11usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(fec_codec_mtu)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).dvr_record) as usize - ptr as usize },
-        13usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(dvr_record)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).camera) as usize - ptr as usize },
-        14usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Ground2Air_Config_Packet),
-            "::",
-            stringify!(camera)
-        )
-    );
-}
+
+pub type Air2Ground_Header_Type = u8;
+pub const Air2Ground_Header_Type_Video: Air2Ground_Header_Type = 0;
+pub const Air2Ground_Header_Type_Telemetry: Air2Ground_Header_Type = 1;
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Air2Ground_Header {
@@ -1733,148 +147,72 @@ pub struct Air2Ground_Header {
     pub pong: u8,
     pub crc: u8,
 }
-pub const Air2Ground_Header_Type_Video: Air2Ground_Header_Type = 0;
-pub const Air2Ground_Header_Type_Telemetry: Air2Ground_Header_Type = 1;
-pub type Air2Ground_Header_Type = u8;
-#[test]
-fn bindgen_test_layout_Air2Ground_Header() {
-    const UNINIT: ::std::mem::MaybeUninit<Air2Ground_Header> = ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Air2Ground_Header>(),
-        7usize,
-        concat!("Size of: ", stringify!(Air2Ground_Header))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Air2Ground_Header>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Air2Ground_Header))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).type_) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Header),
-            "::",
-            stringify!(type_)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).size) as usize - ptr as usize },
-        1usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Header),
-            "::",
-            stringify!(size)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).pong) as usize - ptr as usize },
-        5usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Header),
-            "::",
-            stringify!(pong)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).crc) as usize - ptr as usize },
-        6usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Header),
-            "::",
-            stringify!(crc)
-        )
-    );
-}
+
 #[repr(C, packed)]
 #[derive(Debug, Copy, Clone)]
 pub struct Air2Ground_Video_Packet {
     pub _base: Air2Ground_Header,
     pub resolution: Resolution,
-    pub _bitfield_align_1: [u8; 0],
-    pub _bitfield_1: __BindgenBitfieldUnit<[u8; 1usize]>,
+    pub _bitfield_1: u8, // part_index: 7, last_part: 1, gcc allocates the bitfields from the lowest bit
     pub frame_index: u32,
 }
-#[test]
-fn bindgen_test_layout_Air2Ground_Video_Packet() {
-    const UNINIT: ::std::mem::MaybeUninit<Air2Ground_Video_Packet> =
-        ::std::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::std::mem::size_of::<Air2Ground_Video_Packet>(),
-        13usize,
-        concat!("Size of: ", stringify!(Air2Ground_Video_Packet))
-    );
-    assert_eq!(
-        ::std::mem::align_of::<Air2Ground_Video_Packet>(),
-        1usize,
-        concat!("Alignment of ", stringify!(Air2Ground_Video_Packet))
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).resolution) as usize - ptr as usize },
-        7usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Video_Packet),
-            "::",
-            stringify!(resolution)
-        )
-    );
-    assert_eq!(
-        unsafe { ::std::ptr::addr_of!((*ptr).frame_index) as usize - ptr as usize },
-        9usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(Air2Ground_Video_Packet),
-            "::",
-            stringify!(frame_index)
-        )
-    );
-}
+
 impl Air2Ground_Video_Packet {
     #[inline]
     pub fn part_index(&self) -> u8 {
-        unsafe { ::std::mem::transmute(self._bitfield_1.get(0usize, 7u8) as u8) }
+        self._bitfield_1 & 0x7F
     }
     #[inline]
     pub fn set_part_index(&mut self, val: u8) {
-        unsafe {
-            let val: u8 = ::std::mem::transmute(val);
-            self._bitfield_1.set(0usize, 7u8, val as u64)
-        }
+        self._bitfield_1 = (self._bitfield_1 & 0x80) | (val & 0x7F);
     }
     #[inline]
     pub fn last_part(&self) -> u8 {
-        unsafe { ::std::mem::transmute(self._bitfield_1.get(7usize, 1u8) as u8) }
+        self._bitfield_1 >> 7
     }
     #[inline]
     pub fn set_last_part(&mut self, val: u8) {
-        unsafe {
-            let val: u8 = ::std::mem::transmute(val);
-            self._bitfield_1.set(7usize, 1u8, val as u64)
-        }
+        self._bitfield_1 = (self._bitfield_1 & 0x7F) | ((val & 1) << 7);
     }
     #[inline]
-    pub fn new_bitfield_1(part_index: u8, last_part: u8) -> __BindgenBitfieldUnit<[u8; 1usize]> {
-        let mut __bindgen_bitfield_unit: __BindgenBitfieldUnit<[u8; 1usize]> = Default::default();
-        __bindgen_bitfield_unit.set(0usize, 7u8, {
-            let part_index: u8 = unsafe { ::std::mem::transmute(part_index) };
-            part_index as u64
-        });
-        __bindgen_bitfield_unit.set(7usize, 1u8, {
-            let last_part: u8 = unsafe { ::std::mem::transmute(last_part) };
-            last_part as u64
-        });
-        __bindgen_bitfield_unit
+    pub fn new_bitfield_1(part_index: u8, last_part: u8) -> u8 {
+        (part_index & 0x7F) | ((last_part & 1) << 7)
     }
 }
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct __locale_data {
-    pub _address: u8,
+
+// the static_asserts of the headers
+const _: () = assert!(WLAN_IEEE_HEADER_SIZE == 24);
+const _: () = assert!(size_of::<Ground2Air_Data_Packet>() <= GROUND2AIR_DATA_MAX_SIZE);
+const _: () = assert!(size_of::<Ground2Air_Config_Packet>() <= GROUND2AIR_DATA_MAX_SIZE);
+const _: () = assert!(size_of::<Air2Ground_Video_Packet>() == 13);
+
+const _: () = assert!(AIR2GROUND_MTU == 1470);
+
+// the layout the air unit sees, generated from packets.h by build.rs
+include!(concat!(env!("OUT_DIR"), "/packets_h_layout.rs"));
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use crate::packet_h_bind::*;
+
+        #[test]
+        fn test_bitfield() {
+            let mut packet = Air2Ground_Video_Packet {
+                _base: Air2Ground_Header {
+                    type_: Air2Ground_Header_Type_Video,
+                    size: 0,
+                    pong: 0,
+                    crc: 0,
+                },
+                resolution: Resolution_VGA,
+                _bitfield_1: Air2Ground_Video_Packet::new_bitfield_1(5, 1),
+                frame_index: 0,
+            };
+            assert_eq!(packet._bitfield_1, 0x85);
+            packet.set_part_index(0x7F);
+            packet.set_last_part(0);
+            assert_eq!((packet.part_index(), packet.last_part()), (0x7F, 0));
+        }
+    }
 }
@@ -0,0 +1,301 @@
+use std::{fmt::Display, str::FromStr};
+
+use crc::{Crc, CRC_8_SMBUS};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codec::{Codec, CodecError},
+    packet_h_bind::{
+        Air2Ground_Header, Air2Ground_Header_Type_Video, Air2Ground_Video_Packet,
+        Ground2Air_Config_Packet, Ground2Air_Data_Packet, Ground2Air_Header,
+    },
+};
+
+/*
+    The packet layouts of the air firmwares.
+
+    V1: bind_src/packets.h, the layout of packet_h_bind.
+
+    The newer upstream packets.h is not encoded yet, it is added here as another version
+    with its codec converting the packets to the structs of packet_h_bind, so the rest of the crate stays version independent.
+    The air unit has no way to tell its version to the ground, it's detected by which layout the crc of the video headers matches,
+    refer ProtocolDetector.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ProtocolVersion {
+    V1,
+}
+
+static SMBUS_CRC: Crc<u8> = Crc::<u8>::new(&CRC_8_SMBUS);
+
+impl ProtocolVersion {
+    pub const ALL: [ProtocolVersion; 1] = [ProtocolVersion::V1];
+
+    pub fn air2ground_header_size(self) -> usize {
+        match self {
+            ProtocolVersion::V1 => Air2Ground_Header::SIZE,
+        }
+    }
+
+    pub fn video_header_size(self) -> usize {
+        match self {
+            ProtocolVersion::V1 => Air2Ground_Video_Packet::SIZE,
+        }
+    }
+
+    pub const fn ground2air_header_size(self) -> usize {
+        match self {
+            ProtocolVersion::V1 => Ground2Air_Header::SIZE,
+        }
+    }
+
+    fn crc_index(self) -> usize {
+        self.air2ground_header_size() - 1
+    }
+
+    pub fn decode_air2ground_header(self, data: &[u8]) -> Result<Air2Ground_Header, CodecError> {
+        match self {
+            ProtocolVersion::V1 => Air2Ground_Header::decode(data),
+        }
+    }
+
+    pub fn decode_video_header(self, data: &[u8]) -> Result<Air2Ground_Video_Packet, CodecError> {
+        match self {
+            ProtocolVersion::V1 => Air2Ground_Video_Packet::decode(data),
+        }
+    }
+
+    /*
+        the crc of the video header is calculated on the header with crc 0.
+    */
+    pub fn video_crc_valid(self, data: &[u8]) -> bool {
+        let size = self.video_header_size();
+        if data.len() < size {
+            return false;
+        }
+        let mut header = data[..size].to_vec();
+        let crc = std::mem::take(&mut header[self.crc_index()]);
+        SMBUS_CRC.checksum(&header) == crc
+    }
+
+    /*
+        the crc of a ground2air packet is calculated on the whole packet with crc 0.
+    */
+    pub fn ground2air_crc_valid(self, data: &[u8]) -> bool {
+        let size = self.ground2air_header_size();
+        if data.len() < size {
+            return false;
+        }
+        let mut packet = data.to_vec();
+        let crc = std::mem::take(&mut packet[size - 1]);
+        SMBUS_CRC.checksum(&packet) == crc
+    }
+
+    pub fn encode_config_packet(self, packet: &Ground2Air_Config_Packet) -> Vec<u8> {
+        match self {
+            ProtocolVersion::V1 => packet.to_bytes(),
+        }
+    }
+
+    pub fn decode_config_packet(self, data: &[u8]) -> Result<Ground2Air_Config_Packet, CodecError> {
+        match self {
+            ProtocolVersion::V1 => Ground2Air_Config_Packet::decode(data),
+        }
+    }
+
+    pub fn encode_data_packet(self, payload: &[u8]) -> Vec<u8> {
+        match self {
+            ProtocolVersion::V1 => Ground2Air_Data_Packet::with_payload(payload),
+        }
+    }
+
+    /*
+        the payload of a data packet, the bytes after its size are the padding.
+    */
+    pub fn decode_data_packet(self, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+        let header = match self {
+            ProtocolVersion::V1 => Ground2Air_Header::decode(data)?,
+        };
+        let size = (header.size as usize).clamp(Ground2Air_Data_Packet::SIZE, data.len());
+        Ok(data[Ground2Air_Data_Packet::SIZE..size].to_vec())
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "v1" | "1" => Ok(ProtocolVersion::V1),
+            _ => Err(format!("invalid protocol version:{}, should be v1", s)),
+        }
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolVersion::V1 => write!(f, "v1"),
+        }
+    }
+}
+
+impl TryFrom<String> for ProtocolVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ProtocolVersion> for String {
+    fn from(value: ProtocolVersion) -> String {
+        value.to_string()
+    }
+}
+
+// consecutive video headers matching only one version to decide it
+const DETECT_THRESHOLD: u32 = 3;
+
+/*
+    Detect the protocol version from the air2ground packets.
+    A video header may match the crc of several versions by chance(1/256), such packets are not counted.
+*/
+#[derive(Debug, Default)]
+pub struct ProtocolDetector {
+    candidate: Option<ProtocolVersion>,
+    count: u32,
+}
+
+impl ProtocolDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+        observe an air2ground packet, return the version once decided.
+    */
+    pub fn observe(&mut self, data: &[u8]) -> Option<ProtocolVersion> {
+        if data.first() != Some(&Air2Ground_Header_Type_Video) {
+            return None;
+        }
+        let mut matched = ProtocolVersion::ALL.into_iter().filter(|x| x.video_crc_valid(data));
+        let (Some(version), None) = (matched.next(), matched.next()) else {
+            return None;
+        };
+        if self.candidate == Some(version) {
+            self.count += 1;
+        } else {
+            self.candidate = Some(version);
+            self.count = 1;
+        }
+        if self.count >= DETECT_THRESHOLD {
+            self.candidate
+        } else {
+            None
+        }
+    }
+
+    /*
+        the most likely version so far, used before it is decided.
+    */
+    pub fn candidate(&self) -> Option<ProtocolVersion> {
+        self.candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use crate::{
+            codec::Codec,
+            packet_h_bind::*,
+            protocol::{ProtocolDetector, ProtocolVersion},
+        };
+
+        fn video_header(version: ProtocolVersion, part_index: u8) -> Vec<u8> {
+            let mut header = Air2Ground_Video_Packet {
+                _base: Air2Ground_Header {
+                    type_: Air2Ground_Header_Type_Video,
+                    size: 1470,
+                    pong: 9,
+                    crc: 0,
+                },
+                resolution: Resolution_VGA,
+                _bitfield_1: Air2Ground_Video_Packet::new_bitfield_1(part_index, 0),
+                frame_index: 0x1234,
+            };
+            let mut data = header.to_bytes();
+            header._base.crc = crc::Crc::<u8>::new(&crc::CRC_8_SMBUS).checksum(&data);
+            let crc_index = version.air2ground_header_size() - 1;
+            data[crc_index] = header._base.crc;
+            data
+        }
+
+        #[test]
+        fn test_decode_v1() {
+            let data = video_header(ProtocolVersion::V1, 3);
+            assert_eq!(data.len(), 13);
+            assert!(ProtocolVersion::V1.video_crc_valid(&data));
+            let header = ProtocolVersion::V1.decode_video_header(&data).unwrap();
+            assert_eq!(header.part_index(), 3);
+            assert_eq!(header._base.pong, 9);
+            assert_eq!({ header.frame_index }, 0x1234);
+            assert_eq!(header.to_bytes(), data);
+            assert!(ProtocolVersion::V1
+                .decode_video_header(&data[..12])
+                .is_err());
+
+            let mut corrupted = data;
+            corrupted[12] ^= 1;
+            assert!(!ProtocolVersion::V1.video_crc_valid(&corrupted));
+        }
+
+        #[test]
+        fn test_ground2air_round_trip() {
+            let mut config = Ground2Air_Config_Packet::default();
+            config.ping = 7;
+            config.camera.quality = 20;
+            config.update_crc();
+            for version in ProtocolVersion::ALL {
+                let data = version.encode_config_packet(&config);
+                assert_eq!(
+                    data.len(),
+                    version.ground2air_header_size() + Ground2Air_Config_Packet::SIZE
+                        - Ground2Air_Header::SIZE
+                );
+                assert_eq!(
+                    u32::from_le_bytes(data[1..5].try_into().unwrap()) as usize,
+                    data.len()
+                );
+                assert!(version.ground2air_crc_valid(&data));
+                let decoded = version.decode_config_packet(&data).unwrap();
+                assert_eq!(decoded.to_bytes(), config.to_bytes());
+                assert_eq!(version.encode_config_packet(&decoded), data);
+
+                let mut data = version.encode_data_packet(b"mavlink");
+                assert_eq!(data.len(), version.ground2air_header_size() + 7);
+                assert!(version.ground2air_crc_valid(&data));
+                data.resize(64, 0); // padded in the block
+                assert_eq!(version.decode_data_packet(&data).unwrap(), b"mavlink");
+            }
+            assert!(ProtocolVersion::V1.decode_config_packet(&[0; 6]).is_err());
+        }
+
+        #[test]
+        fn test_detect() {
+            for version in ProtocolVersion::ALL {
+                let mut detector = ProtocolDetector::new();
+                let decided: Vec<_> = (0..8)
+                    .map(|i| detector.observe(&video_header(version, i)))
+                    .collect();
+                // a header may match several versions by chance, which is not counted
+                assert_eq!(decided[..2], [None, None]);
+                assert_eq!(decided.into_iter().flatten().next(), Some(version));
+            }
+            assert_eq!("V1".parse(), Ok(ProtocolVersion::V1));
+            assert!("v2".parse::<ProtocolVersion>().is_err());
+        }
+    }
+}
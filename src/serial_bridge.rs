@@ -0,0 +1,395 @@
+use std::{
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    mem::MaybeUninit,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, OpenOptionsExt},
+        io::{AsRawFd, FromRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    inject::GROUND2AIR_DATA_PAYLOAD_MAX_SIZE,
+    uplink::{UplinkPriority, UplinkQueue},
+};
+
+/*
+    Carry a serial link(mavlink, msp...) over the vtx link:
+    the bytes written to the serial port are sent upstream in ground2air data packets,
+    the payloads of air2ground telemetry packets are written back to it.
+
+    The serial port is either a real serial device, e.g. a usb-uart connected to a radio,
+    or a pseudo-terminal linked at the given path, so a GCS could open it as if the flight controller were wired locally:
+        esp-vtx-gs-rs -d wlan0 --serial /tmp/vtx-serial
+        mavproxy.py --master /tmp/vtx-serial
+    The pty link is removed when the process exits(refer remove_link_on_exit),
+    a stale one left by a killed process is replaced at the next start.
+*/
+pub struct SerialBridge {
+    port: File,
+    _pty_slave: Option<File>, // keep the slave open, or reading the master fails when no GCS is connected
+    link: Option<PathBuf>,
+}
+
+// bytes waiting to be written to the serial port, the newer ones are dropped when full
+const DOWNSTREAM_QUEUE_LEN: usize = 64;
+// the bytes read in this time are sent in one payload, as a read in raw mode is often a few bytes
+const COALESCE_TIME: Duration = Duration::from_millis(5);
+
+// the pty link to remove at exit, async-signal-safe to read
+static EXIT_LINK: OnceLock<CString> = OnceLock::new();
+
+extern "C" fn remove_exit_link() {
+    if let Some(link) = EXIT_LINK.get() {
+        unsafe { libc::unlink(link.as_ptr()) };
+    }
+}
+
+extern "C" fn remove_exit_link_and_raise(signal: libc::c_int) {
+    remove_exit_link();
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/*
+    wait at most timeout for the fd to be readable, return false if timed out.
+*/
+fn wait_readable(fd: RawFd, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout_ms = timeout.as_micros().div_ceil(1000) as libc::c_int;
+    loop {
+        match cvt(unsafe { libc::poll(&mut pollfd, 1, timeout_ms) }) {
+            Ok(ret) => return Ok(ret > 0),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn baud_constant(baud: u32) -> io::Result<libc::speed_t> {
+    Ok(match baud {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        921600 => libc::B921600,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported baud rate: {}", baud),
+            ))
+        }
+    })
+}
+
+/*
+    raw mode, so the bytes are passed through untouched.
+*/
+fn set_raw(fd: RawFd, baud: Option<u32>) -> io::Result<()> {
+    let mut termios = MaybeUninit::<libc::termios>::uninit();
+    unsafe {
+        cvt(libc::tcgetattr(fd, termios.as_mut_ptr()))?;
+        let mut termios = termios.assume_init();
+        libc::cfmakeraw(&mut termios);
+        if let Some(baud) = baud {
+            cvt(libc::cfsetspeed(&mut termios, baud_constant(baud)?))?;
+        }
+        cvt(libc::tcsetattr(fd, libc::TCSANOW, &termios))?;
+    }
+    Ok(())
+}
+
+impl SerialBridge {
+    /*
+        open the serial device at path with the baud rate,
+        or create a pseudo-terminal linked at path if it is not a serial device.
+        A symlink to a pty is the stale link of a bridge, which is replaced instead of opening the pty,
+        the other symlinks are followed, e.g. /dev/serial/by-id/...
+    */
+    pub fn open(path: &Path, baud: u32) -> io::Result<Self> {
+        let is_device = match fs::symlink_metadata(path) {
+            Ok(x) if x.file_type().is_symlink() => {
+                let stale_pty = fs::read_link(path)?.starts_with("/dev/pts");
+                !stale_pty
+                    && fs::metadata(path)
+                        .map(|x| x.file_type().is_char_device())
+                        .unwrap_or(false)
+            }
+            Ok(x) => x.file_type().is_char_device(),
+            Err(_) => false,
+        };
+        if is_device {
+            Self::open_device(path, baud)
+        } else {
+            Self::open_pty(path)
+        }
+    }
+
+    pub fn open_device(path: &Path, baud: u32) -> io::Result<Self> {
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+        set_raw(port.as_raw_fd(), Some(baud))?;
+        Ok(SerialBridge {
+            port,
+            _pty_slave: None,
+            link: None,
+        })
+    }
+
+    /*
+        the symlink at link is replaced if exists, and removed when the bridge is dropped.
+        Any other file at link is kept, and an error is returned.
+    */
+    pub fn open_pty(link: &Path) -> io::Result<Self> {
+        let (mut master, mut slave) = (0, 0);
+        unsafe {
+            cvt(libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null(),
+                std::ptr::null(),
+            ))?;
+        }
+        let (port, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        set_raw(slave.as_raw_fd(), None)?;
+
+        let slave_path = fs::read_link(format!("/proc/self/fd/{}", slave.as_raw_fd()))?;
+        match fs::symlink_metadata(link) {
+            Ok(x) if x.file_type().is_symlink() => fs::remove_file(link)?,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a serial device", link.display()),
+                ))
+            }
+            Err(_) => {}
+        }
+        std::os::unix::fs::symlink(&slave_path, link)?;
+        Ok(SerialBridge {
+            port,
+            _pty_slave: Some(slave),
+            link: Some(link.to_path_buf()),
+        })
+    }
+
+    /*
+        remove the pty link when the process exits or is killed by SIGINT, SIGTERM or SIGHUP,
+        as the bridge is never dropped then. Only the link of the first bridge is removed.
+    */
+    pub fn remove_link_on_exit(&self) {
+        let Some(link) = &self.link else {
+            return;
+        };
+        let Ok(link) = CString::new(link.as_os_str().as_bytes()) else {
+            return;
+        };
+        if EXIT_LINK.set(link).is_err() {
+            return;
+        }
+        unsafe {
+            libc::atexit(remove_exit_link);
+            for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+                libc::signal(
+                    signal,
+                    remove_exit_link_and_raise as extern "C" fn(libc::c_int) as libc::sighandler_t,
+                );
+            }
+        }
+    }
+
+    /*
+        spawn a thread writing the downstream bytes to the serial port,
+        the returned sender never blocks, so it could be called in the capture thread.
+    */
+    pub fn spawn_downstream(&self) -> io::Result<SerialWriter> {
+        let mut port = self.port.try_clone()?;
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(DOWNSTREAM_QUEUE_LEN);
+        std::thread::Builder::new()
+            .name("serial-downstream".to_string())
+            .spawn(move || {
+                for data in receiver {
+                    if let Err(e) = port.write_all(&data) {
+                        crate::warn_limited!("serial write failed:{}", e);
+                    }
+                }
+            })?;
+        Ok(SerialWriter { sender })
+    }
+
+    /*
+        read the serial port and push the bytes to the uplink queue, it never returns unless the port is closed.
+        The reads are coalesced into a payload until it's full or COALESCE_TIME after its first byte.
+    */
+    pub fn run_upstream(&self, queue: UplinkQueue) {
+        let mut port = &self.port;
+        let mut payload = [0u8; GROUND2AIR_DATA_PAYLOAD_MAX_SIZE];
+        let mut len = 0;
+        let mut deadline = Instant::now();
+        loop {
+            if len > 0 {
+                let readable = wait_readable(
+                    port.as_raw_fd(),
+                    deadline.saturating_duration_since(Instant::now()),
+                );
+                if !readable.unwrap_or(true) {
+                    queue.push(UplinkPriority::Normal, &payload[..len]).unwrap(); // never larger than the max size
+                    len = 0;
+                    continue;
+                }
+            }
+            let read_len = match port.read(&mut payload[len..]) {
+                Ok(0) => return,
+                Ok(read_len) => read_len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    log::warn!("serial read failed:{}", e);
+                    return;
+                }
+            };
+            if len == 0 {
+                deadline = Instant::now() + COALESCE_TIME;
+            }
+            len += read_len;
+            if len == payload.len() {
+                queue.push(UplinkPriority::Normal, &payload).unwrap();
+                len = 0;
+            }
+        }
+    }
+}
+
+impl Drop for SerialBridge {
+    fn drop(&mut self) {
+        if let Some(link) = &self.link {
+            let _ = fs::remove_file(link);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SerialWriter {
+    sender: SyncSender<Vec<u8>>,
+}
+
+impl SerialWriter {
+    /*
+        return false if the data is dropped, as the serial port could not keep up.
+    */
+    pub fn write(&self, data: &[u8]) -> bool {
+        match self.sender.try_send(data.to_vec()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::{
+            fs::OpenOptions,
+            io::{Read, Write},
+            sync::Arc,
+            time::Duration,
+        };
+
+        use crate::{
+            inject::GROUND2AIR_DATA_PAYLOAD_MAX_SIZE, serial_bridge::SerialBridge,
+            uplink::UplinkQueue,
+        };
+
+        #[test]
+        fn test_pty_bridge() {
+            let link = std::env::temp_dir().join(format!("vtx-serial-test-{}", std::process::id()));
+            let bridge = Arc::new(SerialBridge::open(&link, 115200).unwrap());
+            let mut gcs = OpenOptions::new().read(true).write(true).open(&link).unwrap();
+
+            // upstream: split into data packet payloads
+            let queue = UplinkQueue::new(16);
+            let upstream = bridge.clone();
+            let upstream_queue = queue.clone();
+            std::thread::spawn(move || upstream.run_upstream(upstream_queue));
+            gcs.write_all(&[0x55; 100]).unwrap();
+            let mut received = Vec::new();
+            while let Some(payload) = queue.pop_timeout(Duration::from_secs(1)) {
+                assert!(payload.len() <= GROUND2AIR_DATA_PAYLOAD_MAX_SIZE);
+                received.extend(payload);
+                if received.len() == 100 {
+                    break;
+                }
+            }
+            assert_eq!(received, [0x55; 100]);
+
+            // the small writes are coalesced into one payload
+            for x in b"ping" {
+                gcs.write_all(&[*x]).unwrap();
+            }
+            assert_eq!(
+                queue.pop_timeout(Duration::from_secs(1)),
+                Some(b"ping".to_vec())
+            );
+
+            // downstream
+            let writer = bridge.spawn_downstream().unwrap();
+            assert!(writer.write(b"telemetry"));
+            let mut buf = [0u8; 9];
+            gcs.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"telemetry");
+
+            drop(gcs);
+            assert!(link.exists());
+        }
+
+        #[test]
+        fn test_stale_link() {
+            let dir = std::env::temp_dir();
+            let link = dir.join(format!("vtx-serial-live-{}", std::process::id()));
+            let stale = dir.join(format!("vtx-serial-stale-{}", std::process::id()));
+            let bridge = SerialBridge::open(&link, 115200).unwrap();
+            // a link left by a killed bridge, to a pty reused by another process
+            let pty = std::fs::read_link(&link).unwrap();
+            std::os::unix::fs::symlink(&pty, &stale).unwrap();
+            let stale_bridge = SerialBridge::open(&stale, 115200).unwrap();
+            assert_ne!(std::fs::read_link(&stale).unwrap(), pty);
+            drop(stale_bridge);
+            assert!(std::fs::symlink_metadata(&stale).is_err());
+
+            // a regular file is never replaced
+            std::fs::write(&stale, b"keep").unwrap();
+            let e = SerialBridge::open(&stale, 115200).err().unwrap();
+            assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists);
+            assert_eq!(std::fs::read(&stale).unwrap(), b"keep");
+            std::fs::remove_file(&stale).unwrap();
+            drop(bridge);
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    air_config::{AirConfig, ConfigError},
+    inject::tx_params::{TxParams, TxRate},
+    jpeg::JpegCheck,
+    logger::LogFilter,
+    osd::OsdFormat,
+    output::queue::QueuePolicy,
+    protocol::ProtocolVersion,
+    uplink::SchedulerConfig,
+};
+
+/*
+    Settings of the ground station, loaded from a toml file by --config.
+    The cli flags override the values in the file.
+
+        device = "wlan0"
+
+        [output]
+        port = 12345
+        target_ip = "192.168.2.101"
+
+        [air.camera]
+        resolution = "VGA"
+        quality = 12
+
+    Every field is optional, refer the structs below for all fields and defaults.
+
+    The air config confirmed by the air unit is saved to the air state file,
+    and loaded in the next session to override [air], so the camera tuning is kept.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub device: Option<String>,
+    pub protocol: Option<ProtocolVersion>, // of the air firmware, detected from the packets if not set
+    pub output: OutputSettings,
+    pub dump: DumpSettings,
+    pub control: ControlSettings,
+    pub uplink: UplinkSettings,
+    pub serial: SerialSettings,
+    pub stats: StatsSettings,
+    pub metrics: MetricsSettings,
+    pub osd: OsdSettings,
+    pub log: LogSettings,
+    pub events: EventSettings,
+    pub blackbox: BlackboxSettings,
+    pub air: AirConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    pub port: u16,
+    pub target_ip: String,
+    pub udp_header: bool,
+    pub mjpeg_out: Option<String>,
+    pub jpeg_check: JpegCheck,
+    pub queue: QueuePolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DumpSettings {
+    pub dir: Option<String>,
+    pub session: Option<String>,
+    pub sidecar: bool,
+    pub max_mb: Option<u64>,
+    pub queue: QueuePolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ControlSettings {
+    pub port: Option<u16>,
+    pub air_state: Option<PathBuf>,
+    pub confirm_timeout_ms: u64, // stop retransmitting a changed config faster if not confirmed in this time
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UplinkSettings {
+    pub fec_k: u32,
+    pub fec_n: u32,
+    pub port: Option<u16>,      // udp input of normal priority payloads
+    pub high_port: Option<u16>, // udp input of high priority payloads, e.g. rc commands
+    pub queue_len: usize,
+    pub tx_rate: Option<TxRate>, // e.g. "6m" or "mcs1", default is decided by the driver
+    pub tx_power: Option<i8>,    // dBm
+    pub tx_retries: u8,
+    pub flush_ms: u64, // flush a partially filled fec block after this time, 0 to disable
+    pub keepalive_ms: u64, // config interval when nothing is changed
+    pub config_burst: u32, // extra copies of a changed config
+    pub max_data_rate: u32, // data packets per second, 0 for unlimited
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SerialSettings {
+    pub path: Option<PathBuf>, // a serial device, or the link of a created pty
+    pub baud: u32,             // only for a serial device
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsSettings {
+    pub interval_ms: u64, // of the status line
+    pub window_ms: u64,   // the link quality is calculated over this window
+    pub json: bool,       // print the link report as json lines instead of the status line
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    pub port: Option<u16>, // http port of the prometheus /metrics endpoint
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsdSettings {
+    pub port: Option<u16>,         // udp port of the clients to send the OSD telemetry to
+    pub target_ip: Option<String>, // default: output.target_ip
+    pub interval_ms: u64,
+    pub format: OsdFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    pub level: LogFilter, // e.g. "info,esp_vtx_gs_rs::uplink=debug"
+    pub json: bool,       // write the logs as json lines
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventSettings {
+    pub path: Option<PathBuf>, // json-lines event log, appended if exists
+    pub snapshot_interval_ms: u64, // of the stats snapshots, 0 to disable
+    pub link_timeout_ms: u64,      // the link is lost when no packet is captured in it
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlackboxSettings {
+    pub dir: Option<PathBuf>, // directory of the dumped pcap files, the blackbox is disabled if not set
+    pub window_ms: u64,       // the captured packets of this window are kept
+    pub max_mb: u64,          // memory cap of the kept packets
+    pub error_threshold: u32, // dump when the decode errors in a second reach it, 0 to disable
+    pub cooldown_ms: u64,     // min interval of the automatic dumps
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings {
+            port: 12345,
+            target_ip: "127.0.0.1".to_string(),
+            udp_header: false,
+            mjpeg_out: None,
+            jpeg_check: JpegCheck::Drop,
+            queue: QueuePolicy::LatestOnly,
+        }
+    }
+}
+
+impl Default for DumpSettings {
+    fn default() -> Self {
+        DumpSettings {
+            dir: None,
+            session: None,
+            sidecar: false,
+            max_mb: None,
+            queue: QueuePolicy::BoundedFifo(64),
+        }
+    }
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        ControlSettings {
+            port: None,
+            air_state: None,
+            confirm_timeout_ms: 3000,
+        }
+    }
+}
+
+impl Default for OsdSettings {
+    fn default() -> Self {
+        OsdSettings {
+            port: None,
+            target_ip: None,
+            interval_ms: 200,
+            format: OsdFormat::Json,
+        }
+    }
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        EventSettings {
+            path: None,
+            snapshot_interval_ms: 10000,
+            link_timeout_ms: 2000,
+        }
+    }
+}
+
+impl Default for BlackboxSettings {
+    fn default() -> Self {
+        BlackboxSettings {
+            dir: None,
+            window_ms: 5000,
+            max_mb: 64,
+            error_threshold: 20,
+            cooldown_ms: 30000,
+        }
+    }
+}
+
+impl UplinkSettings {
+    pub fn flush_timeout(&self) -> Option<Duration> {
+        if self.flush_ms == 0 {
+            None
+        } else {
+            Some(Duration::from_millis(self.flush_ms))
+        }
+    }
+
+    pub fn scheduler_config(&self) -> SchedulerConfig {
+        SchedulerConfig {
+            keepalive: Duration::from_millis(self.keepalive_ms),
+            burst_count: self.config_burst,
+            max_data_rate: self.max_data_rate,
+            ..Default::default()
+        }
+    }
+
+    pub fn tx_params(&self) -> TxParams {
+        TxParams {
+            rate: self.tx_rate,
+            tx_power: self.tx_power,
+            retries: self.tx_retries,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for UplinkSettings {
+    fn default() -> Self {
+        UplinkSettings {
+            fec_k: 2,
+            fec_n: 6,
+            port: None,
+            high_port: None,
+            queue_len: 16,
+            tx_rate: None,
+            tx_power: None,
+            tx_retries: 0,
+            flush_ms: 20,
+            keepalive_ms: 500,
+            config_burst: 2,
+            max_data_rate: 100,
+        }
+    }
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        SerialSettings {
+            path: None,
+            baud: 115200,
+        }
+    }
+}
+
+impl Default for StatsSettings {
+    fn default() -> Self {
+        StatsSettings {
+            interval_ms: 1000,
+            window_ms: 10000,
+            json: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Serialize(toml::ser::Error),
+    InvalidAirConfig(ConfigError),
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SettingsError::Parse(path, e) => write!(f, "{}: {}", path.display(), e),
+            SettingsError::Serialize(e) => write!(f, "serialize settings failed: {}", e),
+            SettingsError::InvalidAirConfig(e) => write!(f, "invalid air config: {}", e),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+fn read_toml<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, SettingsError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| SettingsError::Io(path.to_path_buf(), e))?;
+    toml::from_str(&content).map_err(|e| SettingsError::Parse(path.to_path_buf(), e))
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        let ret: Settings = read_toml(path)?;
+        ret.air.validate().map_err(SettingsError::InvalidAirConfig)?;
+        Ok(ret)
+    }
+
+    /*
+        the air state file, default is next to the config file: gs.toml -> gs.air.toml
+    */
+    pub fn air_state_path(&self, config_path: Option<&Path>) -> Option<PathBuf> {
+        self.control
+            .air_state
+            .clone()
+            .or_else(|| config_path.map(|x| x.with_extension("air.toml")))
+    }
+}
+
+/*
+    load the air config saved by the last session, None if it was never saved.
+*/
+pub fn load_air_state(path: &Path) -> Result<Option<AirConfig>, SettingsError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let ret: AirConfig = read_toml(path)?;
+    ret.validate().map_err(SettingsError::InvalidAirConfig)?;
+    Ok(Some(ret))
+}
+
+pub fn save_air_state(path: &Path, config: &AirConfig) -> Result<(), SettingsError> {
+    let content = toml::to_string_pretty(config).map_err(SettingsError::Serialize)?;
+    // write to a temp file then rename, so a crash never leaves a broken state file
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content).map_err(|e| SettingsError::Io(tmp_path.clone(), e))?;
+    fs::rename(&tmp_path, path).map_err(|e| SettingsError::Io(path.to_path_buf(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::fs;
+
+        use crate::{
+            air_config::{AirConfig, Resolution},
+            output::queue::QueuePolicy,
+            settings::*,
+        };
+
+        #[test]
+        fn test_load() {
+            let settings: Settings = toml::from_str(
+                r#"
+                device = "wlan1"
+                [output]
+                port = 5600
+                queue = "drop-oldest:2"
+                [air]
+                wifi_rate = "RATE_G_24M_ODFM"
+                [air.camera]
+                resolution = "VGA"
+                quality = 12
+                "#,
+            )
+            .unwrap();
+            assert_eq!(settings.device.as_deref(), Some("wlan1"));
+            assert_eq!(settings.output.port, 5600);
+            assert_eq!(settings.output.target_ip, "127.0.0.1");
+            assert_eq!(settings.output.queue, QueuePolicy::DropOldest(2));
+            assert_eq!(settings.air.camera.resolution, Resolution::Vga);
+            assert_eq!(settings.air.camera.quality, 12);
+            assert_eq!(settings.air.camera.sharpness, -1); // default
+            assert_eq!(settings.uplink.fec_n, 6);
+
+            assert!(toml::from_str::<Settings>("unknown = 1").is_err());
+        }
+
+        #[test]
+        fn test_air_state() {
+            let dir = std::env::temp_dir().join("esp_vtx_gs_rs_test_air_state");
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("gs.air.toml");
+            let _ = fs::remove_file(&path);
+            assert_eq!(load_air_state(&path).unwrap(), None);
+
+            let config = AirConfig::builder().quality(20).build().unwrap();
+            save_air_state(&path, &config).unwrap();
+            assert_eq!(load_air_state(&path).unwrap(), Some(config));
+
+            fs::write(&path, "[camera]\nquality = 64\n").unwrap();
+            assert!(matches!(
+                load_air_state(&path),
+                Err(SettingsError::InvalidAirConfig(_))
+            ));
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::ConnectStats;
+
+/*
+    Link quality over a sliding window, from the snapshots of the cumulative ConnectStats.
+
+    The counters of a report are the increase within the window, the rates are per second,
+    the latency is from the first part of a frame received to the frame completed.
+    Call record periodically(e.g. every report interval), the window is only as accurate as the records.
+*/
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct LinkReport {
+    pub window_ms: u64,
+    pub packets: u32,
+    pub filtered_packets: u32,
+    pub duplicate_packets: u32,
+    pub complete_blocks: u32,
+    pub recovered_blocks: u32,
+    pub lost_blocks: u32,
+    pub complete_frames: u32,
+    pub dropped_frames: u32, // dropped by the jpeg check
+    pub partial_frames: u32, // incomplete frames dropped
+    pub crc_errors: u32,
+    pub jpeg_errors: u32,
+    pub packet_rate: f64,
+    pub byte_rate: f64,
+    pub frame_byte_rate: f64,
+    pub fps: f64,
+    pub block_loss: f64, // lost / all blocks, 0..=1
+    pub latency_avg_ms: Option<f64>,
+    pub latency_max_ms: Option<f64>,
+    pub rssi: Option<i8>,
+}
+
+pub struct StatsEngine {
+    window: Duration,
+    snapshots: VecDeque<(Instant, ConnectStats)>,
+    latencies: VecDeque<(Instant, Duration)>,
+}
+
+impl StatsEngine {
+    pub fn new(window: Duration) -> Self {
+        StatsEngine {
+            window,
+            snapshots: VecDeque::new(),
+            latencies: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, stats: &ConnectStats) {
+        self.snapshots.push_back((now, stats.clone()));
+        // keep the newest snapshot at or before the window start as the base
+        while self.snapshots.len() > 2 && now.duration_since(self.snapshots[1].0) >= self.window {
+            self.snapshots.pop_front();
+        }
+        self.prune_latencies(now);
+    }
+
+    pub fn observe_latency(&mut self, now: Instant, latency: Duration) {
+        self.latencies.push_back((now, latency));
+        self.prune_latencies(now);
+    }
+
+    fn prune_latencies(&mut self, now: Instant) {
+        while let Some((time, _)) = self.latencies.front() {
+            if now.saturating_duration_since(*time) > self.window {
+                self.latencies.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /*
+        the report between the oldest and the newest record in the window, None before two records.
+    */
+    pub fn report(&self) -> Option<LinkReport> {
+        let (start, base) = self.snapshots.front()?;
+        let (end, last) = self.snapshots.back()?;
+        let elapsed = end.duration_since(*start);
+        if elapsed.is_zero() {
+            return None;
+        }
+        let secs = elapsed.as_secs_f64();
+        let complete_blocks = last.complete_block_count - base.complete_block_count;
+        let recovered_blocks = last.recovered_block_count - base.recovered_block_count;
+        let lost_blocks = last.broken_block_count - base.broken_block_count;
+        let all_blocks = complete_blocks + recovered_blocks + lost_blocks;
+        let complete_frames = last.frame_count - base.frame_count;
+        let to_ms = |x: Duration| x.as_secs_f64() * 1000.0;
+        let latencies = self.latencies.iter().map(|(_, x)| *x);
+
+        Some(LinkReport {
+            window_ms: elapsed.as_millis() as u64,
+            packets: last.packet_count - base.packet_count,
+            filtered_packets: last.filtered_packet_count - base.filtered_packet_count,
+            duplicate_packets: last.duplicate_packet_count - base.duplicate_packet_count,
+            complete_blocks,
+            recovered_blocks,
+            lost_blocks,
+            complete_frames,
+            dropped_frames: last.jpeg_dropped_count - base.jpeg_dropped_count,
+            partial_frames: last.dropped_frame_count - base.dropped_frame_count,
+            crc_errors: last.crc_error_count - base.crc_error_count,
+            jpeg_errors: last.jpeg_error_count - base.jpeg_error_count,
+            packet_rate: (last.packet_count - base.packet_count) as f64 / secs,
+            byte_rate: (last.byte_count - base.byte_count) as f64 / secs,
+            frame_byte_rate: (last.frame_byte_count - base.frame_byte_count) as f64 / secs,
+            fps: complete_frames as f64 / secs,
+            block_loss: if all_blocks == 0 {
+                0.0
+            } else {
+                lost_blocks as f64 / all_blocks as f64
+            },
+            latency_avg_ms: (!self.latencies.is_empty())
+                .then(|| to_ms(latencies.clone().sum::<Duration>()) / self.latencies.len() as f64),
+            latency_max_ms: latencies.max().map(to_ms),
+            rssi: last.rssi,
+        })
+    }
+}
+
+impl LinkReport {
+    /*
+        one line for the console.
+    */
+    pub fn summary(&self) -> String {
+        let latency = match (self.latency_avg_ms, self.latency_max_ms) {
+            (Some(avg), Some(max)) => format!("{:.1}/{:.1}ms", avg, max),
+            _ => "-".to_string(),
+        };
+        format!(
+            "fps:{:.1} rate:{:.1}KB/s blocks:{}+{}fec-{}lost({:.1}%) frames:{}/partial:{}/dropped:{} crc_error:{} latency:{}",
+            self.fps,
+            self.byte_rate / 1024.0,
+            self.complete_blocks,
+            self.recovered_blocks,
+            self.lost_blocks,
+            self.block_loss * 100.0,
+            self.complete_frames,
+            self.partial_frames,
+            self.dropped_frames,
+            self.crc_errors,
+            latency,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::time::{Duration, Instant};
+
+        use crate::{stats::StatsEngine, ConnectStats};
+
+        #[test]
+        fn test_window() {
+            let now = Instant::now();
+            let mut engine = StatsEngine::new(Duration::from_secs(2));
+            let mut stats = ConnectStats::new();
+            engine.record(now, &stats);
+            assert!(engine.report().is_none());
+
+            for i in 1..=4u64 {
+                stats.packet_count += 100;
+                stats.byte_count += 100 * 1500;
+                stats.complete_block_count += 8;
+                stats.recovered_block_count += 1;
+                stats.broken_block_count += 1;
+                stats.frame_count += 30;
+                engine.observe_latency(now + Duration::from_secs(i), Duration::from_millis(10 * i));
+                engine.record(now + Duration::from_secs(i), &stats);
+            }
+            // only the last 2 seconds
+            let report = engine.report().unwrap();
+            assert_eq!(report.window_ms, 2000);
+            assert_eq!(report.packets, 200);
+            assert_eq!(report.packet_rate, 100.0);
+            assert_eq!(report.byte_rate, 150000.0);
+            assert_eq!(report.fps, 30.0);
+            assert_eq!((report.complete_blocks, report.recovered_blocks, report.lost_blocks), (16, 2, 2));
+            assert_eq!(report.block_loss, 0.1);
+            assert_eq!(report.latency_max_ms, Some(40.0));
+            assert_eq!(report.latency_avg_ms, Some(30.0));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{error::Error, fmt::Display, time::SystemTime};
+
+use crate::{air_config::Resolution, output::unix_micros, Frame};
+
+/*
+    Optional header prepended to each udp datagram of the jpeg output,
+    so clients could know the frame index, timestamps and link quality of the jpeg.
+
+    Layout (little-endian):
+    offset  size  field
+    0       4     magic "EVTX"
+    4       1     version
+    5       1     header_len, clients should skip header_len bytes to get the jpeg,
+                  new fields of newer versions are only appended, so the fields below are decoded
+                  from any version and the appended ones are skipped.
+    6       4     frame_index
+    10      1     resolution (Resolution in packets.h)
+    11      2     width
+    13      2     height
+    15      8     capture_time_us, unix time of the first packet of the frame captured(the pcap timestamp)
+    23      8     complete_time_us, unix time when the frame is completed
+    31      1     rssi(dBm), i8::MIN if unknown
+    32      4     packet_count
+    36      4     lost_block_count
+    40      4     dropped_frame_count
+*/
+pub const STREAM_HEADER_MAGIC: [u8; 4] = *b"EVTX";
+pub const STREAM_HEADER_VERSION: u8 = 1;
+pub const STREAM_HEADER_SIZE: usize = 44;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamHeader {
+    pub version: u8,
+    pub frame_index: u32,
+    pub resolution: u8,
+    pub width: u16,
+    pub height: u16,
+    pub capture_time_us: u64,
+    pub complete_time_us: u64,
+    pub rssi: Option<i8>,
+    pub packet_count: u32,
+    pub lost_block_count: u32,
+    pub dropped_frame_count: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamHeaderError {
+    TooShort(usize),
+    BadMagic,
+    UnsupportedVersion(u8),
+}
+
+impl Display for StreamHeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamHeaderError::TooShort(len) => write!(f, "datagram too short for stream header: {} bytes", len),
+            StreamHeaderError::BadMagic => write!(f, "bad stream header magic"),
+            StreamHeaderError::UnsupportedVersion(v) => write!(f, "unsupported stream header version: {}", v),
+        }
+    }
+}
+
+impl Error for StreamHeaderError {}
+
+impl StreamHeader {
+    pub fn from_frame(frame: &Frame) -> Self {
+        let resolution = frame.resolution().unwrap_or(u8::MAX);
+        let (width, height) = Resolution::try_from(resolution)
+            .map(|x| x.dimensions())
+            .unwrap_or((0, 0));
+        let stats = &frame.link_stats;
+        StreamHeader {
+            version: STREAM_HEADER_VERSION,
+            frame_index: frame.frame_index(),
+            resolution,
+            width,
+            height,
+            capture_time_us: unix_micros(frame.capture_time()),
+            complete_time_us: unix_micros(frame.complete_time.unwrap_or_else(SystemTime::now)),
+            rssi: stats.rssi,
+            packet_count: stats.packet_count,
+            lost_block_count: stats.broken_block_count,
+            dropped_frame_count: stats.dropped_frame_count,
+        }
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&STREAM_HEADER_MAGIC);
+        out.push(self.version);
+        out.push(STREAM_HEADER_SIZE as u8);
+        out.extend_from_slice(&self.frame_index.to_le_bytes());
+        out.push(self.resolution);
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&self.capture_time_us.to_le_bytes());
+        out.extend_from_slice(&self.complete_time_us.to_le_bytes());
+        out.push(self.rssi.unwrap_or(i8::MIN) as u8);
+        out.extend_from_slice(&self.packet_count.to_le_bytes());
+        out.extend_from_slice(&self.lost_block_count.to_le_bytes());
+        out.extend_from_slice(&self.dropped_frame_count.to_le_bytes());
+    }
+
+    /*
+        decode the header of a datagram, return the header and the jpeg data after it.
+    */
+    pub fn decode(datagram: &[u8]) -> Result<(StreamHeader, &[u8]), StreamHeaderError> {
+        if datagram.len() < 6 {
+            return Err(StreamHeaderError::TooShort(datagram.len()));
+        }
+        if datagram[..4] != STREAM_HEADER_MAGIC {
+            return Err(StreamHeaderError::BadMagic);
+        }
+        let version = datagram[4];
+        if version == 0 {
+            return Err(StreamHeaderError::UnsupportedVersion(version));
+        }
+        let header_len = datagram[5] as usize;
+        if header_len < STREAM_HEADER_SIZE || datagram.len() < header_len {
+            return Err(StreamHeaderError::TooShort(datagram.len()));
+        }
+
+        let u16_at = |i: usize| u16::from_le_bytes(datagram[i..i + 2].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(datagram[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(datagram[i..i + 8].try_into().unwrap());
+        let rssi = datagram[31] as i8;
+
+        let header = StreamHeader {
+            version,
+            frame_index: u32_at(6),
+            resolution: datagram[10],
+            width: u16_at(11),
+            height: u16_at(13),
+            capture_time_us: u64_at(15),
+            complete_time_us: u64_at(23),
+            rssi: if rssi == i8::MIN { None } else { Some(rssi) },
+            packet_count: u32_at(32),
+            lost_block_count: u32_at(36),
+            dropped_frame_count: u32_at(40),
+        };
+        Ok((header, &datagram[header_len..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        use crate::{stream_header::*, Frame};
+
+        fn new_header() -> StreamHeader {
+            StreamHeader {
+                version: STREAM_HEADER_VERSION,
+                frame_index: 1234,
+                resolution: 3,
+                width: 640,
+                height: 480,
+                capture_time_us: 1_700_000_000_000_000,
+                complete_time_us: 1_700_000_000_012_000,
+                rssi: Some(-56),
+                packet_count: 100,
+                lost_block_count: 2,
+                dropped_frame_count: 1,
+            }
+        }
+
+        #[test]
+        fn test_encode_decode() {
+            let header = new_header();
+            let mut datagram = Vec::new();
+            header.encode(&mut datagram);
+            assert_eq!(datagram.len(), STREAM_HEADER_SIZE);
+            datagram.extend_from_slice(&[0xff, 0xd8, 0xff, 0xd9]);
+
+            let (decoded, jpeg) = StreamHeader::decode(&datagram).unwrap();
+            assert_eq!(decoded, header);
+            assert_eq!(jpeg, &[0xff, 0xd8, 0xff, 0xd9]);
+        }
+
+        #[test]
+        fn test_from_frame() {
+            let mut frame = Frame::new(7);
+            frame.timestamps.first_packet = Some(UNIX_EPOCH + Duration::from_micros(1234));
+            let header = StreamHeader::from_frame(&frame);
+            assert_eq!(header.frame_index, 7);
+            assert_eq!(header.capture_time_us, 1234);
+        }
+
+        #[test]
+        fn test_decode_skip_unknown_fields() {
+            let mut datagram = Vec::new();
+            new_header().encode(&mut datagram);
+            datagram[5] = STREAM_HEADER_SIZE as u8 + 2; // a longer header of the same version
+            datagram.extend_from_slice(&[0, 0, 0xff, 0xd8]);
+            let (_, jpeg) = StreamHeader::decode(&datagram).unwrap();
+            assert_eq!(jpeg, &[0xff, 0xd8]);
+        }
+
+        #[test]
+        fn test_decode_newer_version() {
+            let mut datagram = Vec::new();
+            new_header().encode(&mut datagram);
+            datagram[4] = 2;
+            datagram[5] = STREAM_HEADER_SIZE as u8 + 4; // fields appended by v2
+            datagram.extend_from_slice(&[1, 2, 3, 4, 0xff, 0xd8]);
+            let (header, jpeg) = StreamHeader::decode(&datagram).unwrap();
+            assert_eq!(header.version, 2);
+            assert_eq!(header.frame_index, 1234);
+            assert_eq!(header.dropped_frame_count, 1);
+            assert_eq!(jpeg, &[0xff, 0xd8]);
+        }
+
+        #[test]
+        fn test_decode_error() {
+            let mut datagram = Vec::new();
+            new_header().encode(&mut datagram);
+            assert_eq!(
+                StreamHeader::decode(&datagram[..20]),
+                Err(StreamHeaderError::TooShort(20))
+            );
+            datagram[4] = 0;
+            assert_eq!(
+                StreamHeader::decode(&datagram),
+                Err(StreamHeaderError::UnsupportedVersion(0))
+            );
+            assert_eq!(
+                StreamHeader::decode(&[0xff, 0xd8, 0xff, 0xe0, 0, 0x10]),
+                Err(StreamHeaderError::BadMagic)
+            );
+        }
+    }
+}
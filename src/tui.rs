@@ -0,0 +1,443 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline},
+    Frame,
+};
+
+use crate::{
+    air_config::{AirConfig, ConfigError, Resolution, WifiRate},
+    config_tracker::ConfigTracker,
+    control::{CameraParams, ControlCommand},
+    logger::RecentLogs,
+    metrics::Metrics,
+    stats::{LinkReport, StatsEngine},
+    ConnectStats, MAX_ANTENNAS,
+};
+
+/*
+    Terminal dashboard of the live link state, --tui(built with the tui feature):
+    the signal of the card and its antennas, the loss/fec/fps/bitrate graphs of the last minutes,
+    the air config, the outputs and the recent logs.
+
+    keys:
+        r / R   next / previous resolution
+        + / -   better / worse jpeg quality(a lower / higher quality value)
+        w / W   next / previous wifi rate
+        q       quit
+
+    The key changes are applied as the control commands, refer do_when_command.
+*/
+pub struct Dashboard {
+    stats: Arc<RwLock<ConnectStats>>,
+    metrics: Arc<RwLock<Metrics>>,
+    tracker: Arc<Mutex<ConfigTracker>>,
+    logs: RecentLogs,
+    command_callback: Option<CommandCallback>,
+    engine: StatsEngine,
+    history: VecDeque<LinkReport>,
+    last_sample: Option<Instant>,
+    message: String, // result of the last key command
+}
+
+type GraphValue = fn(&LinkReport) -> f64;
+
+type CommandCallback = Box<dyn FnMut(&ControlCommand) -> Result<AirConfig, ConfigError> + Send>;
+
+// the rates are of this window, and a sample of the graphs is taken every window
+const SAMPLE_WINDOW: Duration = Duration::from_secs(1);
+const HISTORY_LEN: usize = 300;
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+// the range of the signal bars
+const RSSI_MIN: f64 = -100.0;
+const RSSI_MAX: f64 = -20.0;
+
+// the worst jpeg quality of the camera
+const MAX_QUALITY: u8 = 63;
+
+/*
+    the command of a key, None if the key does nothing.
+*/
+pub fn key_command(key: char, config: &AirConfig) -> Option<ControlCommand> {
+    fn step<T: Copy + PartialEq>(all: &[T], current: T, forward: bool) -> Option<T> {
+        let index = all.iter().position(|x| *x == current)?;
+        let next = if forward {
+            index.checked_add(1).filter(|x| *x < all.len())?
+        } else {
+            index.checked_sub(1)?
+        };
+        Some(all[next])
+    }
+    let camera = |params: CameraParams| Some(ControlCommand::SetCamera(params));
+    let radio = |wifi_rate| {
+        Some(ControlCommand::SetRadio {
+            wifi_rate: Some(wifi_rate),
+            wifi_power: None,
+        })
+    };
+    match key {
+        'r' | 'R' => camera(CameraParams {
+            resolution: Some(step(Resolution::ALL, config.camera.resolution, key == 'r')?),
+            ..Default::default()
+        }),
+        '+' | '-' => camera(CameraParams {
+            quality: Some(match key {
+                '+' => config.camera.quality.checked_sub(1)?,
+                _ => config
+                    .camera
+                    .quality
+                    .checked_add(1)
+                    .filter(|x| *x <= MAX_QUALITY)?,
+            }),
+            ..Default::default()
+        }),
+        'w' | 'W' => radio(step(WifiRate::ALL, config.wifi_rate, key == 'w')?),
+        _ => None,
+    }
+}
+
+fn percent(part: u32, all: u32) -> f64 {
+    if all == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / all as f64
+    }
+}
+
+fn frame_loss(report: &LinkReport) -> f64 {
+    let lost = report.partial_frames + report.dropped_frames;
+    percent(lost, report.complete_frames + lost)
+}
+
+fn fec_recovery(report: &LinkReport) -> f64 {
+    let all = report.complete_blocks + report.recovered_blocks + report.lost_blocks;
+    percent(report.recovered_blocks, all)
+}
+
+fn rssi_gauge(title: String, rssi: Option<i8>) -> Gauge<'static> {
+    let (ratio, label) = match rssi {
+        Some(x) => (
+            ((x as f64 - RSSI_MIN) / (RSSI_MAX - RSSI_MIN)).clamp(0.0, 1.0),
+            format!("{}dBm", x),
+        ),
+        None => (0.0, "-".to_string()),
+    };
+    let color = match rssi {
+        Some(x) if x >= -60 => Color::Green,
+        Some(x) if x >= -75 => Color::Yellow,
+        _ => Color::Red,
+    };
+    Gauge::default()
+        .block(Block::default().title(title))
+        .gauge_style(Style::default().fg(color))
+        .ratio(ratio)
+        .label(label)
+}
+
+impl Dashboard {
+    pub fn new(
+        stats: Arc<RwLock<ConnectStats>>,
+        metrics: Arc<RwLock<Metrics>>,
+        tracker: Arc<Mutex<ConfigTracker>>,
+        logs: RecentLogs,
+    ) -> Self {
+        Dashboard {
+            stats,
+            metrics,
+            tracker,
+            logs,
+            command_callback: None,
+            engine: StatsEngine::new(SAMPLE_WINDOW),
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            last_sample: None,
+            message: String::new(),
+        }
+    }
+
+    /*
+        apply the command of a key, return the new config or the reason it's rejected.
+    */
+    pub fn do_when_command<F>(&mut self, func: F)
+    where
+        F: FnMut(&ControlCommand) -> Result<AirConfig, ConfigError> + Send + 'static,
+    {
+        self.command_callback = Some(Box::new(func))
+    }
+
+    /*
+        draw until q is pressed, the terminal is restored when it returns.
+    */
+    pub fn run(mut self) -> io::Result<()> {
+        let mut terminal = ratatui::init();
+        let ret = loop {
+            self.sample(Instant::now());
+            if let Err(e) = terminal.draw(|frame| self.draw(frame)) {
+                break Err(e);
+            }
+            match event::poll(REFRESH_INTERVAL)
+                .and_then(|ready| ready.then(event::read).transpose())
+            {
+                Ok(Some(Event::Key(key))) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+                    KeyCode::Char(c) => self.handle_key(c),
+                    _ => {}
+                },
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        ratatui::restore();
+        ret
+    }
+
+    fn sample(&mut self, now: Instant) {
+        self.engine.record(now, &self.stats.read().unwrap());
+        if self
+            .last_sample
+            .is_some_and(|x| now.duration_since(x) < SAMPLE_WINDOW)
+        {
+            return;
+        }
+        self.last_sample = Some(now);
+        if let Some(report) = self.engine.report() {
+            if self.history.len() >= HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(report);
+        }
+    }
+
+    fn handle_key(&mut self, key: char) {
+        let config = {
+            let tracker = self.tracker.lock().unwrap();
+            tracker.requested()
+        };
+        let (Some(command), Some(callback)) =
+            (key_command(key, &config), self.command_callback.as_mut())
+        else {
+            return;
+        };
+        self.message = match callback(&command) {
+            Ok(config) => format!(
+                "requested {} quality:{} {}",
+                config.camera.resolution, config.camera.quality, config.wifi_rate
+            ),
+            Err(e) => format!("rejected: {}", e),
+        };
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [signal, graphs, bottom, help] = Layout::vertical([
+            Constraint::Length(2 + 2 * (1 + MAX_ANTENNAS as u16 / 2)),
+            Constraint::Length(10),
+            Constraint::Min(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        self.draw_signal(frame, signal);
+        self.draw_graphs(frame, graphs);
+        let [config, events] =
+            Layout::horizontal([Constraint::Length(44), Constraint::Min(20)]).areas(bottom);
+        self.draw_config(frame, config);
+        self.draw_events(frame, events);
+        let help_line = format!(
+            " r/R resolution  +/- quality  w/W wifi rate  q quit   {}",
+            self.message
+        );
+        frame.render_widget(Paragraph::new(help_line), help);
+    }
+
+    fn draw_signal(&self, frame: &mut Frame, area: Rect) {
+        let stats = self.stats.read().unwrap();
+        let card = self.metrics.read().unwrap().card.clone();
+        let uptime = stats.start_time.elapsed().unwrap_or_default().as_secs();
+        let noise = stats.noise.map_or("-".to_string(), |x| format!("{}dBm", x));
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            " {} uptime {:02}:{:02}:{:02} noise {} ",
+            card,
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60,
+            noise
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let rows = Layout::vertical([Constraint::Length(2); 1 + MAX_ANTENNAS / 2]).split(inner);
+        frame.render_widget(rssi_gauge("rssi".to_string(), stats.rssi), rows[0]);
+        for (i, pair) in stats.antenna_rssi.chunks(2).enumerate() {
+            let columns = Layout::horizontal([Constraint::Ratio(1, 2); 2]).split(rows[i + 1]);
+            for (j, rssi) in pair.iter().enumerate() {
+                let antenna = i * 2 + j;
+                frame.render_widget(
+                    rssi_gauge(format!("antenna {}", antenna), *rssi),
+                    columns[j],
+                );
+            }
+        }
+    }
+
+    fn draw_graphs(&self, frame: &mut Frame, area: Rect) {
+        let [top, bottom] = Layout::vertical([Constraint::Ratio(1, 2); 2]).areas(area);
+        let top = Layout::horizontal([Constraint::Ratio(1, 2); 2]).split(top);
+        let bottom = Layout::horizontal([Constraint::Ratio(1, 3); 3]).split(bottom);
+        let graphs: [(Rect, &str, GraphValue, &str, Color); 5] = [
+            (top[0], "fps", |x| x.fps, "", Color::Cyan),
+            (
+                top[1],
+                "bitrate",
+                |x| x.frame_byte_rate * 8.0 / 1000.0,
+                "kbps",
+                Color::Cyan,
+            ),
+            (
+                bottom[0],
+                "block loss",
+                |x| x.block_loss * 100.0,
+                "%",
+                Color::Red,
+            ),
+            (bottom[1], "frame loss", frame_loss, "%", Color::Red),
+            (bottom[2], "fec recovery", fec_recovery, "%", Color::Yellow),
+        ];
+        for (area, name, value, unit, color) in graphs {
+            // the newest samples which fit in the width
+            let width = area.width.saturating_sub(2) as usize;
+            let skip = self.history.len().saturating_sub(width);
+            let data: Vec<u64> = self
+                .history
+                .iter()
+                .skip(skip)
+                .map(|x| (value(x) * 10.0).round() as u64) // keep a decimal
+                .collect();
+            let latest = self.history.back().map_or(0.0, value);
+            let title = format!(" {} {:.1}{} ", name, latest, unit);
+            let sparkline = Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .style(Style::default().fg(color))
+                .data(&data);
+            frame.render_widget(sparkline, area);
+        }
+    }
+
+    fn draw_config(&self, frame: &mut Frame, area: Rect) {
+        let (requested, confirmed, state, rtt) = {
+            let mut tracker = self.tracker.lock().unwrap();
+            let now = Instant::now();
+            let state = tracker.state(now);
+            (
+                tracker.requested(),
+                tracker.confirmed(),
+                state,
+                tracker.rtt(now),
+            )
+        };
+        let metrics = self.metrics.read().unwrap();
+        let config = confirmed.unwrap_or(requested);
+        let rtt = rtt.map_or("-".to_string(), |x| format!("{}ms", x.as_millis()));
+        let mut lines = vec![
+            Line::from(format!("state      {:?} rtt {}", state, rtt)),
+            Line::from(format!(
+                "resolution {} quality {}",
+                config.camera.resolution, config.camera.quality
+            )),
+            Line::from(format!(
+                "wifi       {} {}dBm",
+                config.wifi_rate, config.wifi_power
+            )),
+            Line::from(format!(
+                "fec        {}/{} mtu {}",
+                config.fec_codec_k, config.fec_codec_n, config.fec_codec_mtu
+            )),
+        ];
+        if confirmed.is_some_and(|x| x != requested) {
+            lines.push(Line::from(format!(
+                "requested  {} quality {} {}",
+                requested.camera.resolution, requested.camera.quality, requested.wifi_rate
+            )));
+        }
+        lines.push(Line::from(""));
+        for (name, stats) in &metrics.outputs {
+            lines.push(Line::from(format!(
+                "output {:<6} sent {} dropped {}",
+                name, stats.delivered, stats.dropped
+            )));
+        }
+        lines.push(Line::from(format!(
+            "uplink       injected {} errors {}",
+            metrics.scheduler.injected_frames, metrics.scheduler.send_errors
+        )));
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(" air config "));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_events(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let lines = self.logs.lines();
+        let items: Vec<ListItem> = lines
+            .iter()
+            .skip(lines.len().saturating_sub(height))
+            .map(|x| ListItem::new(x.as_str()))
+            .collect();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(" events "));
+        frame.render_widget(list, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use crate::{
+            air_config::{AirConfig, Resolution, WifiRate},
+            control::ControlCommand,
+            tui::key_command,
+        };
+
+        #[test]
+        fn test_key_command() {
+            let mut config = AirConfig::default();
+            config.camera.resolution = Resolution::Vga;
+            config.camera.quality = 12;
+            config.wifi_rate = WifiRate::G24mOdfm;
+
+            let apply = |key, config: &AirConfig| {
+                let mut config = *config;
+                key_command(key, &config)?.apply(&mut config).ok()?;
+                Some(config)
+            };
+            assert_eq!(
+                apply('r', &config).unwrap().camera.resolution,
+                Resolution::Svga
+            );
+            assert_eq!(
+                apply('R', &config).unwrap().camera.resolution,
+                Resolution::Hvga
+            );
+            assert_eq!(apply('+', &config).unwrap().camera.quality, 11);
+            assert_eq!(apply('-', &config).unwrap().camera.quality, 13);
+            assert_eq!(apply('w', &config).unwrap().wifi_rate, WifiRate::G36mOdfm);
+            assert_eq!(apply('W', &config).unwrap().wifi_rate, WifiRate::G18mOdfm);
+            assert!(key_command('x', &config).is_none());
+
+            config.camera.resolution = Resolution::Uxga;
+            config.camera.quality = 0;
+            assert!(key_command('r', &config).is_none());
+            assert!(key_command('+', &config).is_none());
+            assert!(matches!(
+                key_command('R', &config),
+                Some(ControlCommand::SetCamera(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    air_config::AirConfig,
+    config_tracker::ConfigTracker,
+    events::{Event, EventBus},
+    inject::{InjectError, InjectHandler, GROUND2AIR_DATA_PAYLOAD_MAX_SIZE},
+    protocol::ProtocolVersion,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UplinkPriority {
+    High,   // rc commands etc., which are useless when late
+    Normal, // mavlink, custom messages
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UplinkStats {
+    pub pushed: u64,
+    pub sent: u64,
+    pub dropped: u64,
+    pub len: usize,
+}
+
+struct UplinkState {
+    high: VecDeque<Vec<u8>>,
+    normal: VecDeque<Vec<u8>>,
+    stats: UplinkStats,
+    woken: bool,
+}
+
+/*
+    Payloads waiting to be injected to the air unit as ground2air data packets.
+    The inject thread sends the config packet when it is due, then the high priority payloads, then the normal ones.
+    Clone it to share the same queue between the pushing and the injecting threads.
+*/
+#[derive(Clone)]
+pub struct UplinkQueue {
+    capacity: usize,
+    inner: Arc<(Mutex<UplinkState>, Condvar)>,
+}
+
+impl UplinkQueue {
+    /*
+        capacity is per priority, the oldest payload is dropped when full.
+    */
+    pub fn new(capacity: usize) -> Self {
+        UplinkQueue {
+            capacity: capacity.max(1),
+            inner: Arc::new((
+                Mutex::new(UplinkState {
+                    high: VecDeque::new(),
+                    normal: VecDeque::new(),
+                    stats: UplinkStats::default(),
+                    woken: false,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    pub fn push(&self, priority: UplinkPriority, payload: &[u8]) -> Result<(), InjectError> {
+        if payload.len() > GROUND2AIR_DATA_PAYLOAD_MAX_SIZE {
+            return Err(InjectError::PayloadTooLarge {
+                len: payload.len(),
+                max: GROUND2AIR_DATA_PAYLOAD_MAX_SIZE,
+            });
+        }
+        let (lock, cond) = &*self.inner;
+        let mut state = lock.lock().unwrap();
+        state.stats.pushed += 1;
+        let queue = match priority {
+            UplinkPriority::High => &mut state.high,
+            UplinkPriority::Normal => &mut state.normal,
+        };
+        let dropped = queue.len() >= self.capacity;
+        if dropped {
+            queue.pop_front();
+        }
+        queue.push_back(payload.to_vec());
+        if dropped {
+            state.stats.dropped += 1;
+        }
+        cond.notify_one();
+        Ok(())
+    }
+
+    /*
+        wake up the waiting pop_timeout or wait_wake, e.g. when the config is changed.
+    */
+    pub fn wake(&self) {
+        let (lock, cond) = &*self.inner;
+        lock.lock().unwrap().woken = true;
+        cond.notify_all();
+    }
+
+    /*
+        wait at most timeout for a wake, the payloads are not popped.
+    */
+    pub fn wait_wake(&self, timeout: Duration) {
+        let (lock, cond) = &*self.inner;
+        let state = lock.lock().unwrap();
+        let (mut state, _) = cond.wait_timeout_while(state, timeout, |x| !x.woken).unwrap();
+        state.woken = false;
+    }
+
+    /*
+        wait at most timeout for a payload, the high priority ones first.
+        return None when woken.
+    */
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<Vec<u8>> {
+        let (lock, cond) = &*self.inner;
+        let state = lock.lock().unwrap();
+        let (mut state, _) = cond
+            .wait_timeout_while(state, timeout, |x| {
+                x.high.is_empty() && x.normal.is_empty() && !x.woken
+            })
+            .unwrap();
+        if state.woken {
+            state.woken = false;
+            return None;
+        }
+        let payload = match state.high.pop_front() {
+            Some(payload) => Some(payload),
+            None => state.normal.pop_front(),
+        };
+        if payload.is_some() {
+            state.stats.sent += 1;
+        }
+        payload
+    }
+
+    pub fn stats(&self) -> UplinkStats {
+        let state = self.inner.0.lock().unwrap();
+        UplinkStats {
+            len: state.high.len() + state.normal.len(),
+            ..state.stats
+        }
+    }
+}
+
+/*
+    Forward each datagram received on the udp port to the uplink queue as one payload.
+*/
+pub struct UplinkInput {
+    socket: UdpSocket,
+    queue: UplinkQueue,
+    priority: UplinkPriority,
+}
+
+impl UplinkInput {
+    pub fn new(port: u16, queue: UplinkQueue, priority: UplinkPriority) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], port)))?;
+        Ok(UplinkInput {
+            socket,
+            queue,
+            priority,
+        })
+    }
+
+    pub fn run(&self) {
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, src) = match self.socket.recv_from(&mut buf) {
+                Ok(ret) => ret,
+                Err(e) => {
+                    crate::warn_limited!("uplink port recv failed:{}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.queue.push(self.priority, &buf[..len]) {
+                crate::warn_limited!("uplink payload from {} dropped:{}", src, e);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    pub keepalive: Duration,      // config interval when nothing is changed
+    pub retry_interval: Duration, // config interval while the config is not confirmed
+    pub burst_count: u32,         // extra copies of a changed config
+    pub burst_interval: Duration,
+    pub max_data_rate: u32, // data packets per second, 0 for unlimited
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            keepalive: Duration::from_millis(500),
+            retry_interval: Duration::from_millis(100),
+            burst_count: 2,
+            burst_interval: Duration::from_millis(20),
+            max_data_rate: 100,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SchedulerStats {
+    pub config_packets: u64,
+    pub data_packets: u64,
+    pub injected_frames: u64, // wifi frames including the fec ones
+    pub send_errors: u64,
+}
+
+/*
+    Decide when to send the config and the data packets of the uplink:
+    a changed config is sent at once, then burst_count more copies every burst_interval,
+    then every retry_interval until confirmed, then every keepalive.
+    A renewed ping of the same config(the rtt probe) is sent at once as a single keepalive, without a burst.
+    The data packets are sent in between, at most max_data_rate per second.
+*/
+pub struct UplinkScheduler {
+    config: SchedulerConfig,
+    last_ping: Option<u8>,
+    last_config: Option<AirConfig>, // of the last sent config packet, the ping aside
+    burst_left: u32,
+    next_config_time: Instant,
+    next_data_time: Instant,
+    stats: Arc<Mutex<SchedulerStats>>,
+    protocol: Arc<Mutex<Option<ProtocolVersion>>>,
+    events: EventBus,
+}
+
+impl UplinkScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        let now = Instant::now();
+        UplinkScheduler {
+            config,
+            last_ping: None,
+            last_config: None,
+            burst_left: 0,
+            next_config_time: now,
+            next_data_time: now,
+            stats: Arc::new(Mutex::new(SchedulerStats::default())),
+            protocol: Arc::new(Mutex::new(None)),
+            events: EventBus::default(),
+        }
+    }
+
+    // publish the first send of each config
+    pub fn set_event_bus(&mut self, events: EventBus) {
+        self.events = events;
+    }
+
+    pub fn stats_handle(&self) -> Arc<Mutex<SchedulerStats>> {
+        self.stats.clone()
+    }
+
+    /*
+        set the protocol detected by the receiver through it, the packets are injected in it from then on.
+    */
+    pub fn protocol_handle(&self) -> Arc<Mutex<Option<ProtocolVersion>>> {
+        self.protocol.clone()
+    }
+
+    /*
+        the ping of the config packet changes with the config, and is renewed alone by the rtt probe.
+    */
+    pub fn config_due(&mut self, now: Instant, config: AirConfig, ping: u8) -> bool {
+        if self.last_config != Some(config) {
+            self.burst_left = self.config.burst_count;
+            return true;
+        }
+        self.last_ping != Some(ping) || now >= self.next_config_time
+    }
+
+    pub fn config_sent(&mut self, now: Instant, config: AirConfig, ping: u8, pending: bool) {
+        self.last_config = Some(config);
+        self.last_ping = Some(ping);
+        self.next_config_time = now
+            + if self.burst_left > 0 {
+                self.burst_left -= 1;
+                self.config.burst_interval
+            } else if pending {
+                self.config.retry_interval
+            } else {
+                self.config.keepalive
+            };
+        self.stats.lock().unwrap().config_packets += 1;
+    }
+
+    pub fn data_allowed(&self, now: Instant) -> bool {
+        now >= self.next_data_time
+    }
+
+    pub fn data_sent(&mut self, now: Instant) {
+        if self.config.max_data_rate > 0 {
+            self.next_data_time = now + Duration::from_secs(1) / self.config.max_data_rate;
+        }
+        self.stats.lock().unwrap().data_packets += 1;
+    }
+
+    pub fn next_config_time(&self) -> Instant {
+        self.next_config_time
+    }
+
+    pub fn next_data_time(&self) -> Instant {
+        self.next_data_time
+    }
+
+    /*
+        the inject loop, it never returns.
+        send injects one wifi frame, the errors are counted and the packet is dropped.
+    */
+    pub fn run<F, E>(
+        mut self,
+        mut inject_handler: InjectHandler,
+        tracker: Arc<Mutex<ConfigTracker>>,
+        queue: UplinkQueue,
+        mut send: F,
+    ) where
+        F: FnMut(&[u8]) -> Result<(), E>,
+        E: Display,
+    {
+        loop {
+            let now = Instant::now();
+            if let Some(protocol) = *self.protocol.lock().unwrap() {
+                inject_handler.set_protocol(protocol);
+            }
+            let mut locked_tracker = tracker.lock().unwrap();
+            locked_tracker.probe(now);
+            let config = locked_tracker.packet();
+            let pending = locked_tracker.is_pending(now);
+            let requested = locked_tracker.requested();
+            drop(locked_tracker);
+
+            let packets = if self.config_due(now, requested, config.ping) {
+                if self.last_config != Some(requested) {
+                    self.events.publish(Event::ConfigSent {
+                        ping: config.ping,
+                        config: requested,
+                    });
+                }
+                self.config_sent(now, requested, config.ping, pending);
+                let packets = inject_handler.push_ground2air_config_packet(&config);
+                // the rtt is measured from here, the packets are sent right below
+                tracker.lock().unwrap().ping_sent(config.ping, now);
+                packets
+            } else {
+                let mut deadline = self.next_config_time;
+                if let Some(flush_deadline) = inject_handler.flush_deadline() {
+                    deadline = deadline.min(flush_deadline);
+                }
+                if self.data_allowed(now) {
+                    match queue.pop_timeout(deadline.saturating_duration_since(now)) {
+                        Some(payload) => {
+                            self.data_sent(Instant::now());
+                            inject_handler
+                                .push_ground2air_data_packet(&payload)
+                                .unwrap_or_default() // the size is checked by UplinkQueue
+                        }
+                        None => inject_handler.poll(Instant::now()),
+                    }
+                } else {
+                    queue.wait_wake(deadline.min(self.next_data_time).saturating_duration_since(now));
+                    inject_handler.poll(Instant::now())
+                }
+            };
+
+            for packet in packets {
+                let ret = send(&packet);
+                let mut stats = self.stats.lock().unwrap();
+                match ret {
+                    Ok(()) => stats.injected_frames += 1,
+                    Err(e) => {
+                        stats.send_errors += 1;
+                        crate::warn_limited!("inject failed:{}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod unittest {
+        use std::time::{Duration, Instant};
+
+        use crate::{
+            air_config::AirConfig,
+            config_tracker::ConfigTracker,
+            inject::GROUND2AIR_DATA_PAYLOAD_MAX_SIZE,
+            uplink::{SchedulerConfig, UplinkPriority, UplinkQueue, UplinkScheduler},
+        };
+
+        #[test]
+        fn test_priority() {
+            let queue = UplinkQueue::new(2);
+            queue.push(UplinkPriority::Normal, &[1]).unwrap();
+            queue.push(UplinkPriority::Normal, &[2]).unwrap();
+            queue.push(UplinkPriority::Normal, &[3]).unwrap();
+            queue.push(UplinkPriority::High, &[4]).unwrap();
+            assert!(queue
+                .push(UplinkPriority::High, &[0; GROUND2AIR_DATA_PAYLOAD_MAX_SIZE + 1])
+                .is_err());
+
+            let timeout = Duration::from_millis(1);
+            assert_eq!(queue.pop_timeout(timeout), Some(vec![4]));
+            assert_eq!(queue.pop_timeout(timeout), Some(vec![2]));
+            assert_eq!(queue.pop_timeout(timeout), Some(vec![3]));
+            assert_eq!(queue.pop_timeout(timeout), None);
+
+            let stats = queue.stats();
+            assert_eq!((stats.pushed, stats.sent, stats.dropped), (4, 3, 1));
+        }
+
+        #[test]
+        fn test_wake() {
+            let queue = UplinkQueue::new(2);
+            queue.wake();
+            assert_eq!(queue.pop_timeout(Duration::from_secs(1)), None);
+            queue.push(UplinkPriority::Normal, &[1]).unwrap();
+            assert_eq!(queue.pop_timeout(Duration::from_secs(1)), Some(vec![1]));
+        }
+
+        #[test]
+        fn test_scheduler() {
+            let config = SchedulerConfig::default();
+            let mut scheduler = UplinkScheduler::new(config);
+            let now = Instant::now();
+            let air = AirConfig::default();
+
+            // a new config is sent at once, with 2 more copies
+            assert!(scheduler.config_due(now, air, 1));
+            scheduler.config_sent(now, air, 1, true);
+            for _ in 0..2 {
+                assert!(!scheduler.config_due(now, air, 1));
+                assert_eq!(scheduler.next_config_time(), now + config.burst_interval);
+                scheduler.config_sent(now, air, 1, true);
+            }
+            // then retry until confirmed, then keepalive
+            assert_eq!(scheduler.next_config_time(), now + config.retry_interval);
+            scheduler.config_sent(now, air, 1, false);
+            assert_eq!(scheduler.next_config_time(), now + config.keepalive);
+
+            // changed config is sent without waiting for the keepalive
+            let changed = air.to_builder().fec(2, 3).build().unwrap();
+            assert!(scheduler.config_due(now, changed, 2));
+
+            assert!(scheduler.data_allowed(now));
+            scheduler.data_sent(now);
+            assert!(!scheduler.data_allowed(now));
+            assert!(scheduler.data_allowed(now + Duration::from_millis(10)));
+
+            let stats = *scheduler.stats_handle().lock().unwrap();
+            assert_eq!((stats.config_packets, stats.data_packets), (4, 1));
+        }
+
+        #[test]
+        fn test_probe_without_burst() {
+            let config = SchedulerConfig::default();
+            let mut scheduler = UplinkScheduler::new(config);
+            let mut tracker = ConfigTracker::new(AirConfig::default(), Duration::from_secs(3));
+            tracker.observe_frame(None, Some(tracker.packet().ping));
+            let start = Instant::now();
+            scheduler.config_sent(start, tracker.requested(), tracker.packet().ping, false);
+
+            // the same config over several probe intervals
+            let mut sent = Vec::new();
+            let mut now = start;
+            while now < start + ConfigTracker::PROBE_INTERVAL * 5 {
+                tracker.probe(now);
+                let packet = tracker.packet();
+                if scheduler.config_due(now, tracker.requested(), packet.ping) {
+                    scheduler.config_sent(now, tracker.requested(), packet.ping, false);
+                    sent.push(now);
+                }
+                now += Duration::from_millis(5);
+            }
+            // the keepalives and one packet for each renewed ping
+            assert!(sent.len() <= 20 + 5);
+            assert!(sent.windows(2).all(|x| x[1] - x[0] > config.burst_interval));
+        }
+    }
+}